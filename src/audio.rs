@@ -1,27 +1,275 @@
 extern crate ffmpeg_next as ffmpeg;
 
-use std::pin::Pin;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use bytemuck::Pod;
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::SizedSample;
+use cpal::{FromSample, SizedSample};
 
 use futures::future::OptionFuture;
 use futures::FutureExt;
 use ringbuf::ring_buffer::{RbRef, RbWrite};
 use ringbuf::HeapRb;
-use std::future::Future;
 
-use crate::player::ControlCommand;
+use crate::clock::PlaybackClock;
+use crate::filter::{frame_channel_layout, AudioFilter, FilterSpec};
+use crate::loudness::{LoudnessGain, LoudnessProcessor, MAX_CHANNELS};
+use crate::player::{ControlCommand, PacketMessage};
+use crate::stats::{AudioStreamInfo, PlaybackStats};
+use crate::visualization::AudioTap;
+
+/// 没有可用的输出设备时重新尝试打开的间隔
+const OUTPUT_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+/// 环形缓冲区的默认时长
+pub const DEFAULT_BUFFER_DURATION: Duration = Duration::from_millis(200);
+/// 等待 cpal 回调腾出空间的最长时间，超时后检查设备是否出错或需要切换
+const SPACE_WAIT_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// 播放器和音频线程共享的音量、播放速度、音频滤镜链、响度和输出设备设置，浮点数以 f32 的
+/// 位模式存放在原子量里（NaN 表示没有值），切换音轨重建音频线程时沿用同一份设置
+pub struct AudioSettings {
+    volume: AtomicU32,
+    speed: AtomicU32,
+    filter: FilterSpec,
+    /// 输出设备名，None 表示系统默认设备；每次修改 generation 加一，音频线程据此重建输出流
+    device: Mutex<Option<String>>,
+    device_generation: AtomicU64,
+    /// 环形缓冲区时长（微秒）和 cpal 设备缓冲区帧数（0 表示后端默认值），重建输出流时生效
+    buffer_duration_us: AtomicU64,
+    device_buffer_frames: AtomicU32,
+    /// 当前音轨的 ReplayGain 增益（dB），优先于响度标准化
+    replaygain: AtomicU32,
+    /// 响度标准化的目标响度（LUFS）
+    normalize_target: AtomicU32,
+    /// 音频回调测得的整体响度（LUFS）和实际应用的增益（dB）
+    integrated_loudness: AtomicU32,
+    loudness_gain: AtomicU32,
+    /// 重采样后的采样分接，供音频可视化使用
+    tap: AudioTap,
+}
+
+impl AudioSettings {
+    pub fn new(filter: &str) -> Self {
+        Self {
+            volume: AtomicU32::new(1.0f32.to_bits()),
+            speed: AtomicU32::new(1.0f32.to_bits()),
+            filter: FilterSpec::new(filter),
+            device: Mutex::new(None),
+            device_generation: AtomicU64::new(0),
+            buffer_duration_us: AtomicU64::new(DEFAULT_BUFFER_DURATION.as_micros() as u64),
+            device_buffer_frames: AtomicU32::new(0),
+            replaygain: AtomicU32::new(f32::NAN.to_bits()),
+            normalize_target: AtomicU32::new(f32::NAN.to_bits()),
+            integrated_loudness: AtomicU32::new(f32::NAN.to_bits()),
+            loudness_gain: AtomicU32::new(0.0f32.to_bits()),
+            tap: AudioTap::new(),
+        }
+    }
+
+    /// 线性增益，1.0 为原始音量
+    pub fn volume(&self) -> f32 {
+        f32::from_bits(self.volume.load(Ordering::Relaxed))
+    }
+
+    pub fn set_volume(&self, volume: f32) {
+        self.volume.store(volume.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn speed(&self) -> f32 {
+        f32::from_bits(self.speed.load(Ordering::Relaxed))
+    }
+
+    pub fn set_speed(&self, speed: f32) {
+        self.speed.store(speed.to_bits(), Ordering::Relaxed);
+    }
+
+    /// 解码和重采样之间的 libavfilter 滤镜链
+    pub fn filter(&self) -> &FilterSpec {
+        &self.filter
+    }
+
+    pub fn device(&self) -> Option<String> {
+        self.device.lock().unwrap().clone()
+    }
+
+    pub fn set_device(&self, device: Option<String>) {
+        *self.device.lock().unwrap() = device;
+        self.device_generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn device_generation(&self) -> u64 {
+        self.device_generation.load(Ordering::SeqCst)
+    }
+
+    pub fn buffer_duration(&self) -> Duration {
+        Duration::from_micros(self.buffer_duration_us.load(Ordering::Relaxed))
+    }
+
+    pub fn set_buffer_duration(&self, duration: Duration) {
+        self.buffer_duration_us
+            .store(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// cpal 设备缓冲区的帧数，None 表示使用后端默认值
+    pub fn device_buffer_frames(&self) -> Option<u32> {
+        Some(self.device_buffer_frames.load(Ordering::Relaxed)).filter(|&frames| frames > 0)
+    }
+
+    pub fn set_device_buffer_frames(&self, frames: Option<u32>) {
+        self.device_buffer_frames
+            .store(frames.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    pub fn replaygain(&self) -> Option<f32> {
+        load_optional(&self.replaygain)
+    }
+
+    pub fn set_replaygain(&self, gain: Option<f32>) {
+        store_optional(&self.replaygain, gain);
+    }
+
+    pub fn normalize_target(&self) -> Option<f32> {
+        load_optional(&self.normalize_target)
+    }
+
+    pub fn set_normalize_target(&self, target: Option<f32>) {
+        store_optional(&self.normalize_target, target);
+    }
+
+    pub fn integrated_loudness(&self) -> Option<f32> {
+        load_optional(&self.integrated_loudness)
+    }
+
+    pub fn loudness_gain(&self) -> f32 {
+        f32::from_bits(self.loudness_gain.load(Ordering::Relaxed))
+    }
+
+    pub fn tap(&self) -> &AudioTap {
+        &self.tap
+    }
+
+    fn set_loudness(&self, integrated: Option<f32>, gain: f32) {
+        store_optional(&self.integrated_loudness, integrated);
+        self.loudness_gain.store(gain.to_bits(), Ordering::Relaxed);
+    }
+
+    /// 音频回调使用的增益来源：有 ReplayGain 标签时用标签，否则按设置做响度标准化
+    fn loudness_source(&self) -> LoudnessGain {
+        match (self.replaygain(), self.normalize_target()) {
+            (Some(gain), _) => LoudnessGain::Fixed(gain),
+            (None, Some(target)) => LoudnessGain::Normalize(target),
+            (None, None) => LoudnessGain::Fixed(0.0),
+        }
+    }
+}
+
+fn load_optional(value: &AtomicU32) -> Option<f32> {
+    Some(f32::from_bits(value.load(Ordering::Relaxed))).filter(|value| !value.is_nan())
+}
+
+fn store_optional(value: &AtomicU32, new: Option<f32>) {
+    value.store(new.unwrap_or(f32::NAN).to_bits(), Ordering::Relaxed);
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self::new("")
+    }
+}
+
+/// 一个音频输出设备的描述，name 用于在配置和命令行中选择设备
+#[derive(Clone, Debug)]
+pub struct AudioDeviceInfo {
+    pub host: String,
+    pub name: String,
+    /// 是否为该音频后端的默认输出设备
+    pub default: bool,
+}
+
+impl std::fmt::Display for AudioDeviceInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.host, self.name)?;
+        if self.default {
+            write!(f, " (默认)")?;
+        }
+        Ok(())
+    }
+}
+
+/// 列出所有可用音频后端（cpal host）的输出设备
+pub fn output_devices() -> Vec<AudioDeviceInfo> {
+    let mut devices = Vec::new();
+    for host_id in cpal::available_hosts() {
+        let Ok(host) = cpal::host_from_id(host_id) else {
+            continue;
+        };
+        let default_name = host.default_output_device().and_then(|device| device.name().ok());
+        let Ok(host_devices) = host.output_devices() else {
+            continue;
+        };
+        for device in host_devices {
+            let Ok(name) = device.name() else {
+                continue;
+            };
+            devices.push(AudioDeviceInfo {
+                host: host_id.name().to_string(),
+                default: default_name.as_deref() == Some(name.as_str()),
+                name,
+            });
+        }
+    }
+    devices
+}
+
+/// 按名称查找输出设备，名称可以是设备名或“后端/设备名”；
+/// 找不到指定设备时退回默认设备，设备拔出后也因此能继续播放
+fn find_output_device(name: Option<&str>) -> Result<cpal::Device, anyhow::Error> {
+    if let Some(name) = name {
+        for host_id in cpal::available_hosts() {
+            let Ok(host) = cpal::host_from_id(host_id) else {
+                continue;
+            };
+            let Ok(devices) = host.output_devices() else {
+                continue;
+            };
+            for device in devices {
+                let Ok(device_name) = device.name() else {
+                    continue;
+                };
+                if device_name == name || format!("{}/{}", host_id.name(), device_name) == name {
+                    return Ok(device);
+                }
+            }
+        }
+        tracing::warn!("找不到音频输出设备 {}，使用默认设备", name);
+    }
+    cpal::default_host()
+        .default_output_device()
+        .ok_or_else(|| anyhow::anyhow!("没有可用的音频输出设备"))
+}
 
 pub struct AudioPlaybackThread {
     control_sender: smol::channel::Sender<ControlCommand>,
-    packet_sender: smol::channel::Sender<ffmpeg::codec::packet::packet::Packet>,
+    packet_sender: smol::channel::Sender<PacketMessage>,
     receiver_thread: Option<std::thread::JoinHandle<()>>,
+    stats: Arc<PlaybackStats>,
 }
 
 impl AudioPlaybackThread {
-    pub fn start(stream: &ffmpeg::format::stream::Stream) -> Result<Self, anyhow::Error> {
+    /// start_paused 为 true 时照常解码并填满缓冲区，但在收到 Play 之前不输出声音，
+    /// 用于提前打开播放列表的下一项；解码结束且缓冲区播放完后把 finished 置为 true
+    pub fn start(
+        stream: &ffmpeg::format::stream::Stream,
+        start_paused: bool,
+        finished: Arc<AtomicBool>,
+        stats: Arc<PlaybackStats>,
+        settings: Arc<AudioSettings>,
+        clock: PlaybackClock,
+    ) -> Result<Self, anyhow::Error> {
         tracing::info!("音频线程启动 - 流信息: {}", stream.duration());
 
         let (control_sender, control_receiver) = smol::channel::unbounded();
@@ -33,60 +281,38 @@ impl AudioPlaybackThread {
 
         tracing::info!("音频解码器初始化完成 - 格式: {:?}", packet_decoder.format());
 
-        let host = cpal::default_host();
-        let device = host
-            .default_output_device()
-            .expect("no output device available");
-        tracing::info!("音频输出设备: {:?}", device.name());
+        let time_base = stream.time_base();
 
-        let config = device.default_output_config().unwrap();
-        tracing::info!(
-            "音频输出配置 - 采样率: {}, 通道: {}, 格式: {:?}",
-            config.sample_rate().0,
-            config.channels(),
-            config.sample_format()
-        );
+        // 输出设备的参数在音频线程打开输出流后填写
+        stats.set_audio_info(AudioStreamInfo {
+            codec: stream.parameters().id().name().to_string(),
+            sample_rate: packet_decoder.rate(),
+            channels: packet_decoder.channels(),
+            ..AudioStreamInfo::default()
+        });
+        let thread_stats = stats.clone();
 
         let receiver_thread = std::thread::Builder::new()
             .name("audio playback thread".into())
             .spawn(move || {
                 smol::block_on(async move {
-                    let output_channel_layout = match config.channels() {
-                        1 => ffmpeg::util::channel_layout::ChannelLayout::MONO,
-                        2 => ffmpeg::util::channel_layout::ChannelLayout::STEREO,
-                        _ => todo!(),
-                    };
-                    tracing::info!("音频输出通道布局: {:?}", output_channel_layout);
-
-                    let mut ffmpeg_to_cpal_forwarder = match config.sample_format() {
-                        cpal::SampleFormat::U8 => {
-                            tracing::info!("使用U8采样格式");
-                            FFmpegToCPalForwarder::new::<u8>(
-                                config,
-                                &device,
-                                packet_receiver,
-                                packet_decoder,
-                                ffmpeg::util::format::sample::Sample::U8(
-                                    ffmpeg::util::format::sample::Type::Packed,
-                                ),
-                                output_channel_layout,
-                            )
-                        }
-                        cpal::SampleFormat::F32 => {
-                            tracing::info!("使用F32采样格式");
-                            FFmpegToCPalForwarder::new::<f32>(
-                                config,
-                                &device,
-                                packet_receiver,
-                                packet_decoder,
-                                ffmpeg::util::format::sample::Sample::F32(
-                                    ffmpeg::util::format::sample::Type::Packed,
-                                ),
-                                output_channel_layout,
-                            )
-                        }
-                        format @ _ => todo!("unsupported cpal output format {:#?}", format),
-                    };
+                    let output = Rc::new(SharedOutput::default());
+                    output.paused.set(start_paused);
+                    if start_paused {
+                        tracing::info!("音频预读，暂不输出");
+                    }
+
+                    let mut ffmpeg_to_cpal_forwarder = FFmpegToCPalForwarder::new(
+                        output.clone(),
+                        packet_receiver,
+                        packet_decoder,
+                        time_base,
+                        finished,
+                        thread_stats,
+                        settings,
+                        clock,
+                    );
+                    let segment_end = ffmpeg_to_cpal_forwarder.segment_end();
 
                     let packet_receiver_impl = async { ffmpeg_to_cpal_forwarder.stream().await }
                         .fuse()
@@ -113,10 +339,18 @@ impl AudioPlaybackThread {
                                     Ok(ControlCommand::Pause) => {
                                         tracing::info!("音频播放暂停");
                                         playing = false;
+                                        output.pause();
                                     }
                                     Ok(ControlCommand::Play) => {
                                         tracing::info!("音频播放开始");
                                         playing = true;
+                                        output.play();
+                                    }
+                                    Ok(ControlCommand::SetLoop(mode)) => {
+                                        segment_end.set(mode.segment_end());
+                                    }
+                                    Ok(command) => {
+                                        tracing::debug!("音频线程忽略控制命令: {:?}", command);
                                     }
                                     Err(e) => {
                                         tracing::error!("音频控制通道关闭 {}",e);
@@ -133,13 +367,21 @@ impl AudioPlaybackThread {
             control_sender,
             packet_sender,
             receiver_thread: Some(receiver_thread),
+            stats,
         })
     }
 
     pub async fn receive_packet(&self, packet: ffmpeg::codec::packet::packet::Packet) -> bool {
-        match self.packet_sender.send(packet).await {
+        self.send_packet_message(PacketMessage::Packet(packet))
+            .await
+    }
+
+    pub async fn send_packet_message(&self, message: PacketMessage) -> bool {
+        match self.packet_sender.send(message).await {
             Ok(_) => {
                 tracing::debug!("音频包发送成功");
+                self.stats
+                    .set_audio_packets_queued(self.packet_sender.len());
                 true
             }
             Err(e) => {
@@ -168,124 +410,767 @@ impl Drop for AudioPlaybackThread {
 }
 
 trait FFMpegToCPalSampleForwarder {
-    fn forward(
-        &mut self,
-        audio_frame: ffmpeg::frame::Audio,
-    ) -> Pin<Box<dyn Future<Output = ()> + '_>>;
+    /// 把一帧重采样后的音频从第 written 个采样开始尽量写入环形缓冲区，返回写完后的位置；
+    /// 帧比缓冲区大时分多次写入
+    fn forward(&mut self, audio_frame: &ffmpeg::frame::Audio, written: usize) -> usize;
+
+    /// 环形缓冲区中尚未被 cpal 取走的采样数
+    fn buffered(&self) -> usize;
+
+    fn capacity(&self) -> usize;
 }
 
 impl<T: Pod, R: RbRef> FFMpegToCPalSampleForwarder for ringbuf::Producer<T, R>
 where
     <R as RbRef>::Rb: RbWrite<T>,
 {
-    fn forward(
-        &mut self,
-        audio_frame: ffmpeg::frame::Audio,
-    ) -> Pin<Box<dyn Future<Output = ()> + '_>> {
+    fn forward(&mut self, audio_frame: &ffmpeg::frame::Audio, written: usize) -> usize {
         tracing::debug!(
             "转发音频帧 - 采样数: {}, 通道数: {}, 格式: {:?}",
             audio_frame.samples(),
             audio_frame.channels(),
             audio_frame.format()
         );
-        Box::pin(async move {
-            // Audio::plane() returns the wrong slice size, so correct it by hand. See also
-            // for a fix https://github.com/zmwangx/rust-ffmpeg/pull/104.
-            let expected_bytes =
-                audio_frame.samples() * audio_frame.channels() as usize * core::mem::size_of::<T>();
-            let cpal_sample_data: &[T] =
-                bytemuck::cast_slice(&audio_frame.data(0)[..expected_bytes]);
-
-            while self.free_len() < cpal_sample_data.len() {
-                smol::Timer::after(std::time::Duration::from_millis(16)).await;
+        // Audio::plane() returns the wrong slice size, so correct it by hand. See also
+        // for a fix https://github.com/zmwangx/rust-ffmpeg/pull/104.
+        let expected_bytes =
+            audio_frame.samples() * audio_frame.channels() as usize * core::mem::size_of::<T>();
+        let cpal_sample_data: &[T] = bytemuck::cast_slice(&audio_frame.data(0)[..expected_bytes]);
+
+        // 只写入整帧，保证 cpal 回调每次都从第一个声道开始取数据
+        let channels = (audio_frame.channels() as usize).max(1);
+        let remaining = &cpal_sample_data[written.min(cpal_sample_data.len())..];
+        let count = remaining.len().min(self.free_len() / channels * channels);
+
+        // Buffer the samples for playback
+        written + self.push_slice(&remaining[..count])
+    }
+
+    fn buffered(&self) -> usize {
+        self.len()
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity()
+    }
+}
+
+/// 重采样器输出端的参数，由输出设备决定
+#[derive(Clone, Copy, PartialEq)]
+struct OutputParams {
+    format: ffmpeg::util::format::sample::Sample,
+    channel_layout: ffmpeg::util::channel_layout::ChannelLayout,
+    rate: u32,
+}
+
+/// 一个打开的 cpal 输出流和写入它的环形缓冲区
+struct AudioOutput {
+    stream: cpal::Stream,
+    pipe: Box<dyn FFMpegToCPalSampleForwarder>,
+    params: OutputParams,
+    /// cpal 报告错误（通常是设备被拔出）后置为 true，转发器随后重建输出流
+    failed: Arc<AtomicBool>,
+    /// cpal 回调每次取走采样后发出通知，转发器据此等待缓冲区腾出空间
+    space_receiver: smol::channel::Receiver<()>,
+    /// 回调报告的设备延迟：从回调写入数据到声卡实际播出的时间（微秒）
+    device_latency_us: Arc<AtomicU64>,
+    /// 回调开始时要丢弃的采样数，跳转后丢掉缓冲区中跳转前的声音
+    discard: Arc<AtomicUsize>,
+    /// 输入结束后缓冲区自然放空，此时的欠载不计数
+    draining: Arc<AtomicBool>,
+}
+
+impl AudioOutput {
+    fn open(
+        device_name: Option<&str>,
+        settings: &Arc<AudioSettings>,
+        stats: &Arc<PlaybackStats>,
+    ) -> Result<Self, anyhow::Error> {
+        let device = find_output_device(device_name)?;
+        let name = device.name().unwrap_or_else(|_| String::from("未知设备"));
+        let supported = device.default_output_config()?;
+        tracing::info!(
+            "音频输出设备: {}，采样率: {}, 通道: {}, 格式: {:?}，设备缓冲区范围: {:?}",
+            name,
+            supported.sample_rate().0,
+            supported.channels(),
+            supported.sample_format(),
+            supported.buffer_size()
+        );
+
+        let channels = supported.channels() as usize;
+        if channels == 0 || channels > MAX_CHANNELS {
+            anyhow::bail!("不支持 {} 声道的输出设备", channels);
+        }
+        let channel_layout = match channels {
+            1 => ffmpeg::util::channel_layout::ChannelLayout::MONO,
+            2 => ffmpeg::util::channel_layout::ChannelLayout::STEREO,
+            channels => ffmpeg::util::channel_layout::ChannelLayout::default(channels as i32),
+        };
+        tracing::info!("音频输出通道布局: {:?}", channel_layout);
+
+        let mut config = supported.config();
+        if let Some(frames) = settings.device_buffer_frames() {
+            let frames = match supported.buffer_size() {
+                cpal::SupportedBufferSize::Range { min, max } => frames.clamp(*min, *max),
+                cpal::SupportedBufferSize::Unknown => frames,
+            };
+            tracing::info!("音频设备缓冲区: {} 帧", frames);
+            config.buffer_size = cpal::BufferSize::Fixed(frames);
+        }
+
+        let output = match supported.sample_format() {
+            cpal::SampleFormat::U8 => {
+                tracing::info!("使用U8采样格式");
+                Self::build::<u8>(
+                    &device,
+                    &config,
+                    ffmpeg::util::format::sample::Sample::U8(
+                        ffmpeg::util::format::sample::Type::Packed,
+                    ),
+                    channel_layout,
+                    settings,
+                    stats,
+                )
+            }
+            cpal::SampleFormat::F32 => {
+                tracing::info!("使用F32采样格式");
+                Self::build::<f32>(
+                    &device,
+                    &config,
+                    ffmpeg::util::format::sample::Sample::F32(
+                        ffmpeg::util::format::sample::Type::Packed,
+                    ),
+                    channel_layout,
+                    settings,
+                    stats,
+                )
+            }
+            format => anyhow::bail!("不支持的输出采样格式 {:?}", format),
+        }?;
+        stats.set_audio_output(name, output.params.rate, channels as u16);
+        Ok(output)
+    }
+
+    fn build<T: Send + Pod + SizedSample + FromSample<f32> + 'static>(
+        device: &cpal::Device,
+        config: &cpal::StreamConfig,
+        format: ffmpeg::util::format::sample::Sample,
+        channel_layout: ffmpeg::util::channel_layout::ChannelLayout,
+        settings: &Arc<AudioSettings>,
+        stats: &Arc<PlaybackStats>,
+    ) -> Result<Self, anyhow::Error>
+    where
+        f32: FromSample<T>,
+    {
+        let channels = config.channels as usize;
+        let rate = config.sample_rate.0;
+        // 缓冲区至少能放下设备一次回调取走的数据，按整帧对齐
+        let device_frames = match config.buffer_size {
+            cpal::BufferSize::Fixed(frames) => frames as usize,
+            cpal::BufferSize::Default => 0,
+        };
+        let frames = ((settings.buffer_duration().as_secs_f64() * rate as f64) as usize)
+            .max(device_frames * 2)
+            .max(1024);
+        tracing::info!(
+            "音频环形缓冲区: {} 帧（{:.0} ms）",
+            frames,
+            frames as f64 / rate as f64 * 1000.0
+        );
+        let buffer = HeapRb::new(frames * channels);
+        let (sample_producer, mut sample_consumer) = buffer.split();
+
+        // 新的输出流从头开始测量响度
+        settings.set_loudness(None, 0.0);
+        let callback_settings = settings.clone();
+        let callback_stats = stats.clone();
+        let mut loudness = LoudnessProcessor::new(channels, rate);
+        let failed = Arc::new(AtomicBool::new(false));
+        let callback_failed = failed.clone();
+        let (space_sender, space_receiver) = smol::channel::bounded(1);
+        let error_space_sender = space_sender.clone();
+        let device_latency_us = Arc::new(AtomicU64::new(0));
+        let callback_latency = device_latency_us.clone();
+        let discard = Arc::new(AtomicUsize::new(0));
+        let callback_discard = discard.clone();
+        let draining = Arc::new(AtomicBool::new(false));
+        let callback_draining = draining.clone();
+        // 已经收到过数据且上一次回调没有欠载，只在从正常转为欠载时计数一次
+        let mut flowing = false;
+        let stream = device.build_output_stream(
+            config,
+            move |data: &mut [T], info: &cpal::OutputCallbackInfo| {
+                let discard = callback_discard.swap(0, Ordering::SeqCst);
+                if discard > 0 {
+                    sample_consumer.skip(discard);
+                }
+                let filled = sample_consumer.pop_slice(data);
+                data[filled..].fill(T::EQUILIBRIUM);
+                let _ = space_sender.try_send(());
+
+                if filled < data.len() {
+                    if flowing && !callback_draining.load(Ordering::Relaxed) {
+                        callback_stats.add_underrun();
+                    }
+                    flowing = false;
+                } else {
+                    flowing = true;
+                }
+
+                let timestamp = info.timestamp();
+                if let Some(latency) = timestamp.playback.duration_since(&timestamp.callback) {
+                    callback_latency.store(latency.as_micros() as u64, Ordering::Relaxed);
+                }
+
+                // 缓冲区欠载补的静音不参与响度测量
+                let source = callback_settings.loudness_source();
+                let volume = callback_settings.volume();
+                let mut samples = [0.0f32; MAX_CHANNELS];
+                for frame in data[..filled].chunks_exact_mut(channels) {
+                    for (value, sample) in samples.iter_mut().zip(frame.iter()) {
+                        *value = f32::from_sample(*sample);
+                    }
+                    loudness.process(&mut samples[..channels], source, volume);
+                    for (sample, value) in frame.iter_mut().zip(samples.iter()) {
+                        *sample = T::from_sample(*value);
+                    }
+                }
+                callback_settings.set_loudness(loudness.integrated(), loudness.gain_db());
+            },
+            move |err| {
+                tracing::error!("error feeding audio stream to cpal: {}", err);
+                callback_failed.store(true, Ordering::SeqCst);
+                // 唤醒正在等待空间的转发器，让它尽快重建输出流
+                let _ = error_space_sender.try_send(());
+            },
+            None,
+        )?;
+        stream.play()?;
+
+        Ok(Self {
+            stream,
+            pipe: Box::new(sample_producer),
+            params: OutputParams {
+                format,
+                channel_layout,
+                rate,
+            },
+            failed,
+            space_receiver,
+            device_latency_us,
+            discard,
+            draining,
+        })
+    }
+
+    /// 输出端每秒的采样数（采样率乘以通道数），用于把缓冲区长度换算成时长
+    fn samples_per_second(&self) -> usize {
+        self.params.rate as usize * self.params.channel_layout.channels().max(1) as usize
+    }
+}
+
+/// 控制循环和转发器共享的输出流：控制循环负责暂停和恢复，
+/// 转发器在切换设备或设备出错时重建，新的输出流沿用暂停状态
+#[derive(Default)]
+struct SharedOutput {
+    output: RefCell<Option<AudioOutput>>,
+    paused: Cell<bool>,
+}
+
+impl SharedOutput {
+    fn pause(&self) {
+        self.paused.set(true);
+        if let Some(output) = &*self.output.borrow() {
+            if let Err(e) = output.stream.pause() {
+                tracing::error!("暂停音频输出失败: {}", e);
+            }
+        }
+    }
+
+    fn play(&self) {
+        self.paused.set(false);
+        if let Some(output) = &*self.output.borrow() {
+            if let Err(e) = output.stream.play() {
+                tracing::error!("开始音频输出失败: {}", e);
             }
+        }
+    }
 
-            // Buffer the samples for playback
-            self.push_slice(cpal_sample_data);
+    fn replace(&self, output: Option<AudioOutput>) {
+        if let Some(output) = &output {
+            if self.paused.get() {
+                if let Err(e) = output.stream.pause() {
+                    tracing::error!("暂停音频输出失败: {}", e);
+                }
+            }
+        }
+        *self.output.borrow_mut() = output;
+    }
+
+    fn params(&self) -> Option<OutputParams> {
+        self.output.borrow().as_ref().map(|output| output.params)
+    }
+
+    fn failed(&self) -> bool {
+        self.output
+            .borrow()
+            .as_ref()
+            .is_some_and(|output| output.failed.load(Ordering::SeqCst))
+    }
+
+    /// 从第 written 个采样开始写入一帧，返回写完后的位置，没有输出流时返回 None
+    fn forward(&self, frame: &ffmpeg::util::frame::Audio, written: usize) -> Option<usize> {
+        self.output
+            .borrow_mut()
+            .as_mut()
+            .map(|output| output.pipe.forward(frame, written))
+    }
+
+    /// 等待 cpal 回调取走数据，超时后返回，让调用者检查设备状态
+    async fn wait_for_space(&self) {
+        let receiver = self
+            .output
+            .borrow()
+            .as_ref()
+            .map(|output| output.space_receiver.clone());
+        let timeout = async {
+            smol::Timer::after(SPACE_WAIT_TIMEOUT).await;
+        };
+        match receiver {
+            Some(receiver) => {
+                smol::future::or(
+                    async {
+                        let _ = receiver.recv().await;
+                    },
+                    timeout,
+                )
+                .await
+            }
+            None => timeout.await,
+        }
+    }
+
+    /// 跳转后丢弃缓冲区中尚未播出的旧采样
+    fn discard_buffered(&self) {
+        if let Some(output) = &*self.output.borrow() {
+            output
+                .discard
+                .store(output.pipe.buffered(), Ordering::SeqCst);
+            output.draining.store(false, Ordering::Relaxed);
+        }
+    }
+
+    fn set_draining(&self) {
+        if let Some(output) = &*self.output.borrow() {
+            output.draining.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// 缓冲区中的采样数、缓冲区容量和输出端每秒的采样数，没有输出流时为 None
+    fn buffer(&self) -> Option<(usize, usize, usize)> {
+        self.output.borrow().as_ref().map(|output| {
+            (output.pipe.buffered(), output.pipe.capacity(), output.samples_per_second())
+        })
+    }
+
+    /// 设备延迟，没有输出流时为 None
+    fn device_latency(&self) -> Option<Duration> {
+        self.output.borrow().as_ref().map(|output| {
+            Duration::from_micros(output.device_latency_us.load(Ordering::Relaxed))
         })
     }
 }
 
 struct FFmpegToCPalForwarder {
-    _cpal_stream: cpal::Stream,
-    ffmpeg_to_cpal_pipe: Box<dyn FFMpegToCPalSampleForwarder>,
-    packet_receiver: smol::channel::Receiver<ffmpeg::codec::packet::packet::Packet>,
+    output: Rc<SharedOutput>,
+    /// 当前输出流对应的设备设置版本
+    device_generation: u64,
+    /// 上次尝试打开输出设备的时间，没有可用设备时按间隔重试
+    last_open_attempt: Option<Instant>,
+    packet_receiver: smol::channel::Receiver<PacketMessage>,
     packet_decoder: ffmpeg::decoder::Audio,
-    resampler: ffmpeg::software::resampling::Context,
+    /// 重采样器及其输出参数，输入参数、播放速度或输出设备变化时重建
+    resampler: Option<ffmpeg::software::resampling::Context>,
+    resampler_output: Option<OutputParams>,
+    /// 无法创建重采样器的输入参数（例如滤镜输出了不支持的声道布局），
+    /// 这种帧直接丢弃，输出设备或播放速度变化前不再重试
+    rejected_input: Option<ffmpeg::software::resampling::context::Definition>,
+    settings: Arc<AudioSettings>,
+    /// 当前重采样器对应的播放速度
+    resampler_speed: f32,
+    /// 解码帧 PTS 的时间基
+    time_base: ffmpeg::Rational,
+    /// 当前滤镜图及其对应的滤镜链版本，版本为 None 表示需要重建
+    filter: Option<AudioFilter>,
+    filter_generation: Option<u64>,
+    /// A-B 循环的终点，之后的音频帧直接丢弃
+    segment_end: Rc<Cell<Option<Duration>>>,
+    /// 跳转后丢弃目标位置之前的音频帧
+    skip_until: Option<Duration>,
+    finished: Arc<AtomicBool>,
+    stats: Arc<PlaybackStats>,
+    /// 播放时钟，按设备延迟调整画面的呈现时间
+    clock: PlaybackClock,
+    /// 最近写入缓冲区的音频帧的结束时间
+    last_frame_end: Option<Duration>,
 }
 
 impl FFmpegToCPalForwarder {
-    fn new<T: Send + Pod + SizedSample + 'static>(
-        config: cpal::SupportedStreamConfig,
-        device: &cpal::Device,
-        packet_receiver: smol::channel::Receiver<ffmpeg::codec::packet::packet::Packet>,
+    fn new(
+        output: Rc<SharedOutput>,
+        packet_receiver: smol::channel::Receiver<PacketMessage>,
         packet_decoder: ffmpeg::decoder::Audio,
-        output_format: ffmpeg::util::format::sample::Sample,
-        output_channel_layout: ffmpeg::util::channel_layout::ChannelLayout,
+        time_base: ffmpeg::Rational,
+        finished: Arc<AtomicBool>,
+        stats: Arc<PlaybackStats>,
+        settings: Arc<AudioSettings>,
+        clock: PlaybackClock,
     ) -> Self {
-        let buffer = HeapRb::new(4096);
-        let (sample_producer, mut sample_consumer) = buffer.split();
-
-        let cpal_stream = device
-            .build_output_stream(
-                &config.config(),
-                move |data, _| {
-                    let filled = sample_consumer.pop_slice(data);
-                    data[filled..].fill(T::EQUILIBRIUM);
-                },
-                move |err| {
-                    tracing::error!("error feeding audio stream to cpal: {}", err);
-                },
-                None,
-            )
-            .unwrap();
-
-        cpal_stream.play().unwrap();
-
-        let resampler = ffmpeg::software::resampling::Context::get(
-            packet_decoder.format(),
-            packet_decoder.channel_layout(),
-            packet_decoder.rate(),
-            output_format,
-            output_channel_layout,
-            config.sample_rate().0,
-        )
-        .unwrap();
-
-        Self {
-            _cpal_stream: cpal_stream,
-            ffmpeg_to_cpal_pipe: Box::new(sample_producer),
+        let mut forwarder = Self {
+            output,
+            device_generation: 0,
+            last_open_attempt: None,
             packet_receiver,
             packet_decoder,
-            resampler,
+            resampler: None,
+            resampler_output: None,
+            rejected_input: None,
+            resampler_speed: settings.speed(),
+            settings,
+            time_base,
+            filter: None,
+            filter_generation: None,
+            segment_end: Rc::new(Cell::new(None)),
+            skip_until: None,
+            finished,
+            stats,
+            clock,
+            last_frame_end: None,
+        };
+        forwarder.open_output();
+        forwarder
+    }
+
+    /// 按当前设备设置打开输出流，先关闭旧的输出流，部分后端不允许同一设备同时打开两个流
+    fn open_output(&mut self) {
+        self.device_generation = self.settings.device_generation();
+        self.last_open_attempt = Some(Instant::now());
+        self.output.replace(None);
+        let device = self.settings.device();
+        match AudioOutput::open(device.as_deref(), &self.settings, &self.stats) {
+            Ok(output) => self.output.replace(Some(output)),
+            Err(e) => tracing::error!(
+                "打开音频输出设备失败: {}，{:?} 后重试",
+                e,
+                OUTPUT_RETRY_INTERVAL
+            ),
+        }
+        self.report_buffer();
+    }
+
+    /// 设备设置变化、输出流出错或之前没能打开设备时重建输出流，
+    /// 缓冲区中尚未播出的少量采样随旧输出流丢弃；重建了输出流时返回 true
+    fn update_output(&mut self) -> bool {
+        let switched = self.settings.device_generation() != self.device_generation;
+        let failed = self.output.failed();
+        let retry = self.output.params().is_none()
+            && self
+                .last_open_attempt
+                .is_none_or(|attempt| attempt.elapsed() >= OUTPUT_RETRY_INTERVAL);
+        if !switched && !failed && !retry {
+            return false;
         }
+        if switched {
+            tracing::info!("切换音频输出设备: {:?}", self.settings.device());
+        } else if failed {
+            tracing::warn!("音频输出流出错，重新打开输出设备");
+        }
+        self.open_output();
+        true
+    }
+
+    fn segment_end(&self) -> Rc<Cell<Option<Duration>>> {
+        self.segment_end.clone()
+    }
+
+    /// 变速通过改变重采样的目标采样率实现：按 1/speed 倍的采样数输出，
+    /// 声卡仍按原采样率播放，音调随速度升降
+    fn create_resampler(
+        input: ffmpeg::software::resampling::context::Definition,
+        output: OutputParams,
+        speed: f32,
+    ) -> Result<ffmpeg::software::resampling::Context, ffmpeg::Error> {
+        let rate = ((output.rate as f64 / speed as f64).round() as u32).max(1);
+        ffmpeg::software::resampling::Context::get(
+            input.format,
+            input.channel_layout,
+            input.rate,
+            output.format,
+            output.channel_layout,
+            rate,
+        )
     }
 
     async fn stream(&mut self) {
         tracing::info!("音频播放线程启动");
         loop {
-            let Ok(packet) = self.packet_receiver.recv().await else {
+            let Ok(message) = self.packet_receiver.recv().await else {
                 break;
             };
+            self.stats
+                .set_audio_packets_queued(self.packet_receiver.len());
+
+            match message {
+                PacketMessage::Packet(packet) => {
+                    self.stats.add_bytes(packet.size());
+                    // 损坏的数据包只丢掉这一个，解码器重建或冲刷后照常接着解码
+                    if let Err(e) = self.packet_decoder.send_packet(&packet) {
+                        tracing::error!("发送音频包到解码器失败: {}", e);
+                        continue;
+                    }
+                    self.forward_decoded_frames().await;
+                }
+                PacketMessage::Discontinuity(position) | PacketMessage::Seek(position) => {
+                    tracing::info!("音频跳转到 {:?}", position);
+                    // 用户跳转时丢掉缓冲区里跳转前的声音；循环回到起点时让缓冲的声音照常播完
+                    if matches!(message, PacketMessage::Seek(_)) {
+                        self.output.discard_buffered();
+                    }
+                    self.packet_decoder.flush();
+                    self.settings.tap().clear();
+                    // 滤镜内部缓存的是跳转前的采样，下一帧到来时重建
+                    self.filter = None;
+                    self.filter_generation = None;
+                    self.skip_until = Some(position);
+                    self.last_frame_end = None;
+                    // 播放结束后再跳转时重新开始播放
+                    self.finished.store(false, Ordering::SeqCst);
+                }
+                // 输入结束：冲刷解码器，等缓冲区播放完再报告结束
+                PacketMessage::EndOfStream => {
+                    tracing::info!("音频输入结束，冲刷解码器");
+                    if let Err(e) = self.packet_decoder.send_eof() {
+                        tracing::error!("冲刷音频解码器失败: {}", e);
+                    }
+                    self.forward_decoded_frames().await;
+                    if let Some(filter) = &mut self.filter {
+                        if let Err(e) = filter.flush() {
+                            tracing::error!("冲刷音频滤镜失败: {}", e);
+                        }
+                        self.forward_filtered_frames().await;
+                    }
+                    // 输出流重建后缓冲区为空，不会一直等待已经拔出的设备
+                    self.output.set_draining();
+                    while self.output.buffer().is_some_and(|(buffered, ..)| buffered > 0) {
+                        self.output.wait_for_space().await;
+                        self.update_output();
+                        self.report_buffer();
+                    }
+                    self.report_buffer();
+                    tracing::info!("音频播放结束");
+                    self.finished.store(true, Ordering::SeqCst);
+                }
+            }
+        }
+    }
+
+    async fn forward_decoded_frames(&mut self) {
+        let mut decoded_frame = ffmpeg::util::frame::Audio::empty();
+        while self
+            .packet_decoder
+            .receive_frame(&mut decoded_frame)
+            .is_ok()
+        {
+            tracing::debug!("音频解码完成");
+            let frame = std::mem::replace(&mut decoded_frame, ffmpeg::util::frame::Audio::empty());
+            self.update_filter(&frame);
+            match &mut self.filter {
+                Some(filter) => {
+                    if let Err(e) = filter.push(&frame) {
+                        tracing::error!("音频帧送入滤镜失败: {}", e);
+                    }
+                    self.forward_filtered_frames().await;
+                }
+                None => {
+                    let time_base = self.time_base;
+                    self.forward_frame(frame, time_base).await;
+                }
+            }
+        }
+    }
+
+    /// 滤镜链变化或输入参数变化时重建滤镜图，创建失败时不做处理直接输出
+    fn update_filter(&mut self, frame: &ffmpeg::util::frame::Audio) {
+        let generation = self.settings.filter().generation();
+        let stale = self.filter.as_ref().is_some_and(|filter| !filter.accepts(frame));
+        if self.filter_generation == Some(generation) && !stale {
+            return;
+        }
+        self.filter_generation = Some(generation);
+        let spec = self.settings.filter().get();
+        self.filter = if spec.is_empty() {
+            None
+        } else {
+            AudioFilter::new(&spec, frame, self.time_base)
+                .map_err(|e| tracing::error!("创建音频滤镜 {} 失败: {}", spec, e))
+                .ok()
+        };
+    }
+
+    async fn forward_filtered_frames(&mut self) {
+        loop {
+            let Some(filter) = &mut self.filter else {
+                return;
+            };
+            let time_base = filter.output_time_base();
+            let Some(frame) = filter.pull() else {
+                return;
+            };
+            self.forward_frame(frame, time_base).await;
+        }
+    }
+
+    /// 重采样后写入环形缓冲区，time_base 为帧 PTS 的时间基（滤镜可能改变时间基）
+    async fn forward_frame(&mut self, frame: ffmpeg::util::frame::Audio, time_base: ffmpeg::Rational) {
+        let mut frame_end = None;
+        let frame_duration = Duration::from_secs_f64(frame.samples() as f64 / frame.rate().max(1) as f64);
+        if let Some(pts) = frame.pts() {
+            let pts = Duration::from_secs_f64((pts as f64 * f64::from(time_base)).max(0.0));
+            if self.skip_until.is_some_and(|start| pts < start) {
+                return;
+            }
+            if self.segment_end.get().is_some_and(|end| pts >= end) {
+                return;
+            }
+            frame_end = Some(pts + frame_duration);
+        }
+        self.skip_until = None;
+
+        self.update_output();
+        let speed = self.settings.speed();
+        let Some(output) = self.output.params() else {
+            // 没有可用的输出设备时按实时速度丢弃音频，画面照常播放，设备恢复后声音接上
+            smol::Timer::after(frame_duration.div_f32(speed)).await;
+            return;
+        };
+
+        // 滤镜可能改变采样格式、声道布局和采样率，重采样器按实际输入和当前输出设备重建
+        let input = ffmpeg::software::resampling::context::Definition {
+            format: frame.format(),
+            channel_layout: frame_channel_layout(&frame),
+            rate: frame.rate(),
+        };
+        let stale = match &self.resampler {
+            Some(resampler) => *resampler.input() != input,
+            None => self.rejected_input != Some(input),
+        };
+        if stale || speed != self.resampler_speed || self.resampler_output != Some(output) {
+            tracing::info!(
+                "重建重采样器 - 播放速度: {}, 输入: {:?} {} Hz, 输出: {:?} {} Hz",
+                speed,
+                input.format,
+                input.rate,
+                output.format,
+                output.rate
+            );
+            self.resampler = match Self::create_resampler(input, output, speed) {
+                Ok(resampler) => {
+                    self.rejected_input = None;
+                    Some(resampler)
+                }
+                Err(e) => {
+                    tracing::error!(
+                        "无法重采样 {:?} {} Hz 的音频，丢弃这种格式的帧: {}",
+                        input.format,
+                        input.rate,
+                        e
+                    );
+                    self.rejected_input = Some(input);
+                    None
+                }
+            };
+            self.resampler_output = Some(output);
+            self.resampler_speed = speed;
+        }
+        let Some(resampler) = self.resampler.as_mut() else {
+            return;
+        };
+        let mut resampled_frame = ffmpeg::util::frame::Audio::empty();
+        tracing::debug!("音频重采样");
+        if let Err(e) = resampler.run(&frame, &mut resampled_frame) {
+            tracing::error!("音频重采样失败，丢弃这一帧: {}", e);
+            return;
+        }
+        tracing::debug!("音频重采样完成");
+        // 重采样后每秒媒体时间对应 rate / speed 个采样
+        self.settings.tap().push(
+            &tap_samples(&resampled_frame),
+            resampled_frame.channels() as usize,
+            frame_end,
+            output.rate as f64 / speed as f64,
+        );
+
+        // 缓冲区满时等 cpal 回调取走数据后的通知，帧比剩余空间大时分几次写入
+        let total = resampled_frame.samples() * resampled_frame.channels() as usize;
+        let mut written = 0;
+        while let Some(position) = self.output.forward(&resampled_frame, written) {
+            written = position;
+            if written >= total {
+                break;
+            }
+            self.output.wait_for_space().await;
+            // 等待期间换了输出流时，这一帧的格式可能与新设备不符，直接丢弃
+            if self.update_output() {
+                return;
+            }
+        }
+        tracing::debug!("音频重采样结果发送给CPAL");
+        if frame_end.is_some() {
+            self.last_frame_end = frame_end;
+        }
+        self.report_buffer();
+    }
 
-            self.packet_decoder.send_packet(&packet).unwrap();
-
-            let mut decoded_frame = ffmpeg::util::frame::Audio::empty();
-            while self
-                .packet_decoder
-                .receive_frame(&mut decoded_frame)
-                .is_ok()
-            {
-                tracing::debug!("音频解码完成");
-                let mut resampled_frame = ffmpeg::util::frame::Audio::empty();
-                tracing::debug!("音频重采样");
-                self.resampler
-                    .run(&decoded_frame, &mut resampled_frame)
-                    .unwrap();
-                tracing::debug!("音频重采样完成");
-                self.ffmpeg_to_cpal_pipe.forward(resampled_frame).await;
-                tracing::debug!("音频重采样结果发送给CPAL");
+    /// 更新环形缓冲区填充度和输出延迟，并用最近写入的帧的结束时间减去缓冲区和设备的
+    /// 延迟得到正在播出的位置；设备延迟同步给播放时钟，让画面晚同样的时间呈现，
+    /// 正在播出的位置也交给播放时钟校正漂移
+    fn report_buffer(&self) {
+        let (buffered, capacity, samples_per_second) = self.output.buffer().unwrap_or((0, 0, 1));
+        self.stats.set_ring_buffer(buffered, capacity);
+        let device_latency = self.output.device_latency();
+        if let Some(device_latency) = device_latency {
+            self.clock.set_output_latency(device_latency);
+        }
+        let buffered_time =
+            Duration::from_secs_f64(buffered as f64 / samples_per_second.max(1) as f64);
+        let latency = device_latency.map(|device_latency| buffered_time + device_latency);
+        self.stats.set_output_latency(latency);
+        // 缓冲区按声卡采样率播放，换算成媒体时长时乘以播放速度
+        let latency = latency.unwrap_or_default().mul_f64(self.resampler_speed as f64);
+        let position = self.last_frame_end.map(|end| end.saturating_sub(latency));
+        self.stats.set_audio_position(position);
+        // 不知道设备延迟或者没有在出声时算出的位置不可靠
+        if let (Some(position), Some(_)) = (position, device_latency) {
+            if !self.output.paused.get() {
+                self.clock.correct_drift(position);
             }
         }
     }
 }
+
+/// 把重采样输出的交错采样转换为 f32 交给可视化分接，输出格式只有 U8 和 F32 两种
+fn tap_samples(frame: &ffmpeg::util::frame::Audio) -> Vec<f32> {
+    let count = frame.samples() * frame.channels() as usize;
+    match frame.format() {
+        ffmpeg::util::format::sample::Sample::U8(_) => frame.data(0)[..count]
+            .iter()
+            .map(|&sample| (sample as f32 - 128.0) / 128.0)
+            .collect(),
+        ffmpeg::util::format::sample::Sample::F32(_) => {
+            let bytes = &frame.data(0)[..count * std::mem::size_of::<f32>()];
+            bytemuck::cast_slice::<u8, f32>(bytes).to_vec()
+        }
+        _ => Vec::new(),
+    }
+}
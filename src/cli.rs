@@ -0,0 +1,245 @@
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::Parser;
+
+use crate::config::Config;
+use crate::config_file;
+use crate::deinterlace::{DeinterlaceMode, Deinterlacer};
+use crate::loudness::ReplayGainMode;
+use crate::playlist::RepeatMode;
+use crate::renderer::CropRect;
+use crate::thumbnail::{ImageFormat, ThumbnailSize};
+use crate::visualization::VisualizationMode;
+
+#[derive(Parser, Debug)]
+#[command(version, about = "FFmpeg OpenGL 视频播放器")]
+pub struct Cli {
+    /// 要播放的媒体文件、目录、通配符或 M3U/M3U8/PLS 播放列表
+    pub paths: Vec<PathBuf>,
+
+    /// 配置文件路径，默认为 $XDG_CONFIG_HOME/player/config.toml
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// 不读取配置文件，全部使用默认值和命令行参数
+    #[arg(long, conflicts_with = "config")]
+    pub no_config: bool,
+
+    /// 随机播放
+    #[arg(long)]
+    pub shuffle: bool,
+
+    /// 播放列表循环模式：off、one、all
+    #[arg(long)]
+    pub repeat: Option<RepeatMode>,
+
+    /// 外挂字幕文件（SRT、WebVTT、ASS/SSA），不指定时自动查找视频旁的同名字幕
+    #[arg(long)]
+    pub sub_file: Option<PathBuf>,
+
+    /// 音轨语言偏好，逗号分隔，例如 chi,eng
+    #[arg(long, value_delimiter = ',')]
+    pub alang: Vec<String>,
+
+    /// 字幕轨语言偏好，逗号分隔
+    #[arg(long, value_delimiter = ',')]
+    pub slang: Vec<String>,
+
+    /// 在指定路径监听 Unix 域套接字，接受按行分隔的 JSON 控制命令
+    #[arg(long, value_name = "PATH")]
+    pub input_ipc_server: Option<PathBuf>,
+
+    /// 在指定端口启动 HTTP 遥控服务器和网页遥控器
+    #[arg(long, value_name = "PORT")]
+    pub http_port: Option<u16>,
+
+    /// HTTP 遥控服务器监听的地址，默认只接受本机连接
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1")]
+    pub http_bind: IpAddr,
+
+    /// HTTP 遥控的共享口令，在局域网内遥控时使用
+    #[arg(long, value_name = "TOKEN")]
+    pub http_token: Option<String>,
+
+    /// 以视频像素为单位裁剪画面：WxH+X+Y，例如 1920x800+0+140
+    #[arg(long, value_name = "WxH+X+Y")]
+    pub crop: Option<CropRect>,
+
+    /// 视频滤镜链（libavfilter filtergraph），例如 yadif,hqdn3d,eq=contrast=1.1
+    #[arg(long, value_name = "FILTERS")]
+    pub vf: Option<String>,
+
+    /// 音频滤镜链（libavfilter filtergraph），例如 highpass=f=80,loudnorm
+    #[arg(long, value_name = "FILTERS")]
+    pub af: Option<String>,
+
+    /// 去隔行模式：auto、on、off
+    #[arg(long)]
+    pub deinterlace: Option<DeinterlaceMode>,
+
+    /// 去隔行方式：yadif、bwdif（CPU）或 bob、linear（GPU）
+    #[arg(long)]
+    pub deinterlacer: Option<Deinterlacer>,
+
+    /// 去隔行时按帧率而不是场频输出
+    #[arg(long)]
+    pub no_field_rate: bool,
+
+    /// ReplayGain 模式：track、album、off
+    #[arg(long)]
+    pub replaygain: Option<ReplayGainMode>,
+
+    /// ReplayGain 前置放大（dB）
+    #[arg(long, value_name = "DB", allow_hyphen_values = true)]
+    pub replaygain_preamp: Option<f32>,
+
+    /// 没有 ReplayGain 标签时把响度标准化到目标值（LUFS），例如 -23
+    #[arg(long, value_name = "LUFS", allow_hyphen_values = true)]
+    pub normalize: Option<f32>,
+
+    /// 音频输出设备名，可以写成“后端/设备名”，用 --audio-device-list 查看可用设备
+    #[arg(long, value_name = "NAME")]
+    pub audio_device: Option<String>,
+
+    /// 音频环形缓冲区时长（毫秒），默认 200
+    #[arg(long, value_name = "MS")]
+    pub audio_buffer: Option<u64>,
+
+    /// 音频设备缓冲区大小（帧），默认由音频后端决定
+    #[arg(long, value_name = "FRAMES")]
+    pub audio_device_buffer: Option<u32>,
+
+    /// 音频相对画面的延迟（毫秒），正数表示声音晚于画面，范围 ±1500，文件记住的延迟优先
+    #[arg(long, value_name = "MS", allow_hyphen_values = true)]
+    pub audio_delay: Option<i64>,
+
+    /// 不从上次关闭的位置继续播放，也不记录本次的播放位置
+    #[arg(long)]
+    pub no_resume: bool,
+
+    /// 播放音乐等没有视频画面的媒体时的音频可视化：spectrum、waveform、off
+    #[arg(long, value_name = "MODE")]
+    pub visualization: Option<VisualizationMode>,
+
+    /// 列出所有音频输出设备后退出
+    #[arg(long)]
+    pub audio_device_list: bool,
+
+    /// 不播放，以 JSON 输出每个文件的容器、章节、元数据和流信息后退出
+    #[arg(long, requires = "paths")]
+    pub probe: bool,
+
+    /// 不播放，为每个文件均匀截取 N 张关键帧缩略图后退出
+    #[arg(long, value_name = "N", requires = "paths", conflicts_with = "probe")]
+    pub thumbnails: Option<usize>,
+
+    /// 不播放，为每个文件生成 N 格的缩略图拼图和 WebVTT 索引后退出
+    #[arg(
+        long,
+        value_name = "N",
+        requires = "paths",
+        conflicts_with_all = ["probe", "thumbnails"]
+    )]
+    pub storyboard: Option<usize>,
+
+    /// 缩略图拼图每行的格数
+    #[arg(long, value_name = "N", default_value_t = 10)]
+    pub storyboard_columns: usize,
+
+    /// 缩略图尺寸，例如 160x90；只写宽度或另一边写 0 时保持宽高比
+    #[arg(long, value_name = "WxH", default_value = "160x0")]
+    pub thumbnail_size: ThumbnailSize,
+
+    /// 缩略图的图片格式：png、jpeg
+    #[arg(long, value_name = "FORMAT", default_value = "jpeg")]
+    pub thumbnail_format: ImageFormat,
+
+    /// 缩略图的输出目录
+    #[arg(long, value_name = "DIR", default_value = ".")]
+    pub thumbnail_dir: PathBuf,
+}
+
+impl Cli {
+    /// 依次用配置文件和命令行参数覆盖默认配置，命令行参数优先
+    pub fn into_config(self) -> Result<Config, anyhow::Error> {
+        let mut config = Config::default();
+        // 明确指定的配置文件必须存在，默认位置的配置文件可以没有
+        let config_file = match self.config {
+            Some(path) => Some(path),
+            None if self.no_config => None,
+            None => config_file::default_path().filter(|path| path.exists()),
+        };
+        if let Some(path) = config_file {
+            config_file::apply(&mut config, &path)?;
+        }
+        if !self.paths.is_empty() {
+            config.playlist = self.paths;
+        }
+        config.shuffle |= self.shuffle;
+        if let Some(repeat) = self.repeat {
+            config.repeat = repeat;
+        }
+        if let Some(sub_file) = self.sub_file {
+            config.subtitle_file = Some(sub_file);
+        }
+        if !self.alang.is_empty() {
+            config.preferred_audio_languages = self.alang;
+        }
+        if !self.slang.is_empty() {
+            config.preferred_subtitle_languages = self.slang;
+        }
+        if let Some(socket) = self.input_ipc_server {
+            config.ipc_socket = Some(socket);
+        }
+        if let Some(port) = self.http_port {
+            config.http_address = Some(SocketAddr::new(self.http_bind, port));
+        }
+        if let Some(token) = self.http_token {
+            config.http_token = Some(token);
+        }
+        if let Some(crop) = self.crop {
+            config.crop = Some(crop);
+        }
+        if let Some(vf) = self.vf {
+            config.video_filter = Some(vf);
+        }
+        if let Some(af) = self.af {
+            config.audio_filter = Some(af);
+        }
+        if let Some(deinterlace) = self.deinterlace {
+            config.deinterlace = deinterlace;
+        }
+        if let Some(deinterlacer) = self.deinterlacer {
+            config.deinterlacer = deinterlacer;
+        }
+        config.deinterlace_field_rate &= !self.no_field_rate;
+        if let Some(replaygain) = self.replaygain {
+            config.replaygain = replaygain;
+        }
+        if let Some(preamp) = self.replaygain_preamp {
+            config.replaygain_preamp = preamp;
+        }
+        if let Some(target) = self.normalize {
+            config.normalize_target = Some(target);
+        }
+        if let Some(device) = self.audio_device {
+            config.audio_device = Some(device);
+        }
+        if let Some(buffer) = self.audio_buffer {
+            config.audio_buffer = Duration::from_millis(buffer.max(1));
+        }
+        if let Some(frames) = self.audio_device_buffer {
+            config.audio_device_buffer = Some(frames);
+        }
+        if let Some(delay) = self.audio_delay {
+            config.audio_delay_ms = delay;
+        }
+        config.resume &= !self.no_resume;
+        if let Some(visualization) = self.visualization {
+            config.visualization = visualization;
+        }
+        Ok(config)
+    }
+}
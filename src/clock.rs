@@ -0,0 +1,202 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 输出延迟变化小于该值时不调整时钟，避免画面随延迟测量的抖动来回跳
+const LATENCY_TOLERANCE: Duration = Duration::from_millis(2);
+/// 时钟与实际播出的声音相差超过该值时开始校正，小于它的偏差听不出来
+const DRIFT_THRESHOLD: Duration = Duration::from_millis(30);
+/// 相差超过该值时不是漂移，而是跳转或循环后缓冲区里还留着旧的声音，不校正
+const MAX_DRIFT: Duration = Duration::from_secs(1);
+/// 每次校正挪动偏差的这一比例，让画面逐步追上声音而不是跳一下
+const DRIFT_CORRECTION: f64 = 0.1;
+
+/// 播放时钟：以墙钟为基准推进媒体时间，暂停时冻结。
+/// 解码线程和渲染循环共享同一个时钟来决定帧的交付和呈现时机。
+/// 音频设备的输出延迟从媒体时间中扣除，使画面与实际听到的声音对齐；
+/// 用户设置的音频延迟再把画面相对声音前后挪动，用于修正固定的音画偏差。
+#[derive(Clone)]
+pub struct PlaybackClock {
+    state: Arc<Mutex<ClockState>>,
+}
+
+struct ClockState {
+    /// 最近一次改变速度、跳转或恢复播放时的墙钟时刻
+    anchor: Instant,
+    /// anchor 时刻对应的媒体时间
+    anchor_position: Duration,
+    /// 暂停时冻结的媒体时间，None 表示时钟正在走
+    paused_at: Option<Duration>,
+    /// 播放速度，媒体时间每秒推进 rate 秒
+    rate: f64,
+    /// 每次 set_position 加一
+    generation: u64,
+    /// 音频从交给声卡到实际播出的延迟（墙钟时间）
+    output_latency: Duration,
+    /// 音频相对画面的延迟（毫秒），正数表示声音晚于画面
+    audio_delay_ms: i64,
+}
+
+impl ClockState {
+    /// 未扣除输出延迟的媒体时间，暂停、跳转和变速都以它为基准
+    fn raw_position(&self) -> Duration {
+        match self.paused_at {
+            Some(position) => position,
+            None => self.anchor_position + self.anchor.elapsed().mul_f64(self.rate),
+        }
+    }
+
+    fn position(&self) -> Duration {
+        let position = self.raw_position().as_secs_f64() - self.shift() * self.rate;
+        Duration::from_secs_f64(position.max(0.0))
+    }
+
+    /// 媒体时间相对未扣除延迟的时间的偏移（秒）：
+    /// 声音晚 delay 播出时画面要提前 delay 显示，相当于少扣除这部分延迟
+    fn shift(&self) -> f64 {
+        self.output_latency.as_secs_f64() - self.audio_delay_ms as f64 / 1000.0
+    }
+
+    fn reanchor(&mut self, position: Duration) {
+        self.anchor = Instant::now();
+        self.anchor_position = position;
+    }
+}
+
+impl PlaybackClock {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(ClockState {
+                anchor: Instant::now(),
+                anchor_position: Duration::ZERO,
+                paused_at: None,
+                rate: 1.0,
+                generation: 0,
+                output_latency: Duration::ZERO,
+                audio_delay_ms: 0,
+            })),
+        }
+    }
+
+    /// 当前媒体时间
+    pub fn position(&self) -> Duration {
+        self.state.lock().unwrap().position()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state.lock().unwrap().paused_at.is_some()
+    }
+
+    pub fn pause(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.paused_at.is_none() {
+            state.paused_at = Some(state.raw_position());
+        }
+    }
+
+    /// 跳转后把媒体时间设为 position，暂停状态保持不变
+    pub fn set_position(&self, position: Duration) {
+        let mut state = self.state.lock().unwrap();
+        match state.paused_at {
+            Some(_) => state.paused_at = Some(position),
+            None => state.reanchor(position),
+        }
+        state.generation += 1;
+    }
+
+    /// 暂停期间跳转后，把媒体时间拨到解码出的第一帧，让它立即呈现。
+    /// 时钟没有暂停或者 generation 之后时间线又被拨动过时不做改动，返回 false
+    pub fn set_paused_position(&self, position: Duration, generation: u64) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.paused_at.is_none() || state.generation != generation {
+            return false;
+        }
+        let raw = position.as_secs_f64() + state.shift() * state.rate;
+        state.paused_at = Some(Duration::from_secs_f64(raw.max(0.0)));
+        state.generation += 1;
+        true
+    }
+
+    /// 时间线被拨动的次数，解码线程据此判断手上等待交付的帧是否已经过期
+    pub fn generation(&self) -> u64 {
+        self.state.lock().unwrap().generation
+    }
+
+    pub fn resume(&self) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(position) = state.paused_at.take() {
+            state.reanchor(position);
+        }
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.state.lock().unwrap().rate
+    }
+
+    /// 改变播放速度，从当前位置开始按新速度推进
+    pub fn set_rate(&self, rate: f64) {
+        let mut state = self.state.lock().unwrap();
+        let position = state.raw_position();
+        state.reanchor(position);
+        state.rate = rate;
+    }
+
+    pub fn output_latency(&self) -> Duration {
+        self.state.lock().unwrap().output_latency
+    }
+
+    /// 更新音频输出延迟，之后的媒体时间晚 latency 推进，画面随之等待声音
+    pub fn set_output_latency(&self, latency: Duration) {
+        let mut state = self.state.lock().unwrap();
+        if latency.abs_diff(state.output_latency) >= LATENCY_TOLERANCE {
+            state.output_latency = latency;
+        }
+    }
+
+    /// 用实际播出的音频位置校正时钟。墙钟和声卡时钟之间会慢慢漂移，
+    /// 欠载后声音也会整体落后，不校正的话画面会一直领先声音
+    pub fn correct_drift(&self, audio_position: Duration) {
+        let mut state = self.state.lock().unwrap();
+        if state.paused_at.is_some() {
+            return;
+        }
+        // 不含用户设置的音频延迟，对应此刻应该正在播出的声音
+        let raw = state.raw_position().as_secs_f64();
+        let expected = raw - state.output_latency.as_secs_f64() * state.rate;
+        let drift = audio_position.as_secs_f64() - expected;
+        if drift.abs() < DRIFT_THRESHOLD.as_secs_f64() || drift.abs() > MAX_DRIFT.as_secs_f64() {
+            return;
+        }
+        tracing::debug!("播放时钟偏离声音 {:+.0} ms，校正", drift * 1000.0);
+        let position = raw + drift * DRIFT_CORRECTION;
+        state.reanchor(Duration::from_secs_f64(position.max(0.0)));
+    }
+
+    pub fn audio_delay_ms(&self) -> i64 {
+        self.state.lock().unwrap().audio_delay_ms
+    }
+
+    /// 设置音频相对画面的延迟，画面立即前后挪动，不需要跳转
+    pub fn set_audio_delay_ms(&self, delay_ms: i64) {
+        self.state.lock().unwrap().audio_delay_ms = delay_ms;
+    }
+}
+
+impl Default for PlaybackClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 把媒体时间格式化为 HH:MM:SS.mmm，不足一小时时省略小时
+pub fn format_time(position: Duration) -> String {
+    let millis = position.as_millis();
+    let hours = millis / 3_600_000;
+    let minutes = millis / 60_000 % 60;
+    let seconds = millis / 1000 % 60;
+    let millis = millis % 1000;
+    if hours > 0 {
+        format!("{:02}:{:02}:{:02}.{:03}", hours, minutes, seconds, millis)
+    } else {
+        format!("{:02}:{:02}.{:03}", minutes, seconds, millis)
+    }
+}
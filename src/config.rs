@@ -1,8 +1,20 @@
+use std::net::SocketAddr;
 use std::path::PathBuf;
-use crate::renderer::ScaleMode;
+use std::time::Duration;
+use crate::deinterlace::{DeinterlaceMode, Deinterlacer};
+use crate::keybindings::KeyBindings;
+use crate::loudness::ReplayGainMode;
+use crate::playlist::RepeatMode;
+use crate::renderer::{CropRect, ScaleMode, SubtitleStyle};
+use crate::visualization::VisualizationMode;
 
 pub struct Config {
-    pub video_path: PathBuf,
+    /// 播放列表输入：媒体文件、目录、通配符或 M3U/M3U8/PLS 文件
+    pub playlist: Vec<PathBuf>,
+    /// 随机播放
+    pub shuffle: bool,
+    /// 播放列表循环模式
+    pub repeat: RepeatMode,
     /// 窗口初始宽度，之后的窗口尺寸由用户通过拖拽等操作来控制
     pub window_width: u32,
     /// 窗口初始高度，之后的窗口尺寸由用户通过拖拽等操作来控制
@@ -11,23 +23,128 @@ pub struct Config {
     /// 视频缩放模式：
     /// - Fit: 按原视频比例显示，可能有黑边
     /// - Fill: 按原比例拉伸占满窗口，可能裁剪
+    /// - Stretch: 忽略比例拉伸占满窗口
+    /// - Original: 按视频像素 1:1 显示
+    /// - Aspect: 强制指定宽高比，例如 4:3、16:9、2.35:1
     pub scale_mode: ScaleMode,
+    /// 以视频像素为单位的裁剪区域，None 表示显示完整画面
+    pub crop: Option<CropRect>,
+    /// 启动时是否进入无边框全屏
+    pub fullscreen: bool,
+    /// 窗口是否总在最前
+    pub always_on_top: bool,
+    /// 启动时按视频尺寸的倍数设置窗口大小，None 表示使用 window_width/window_height
+    pub fit_to_video: Option<f64>,
+    /// 退出时保存窗口位置和大小，并在下次启动时恢复
+    pub remember_geometry: bool,
+    /// 截图保存目录
+    pub screenshot_dir: PathBuf,
+    /// 字幕字体文件，None 时使用系统字体
+    pub subtitle_font: Option<PathBuf>,
+    /// 纯文本字幕的字号、颜色和描边
+    pub subtitle_style: SubtitleStyle,
+    /// 外挂字幕文件，优先于内嵌字幕显示，只用于播放列表的第一项
+    pub subtitle_file: Option<PathBuf>,
+    /// 未指定外挂字幕时，自动加载视频旁的同名字幕文件
+    pub auto_load_subtitles: bool,
+    /// 音轨语言偏好，按顺序匹配流的语言标签，例如 ["chi", "eng"]
+    pub preferred_audio_languages: Vec<String>,
+    /// 字幕轨语言偏好，按顺序匹配流的语言标签
+    pub preferred_subtitle_languages: Vec<String>,
+    /// JSON IPC 控制套接字路径，None 表示不启动 IPC 服务器
+    pub ipc_socket: Option<PathBuf>,
+    /// HTTP 遥控服务器监听地址，None 表示不启动
+    pub http_address: Option<SocketAddr>,
+    /// HTTP 遥控的共享口令，设置后 /api/* 请求必须带上 Authorization: Bearer <口令>，
+    /// 遥控网页通过 /?token=<口令> 打开
+    pub http_token: Option<String>,
+    /// libavfilter 视频滤镜链，例如 yadif,hqdn3d,eq=contrast=1.1
+    pub video_filter: Option<String>,
+    /// libavfilter 音频滤镜链，例如 highpass=f=80,equalizer=f=1000:t=q:w=1:g=3,loudnorm
+    pub audio_filter: Option<String>,
+    /// 去隔行模式：auto 只处理标记为隔行的帧
+    pub deinterlace: DeinterlaceMode,
+    /// 去隔行方式：yadif、bwdif 经 libavfilter 在 CPU 上处理，bob、linear 在着色器中处理
+    pub deinterlacer: Deinterlacer,
+    /// 去隔行时按场频输出，25i/30i 显示为 50p/60p
+    pub deinterlace_field_rate: bool,
+    /// ReplayGain 模式：track、album 或 off，读取容器和音频流中的 ReplayGain/R128 标签
+    pub replaygain: ReplayGainMode,
+    /// 叠加在 ReplayGain 增益上的前置放大（dB）
+    pub replaygain_preamp: f32,
+    /// 没有 ReplayGain 标签时实时测量 EBU R128 响度并标准化到的目标响度（LUFS），例如 -23
+    pub normalize_target: Option<f32>,
+    /// 音频输出设备名（设备名或“后端/设备名”），None 表示系统默认设备
+    pub audio_device: Option<String>,
+    /// 音频环形缓冲区时长，越长越不容易欠载，但跳转和切换设备时丢弃的声音越多
+    pub audio_buffer: Duration,
+    /// cpal 设备缓冲区帧数，None 表示使用后端默认值，较小的值延迟更低但更容易欠载
+    pub audio_device_buffer: Option<u32>,
+    /// 音频相对画面的默认延迟（毫秒），正数表示声音晚于画面，用于修正固定的音画偏差
+    pub audio_delay_ms: i64,
+    /// 按文件记住调整过的音频延迟，再次打开同一文件时恢复
+    pub remember_audio_delay: bool,
+    /// 再次打开同一文件时从上次关闭的位置继续播放
+    pub resume: bool,
+    /// 播放只有音频或封面图的媒体时的可视化方式：频谱、波形或关闭
+    pub visualization: VisualizationMode,
+    /// 初始音量，1.0 为原始音量
+    pub volume: f32,
+    /// 视频解码线程数，None 表示使用所有 CPU 核心
+    pub decoder_threads: Option<usize>,
+    /// 按键到操作的映射
+    pub key_bindings: KeyBindings,
 }
 
 impl Config {
-    pub fn new(video_path: PathBuf) -> Self {
+    pub fn new(playlist: Vec<PathBuf>) -> Self {
         Self {
-            video_path,
+            playlist,
+            shuffle: false,
+            repeat: RepeatMode::Off,
             window_width: 800,    // 初始窗口宽度
             window_height: 600,   // 初始窗口高度
             window_title: String::from("视频播放器"),
             scale_mode: ScaleMode::Fill,
+            crop: None,
+            fullscreen: false,
+            always_on_top: false,
+            fit_to_video: None,
+            remember_geometry: true,
+            screenshot_dir: PathBuf::from("."),
+            subtitle_font: None,
+            subtitle_style: SubtitleStyle::default(),
+            subtitle_file: None,
+            auto_load_subtitles: true,
+            preferred_audio_languages: Vec::new(),
+            preferred_subtitle_languages: Vec::new(),
+            ipc_socket: None,
+            http_address: None,
+            http_token: None,
+            video_filter: None,
+            audio_filter: None,
+            deinterlace: DeinterlaceMode::Auto,
+            deinterlacer: Deinterlacer::Yadif,
+            deinterlace_field_rate: true,
+            replaygain: ReplayGainMode::Track,
+            replaygain_preamp: 0.0,
+            normalize_target: None,
+            audio_device: None,
+            audio_buffer: Duration::from_millis(200),
+            audio_device_buffer: None,
+            audio_delay_ms: 0,
+            remember_audio_delay: true,
+            resume: true,
+            visualization: VisualizationMode::Spectrum,
+            volume: 1.0,
+            decoder_threads: None,
+            key_bindings: KeyBindings::default(),
         }
     }
 }
 
 impl Default for Config {
     fn default() -> Self {
-        Self::new(PathBuf::from("/Users/chinaxxren/Desktop/a.mp4"))
+        Self::new(vec![PathBuf::from("/Users/chinaxxren/Desktop/a.mp4")])
     }
 }
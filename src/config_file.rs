@@ -0,0 +1,419 @@
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use toml::{Table, Value};
+
+use crate::config::Config;
+use crate::keybindings::{Action, KeyChord};
+use crate::paths;
+
+const CONFIG_FILE_NAME: &str = "config.toml";
+/// 音量上限（百分比），与播放器的音量范围一致
+const MAX_VOLUME_PERCENT: f64 = 200.0;
+
+/// 默认配置文件：`$XDG_CONFIG_HOME/player/config.toml`
+pub fn default_path() -> Option<PathBuf> {
+    paths::config_dir().map(|dir| dir.join(CONFIG_FILE_NAME))
+}
+
+/// 读取配置文件，用其中出现的配置项覆盖 config，没有出现的保持默认值。
+/// 类型不对、取值无效或无法识别的配置项会返回指明该项的错误
+pub fn apply(config: &mut Config, path: &Path) -> Result<(), anyhow::Error> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("读取配置文件 {} 失败: {}", path.display(), e))?;
+    apply_str(config, &content, &path.display().to_string())
+}
+
+/// 解析配置文件内容，file 只用于错误信息
+fn apply_str(config: &mut Config, content: &str, file: &str) -> Result<(), anyhow::Error> {
+    let mut root: Table = content
+        .parse()
+        .map_err(|e| anyhow::anyhow!("配置文件 {} 格式错误: {}", file, e))?;
+    let file = file.to_string();
+
+    let mut window = Section::take(&mut root, &file, "window")?;
+    if let Some(width) = window.get("width", positive::<u32>)? {
+        config.window_width = width;
+    }
+    if let Some(height) = window.get("height", positive::<u32>)? {
+        config.window_height = height;
+    }
+    if let Some(title) = window.get("title", string)? {
+        config.window_title = title;
+    }
+    if let Some(scale_mode) = window.get("scale_mode", parsed)? {
+        config.scale_mode = scale_mode;
+    }
+    if let Some(fullscreen) = window.get("fullscreen", boolean)? {
+        config.fullscreen = fullscreen;
+    }
+    if let Some(always_on_top) = window.get("always_on_top", boolean)? {
+        config.always_on_top = always_on_top;
+    }
+    if let Some(scale) = window.get("fit_to_video", float)? {
+        if scale <= 0.0 {
+            return Err(window.error("fit_to_video", "应为正数"));
+        }
+        config.fit_to_video = Some(scale);
+    }
+    if let Some(remember) = window.get("remember_geometry", boolean)? {
+        config.remember_geometry = remember;
+    }
+    window.finish()?;
+
+    let mut playback = Section::take(&mut root, &file, "playback")?;
+    if let Some(shuffle) = playback.get("shuffle", boolean)? {
+        config.shuffle = shuffle;
+    }
+    if let Some(repeat) = playback.get("repeat", parsed)? {
+        config.repeat = repeat;
+    }
+    if let Some(resume) = playback.get("resume", boolean)? {
+        config.resume = resume;
+    }
+    if let Some(threads) = playback.get("decoder_threads", positive::<usize>)? {
+        config.decoder_threads = Some(threads);
+    }
+    if let Some(dir) = playback.get("screenshot_dir", path_value)? {
+        config.screenshot_dir = dir;
+    }
+    playback.finish()?;
+
+    let mut video = Section::take(&mut root, &file, "video")?;
+    if let Some(crop) = video.get("crop", parsed)? {
+        config.crop = Some(crop);
+    }
+    if let Some(filter) = video.get("filter", string)? {
+        config.video_filter = Some(filter).filter(|filter| !filter.trim().is_empty());
+    }
+    if let Some(deinterlace) = video.get("deinterlace", parsed)? {
+        config.deinterlace = deinterlace;
+    }
+    if let Some(deinterlacer) = video.get("deinterlacer", parsed)? {
+        config.deinterlacer = deinterlacer;
+    }
+    if let Some(field_rate) = video.get("field_rate", boolean)? {
+        config.deinterlace_field_rate = field_rate;
+    }
+    video.finish()?;
+
+    let mut audio = Section::take(&mut root, &file, "audio")?;
+    if let Some(volume) = audio.get("volume", float)? {
+        if !(0.0..=MAX_VOLUME_PERCENT).contains(&volume) {
+            let message = format!("应在 0 到 {} 之间", MAX_VOLUME_PERCENT);
+            return Err(audio.error("volume", message));
+        }
+        config.volume = (volume / 100.0) as f32;
+    }
+    if let Some(device) = audio.get("device", string)? {
+        config.audio_device = Some(device).filter(|device| !device.trim().is_empty());
+    }
+    if let Some(buffer) = audio.get("buffer_ms", positive::<u64>)? {
+        config.audio_buffer = Duration::from_millis(buffer);
+    }
+    if let Some(frames) = audio.get("device_buffer", positive::<u32>)? {
+        config.audio_device_buffer = Some(frames);
+    }
+    if let Some(delay) = audio.get("delay_ms", integer::<i64>)? {
+        config.audio_delay_ms = delay;
+    }
+    if let Some(remember) = audio.get("remember_delay", boolean)? {
+        config.remember_audio_delay = remember;
+    }
+    if let Some(filter) = audio.get("filter", string)? {
+        config.audio_filter = Some(filter).filter(|filter| !filter.trim().is_empty());
+    }
+    if let Some(replaygain) = audio.get("replaygain", parsed)? {
+        config.replaygain = replaygain;
+    }
+    if let Some(preamp) = audio.get("replaygain_preamp", float)? {
+        config.replaygain_preamp = preamp as f32;
+    }
+    if let Some(target) = audio.get("normalize", float)? {
+        config.normalize_target = Some(target as f32);
+    }
+    if let Some(languages) = audio.get("languages", string_list)? {
+        config.preferred_audio_languages = languages;
+    }
+    if let Some(visualization) = audio.get("visualization", parsed)? {
+        config.visualization = visualization;
+    }
+    audio.finish()?;
+
+    let mut subtitles = Section::take(&mut root, &file, "subtitles")?;
+    if let Some(font) = subtitles.get("font", path_value)? {
+        config.subtitle_font = Some(font);
+    }
+    if let Some(auto_load) = subtitles.get("auto_load", boolean)? {
+        config.auto_load_subtitles = auto_load;
+    }
+    if let Some(languages) = subtitles.get("languages", string_list)? {
+        config.preferred_subtitle_languages = languages;
+    }
+    if let Some(scale) = subtitles.get("scale", float)? {
+        if scale <= 0.0 || scale > 1.0 {
+            return Err(subtitles.error("scale", "应在 0 到 1 之间（相对于窗口高度）"));
+        }
+        config.subtitle_style.scale = scale as f32;
+    }
+    if let Some(color) = subtitles.get("color", rgba)? {
+        config.subtitle_style.color = color;
+    }
+    if let Some(outline) = subtitles.get("outline", float)? {
+        if outline < 0.0 {
+            return Err(subtitles.error("outline", "不能为负数"));
+        }
+        config.subtitle_style.outline = outline as f32;
+    }
+    if let Some(color) = subtitles.get("outline_color", rgba)? {
+        config.subtitle_style.outline_color = color;
+    }
+    subtitles.finish()?;
+
+    let mut remote = Section::take(&mut root, &file, "remote")?;
+    if let Some(socket) = remote.get("ipc_socket", path_value)? {
+        config.ipc_socket = Some(socket);
+    }
+    if let Some(address) = remote.get("http_address", socket_address)? {
+        config.http_address = Some(address);
+    }
+    if let Some(token) = remote.get("http_token", string)? {
+        if token.is_empty() {
+            return Err(remote.error("http_token", "不能为空"));
+        }
+        config.http_token = Some(token);
+    }
+    remote.finish()?;
+
+    // [bindings] 的键是按键，值是操作名，写 "none" 解除该按键的默认绑定
+    let bindings = Section::take(&mut root, &file, "bindings")?;
+    for (key, value) in &bindings.table {
+        let chord: KeyChord = key.parse().map_err(|e| bindings.error(key, e))?;
+        let action = match string(value.clone()).map_err(|e| bindings.error(key, e))? {
+            name if name.eq_ignore_ascii_case("none") => None,
+            name => Some(name.parse::<Action>().map_err(|e| bindings.error(key, e))?),
+        };
+        config.key_bindings.bind(chord, action);
+    }
+
+    if let Some(key) = root.keys().next() {
+        anyhow::bail!("配置文件 {}: 未知的配置节 {}", file, key);
+    }
+    tracing::info!("已加载配置文件 {}", file);
+    Ok(())
+}
+
+/// 配置文件中的一个表，读取过的键从表中移除，剩下的就是无法识别的键
+struct Section<'a> {
+    file: &'a str,
+    name: &'static str,
+    table: Table,
+}
+
+impl<'a> Section<'a> {
+    fn take(root: &mut Table, file: &'a str, name: &'static str) -> Result<Self, anyhow::Error> {
+        let table = match root.remove(name) {
+            None => Table::new(),
+            Some(Value::Table(table)) => table,
+            Some(_) => anyhow::bail!("配置文件 {}: {} 应为表，写作 [{}]", file, name, name),
+        };
+        Ok(Self { file, name, table })
+    }
+
+    fn get<T>(
+        &mut self,
+        key: &str,
+        convert: fn(Value) -> Result<T, String>,
+    ) -> Result<Option<T>, anyhow::Error> {
+        match self.table.remove(key) {
+            Some(value) => convert(value).map(Some).map_err(|e| self.error(key, e)),
+            None => Ok(None),
+        }
+    }
+
+    fn error(&self, key: &str, message: impl std::fmt::Display) -> anyhow::Error {
+        anyhow::anyhow!("配置文件 {}: {}.{}: {}", self.file, self.name, key, message)
+    }
+
+    fn finish(self) -> Result<(), anyhow::Error> {
+        match self.table.keys().next() {
+            Some(key) => Err(self.error(key, "未知的配置项")),
+            None => Ok(()),
+        }
+    }
+}
+
+fn boolean(value: Value) -> Result<bool, String> {
+    value.as_bool().ok_or_else(|| format!("应为 true 或 false，实际为 {}", value))
+}
+
+fn string(value: Value) -> Result<String, String> {
+    match value {
+        Value::String(s) => Ok(s),
+        value => Err(format!("应为字符串，实际为 {}", value)),
+    }
+}
+
+fn integer<T: TryFrom<i64>>(value: Value) -> Result<T, String> {
+    let n = value
+        .as_integer()
+        .ok_or_else(|| format!("应为整数，实际为 {}", value))?;
+    T::try_from(n).map_err(|_| format!("超出范围: {}", n))
+}
+
+fn positive<T: TryFrom<i64>>(value: Value) -> Result<T, String> {
+    match value.as_integer() {
+        Some(n) if n <= 0 => Err(format!("应为正整数，实际为 {}", n)),
+        _ => integer(value),
+    }
+}
+
+/// 整数也当作浮点数接受，例如 volume = 80
+fn float(value: Value) -> Result<f64, String> {
+    match value {
+        Value::Float(f) if f.is_finite() => Ok(f),
+        Value::Integer(n) => Ok(n as f64),
+        value => Err(format!("应为数字，实际为 {}", value)),
+    }
+}
+
+/// 用各类型自己的 FromStr 解析字符串，和命令行参数使用同样的写法
+fn parsed<T: FromStr<Err = String>>(value: Value) -> Result<T, String> {
+    string(value)?.parse()
+}
+
+fn string_list(value: Value) -> Result<Vec<String>, String> {
+    match value {
+        Value::Array(items) => items.into_iter().map(string).collect(),
+        value => Err(format!("应为字符串数组，例如 [\"chi\", \"eng\"]，实际为 {}", value)),
+    }
+}
+
+/// 以 `~/` 开头的路径相对于主目录
+fn path_value(value: Value) -> Result<PathBuf, String> {
+    let path = string(value)?;
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(relative), Some(home)) => Ok(PathBuf::from(home).join(relative)),
+        _ => Ok(PathBuf::from(path)),
+    }
+}
+
+fn socket_address(value: Value) -> Result<SocketAddr, String> {
+    let address = string(value)?;
+    address
+        .parse()
+        .map_err(|_| format!("无效的地址: {}，例如 127.0.0.1:8080", address))
+}
+
+/// `#RRGGBB` 或 `#RRGGBBAA`
+fn rgba(value: Value) -> Result<[u8; 4], String> {
+    let text = string(value)?;
+    let hex = text.strip_prefix('#').unwrap_or(&text);
+    let invalid = || format!("无效的颜色: {}，应写作 #RRGGBB 或 #RRGGBBAA", text);
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+        return Err(invalid());
+    }
+    let mut color = [255; 4];
+    for (i, channel) in color.iter_mut().enumerate().take(hex.len() / 2) {
+        *channel = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(color)
+}
+
+#[cfg(test)]
+mod tests {
+    use glium::glutin::event::{ModifiersState, VirtualKeyCode};
+
+    use super::*;
+
+    fn apply_test(content: &str) -> Result<Config, String> {
+        let mut config = Config::new(Vec::new());
+        apply_str(&mut config, content, "test.toml").map_err(|e| e.to_string())?;
+        Ok(config)
+    }
+
+    /// 解析应当失败，返回错误信息
+    fn error_of(content: &str) -> String {
+        apply_test(content).err().expect("配置应当无效")
+    }
+
+    #[test]
+    fn applies_known_keys() {
+        let config = apply_test(
+            "[window]\nwidth = 1280\nscale_mode = \"fit\"\n\
+             [audio]\nvolume = 80\ndelay_ms = -120\nlanguages = [\"jpn\", \"eng\"]\n\
+             [subtitles]\ncolor = \"#FFCC0080\"\n\
+             [remote]\nhttp_address = \"127.0.0.1:8080\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.window_width, 1280);
+        assert_eq!(config.window_height, 600);
+        assert_eq!(config.volume, 0.8);
+        assert_eq!(config.audio_delay_ms, -120);
+        assert_eq!(config.preferred_audio_languages, ["jpn", "eng"]);
+        assert_eq!(config.subtitle_style.color, [0xff, 0xcc, 0x00, 0x80]);
+        assert_eq!(config.http_address, Some("127.0.0.1:8080".parse().unwrap()));
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_sections() {
+        let error = error_of("[window]\nwidth = 1280\nwdith = 720\n");
+        assert!(error.contains("test.toml: window.wdith: 未知的配置项"), "{}", error);
+        let error = error_of("[vidoe]\ncrop = \"100x100\"\n");
+        assert!(error.contains("未知的配置节 vidoe"), "{}", error);
+        let error = error_of("window = 1\n");
+        assert!(error.contains("window 应为表"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_wrong_types() {
+        let error = error_of("[window]\nwidth = \"big\"\n");
+        assert!(error.contains("window.width: 应为整数"), "{}", error);
+        let error = error_of("[playback]\nshuffle = \"yes\"\n");
+        assert!(error.contains("playback.shuffle: 应为 true 或 false"), "{}", error);
+        let error = error_of("[audio]\nlanguages = \"jpn\"\n");
+        assert!(error.contains("audio.languages: 应为字符串数组"), "{}", error);
+        let error = error_of("[playback]\nrepeat = \"twice\"\n");
+        assert!(error.contains("playback.repeat: 无效的循环模式"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_out_of_range_values() {
+        let error = error_of("[window]\nwidth = 0\n");
+        assert!(error.contains("window.width: 应为正整数"), "{}", error);
+        let error = error_of("[audio]\nvolume = 250\n");
+        assert!(error.contains("audio.volume: 应在 0 到 200 之间"), "{}", error);
+        let error = error_of("[subtitles]\nscale = 1.5\n");
+        assert!(error.contains("subtitles.scale"), "{}", error);
+        let error = error_of("[subtitles]\ncolor = \"#FFF\"\n");
+        assert!(error.contains("subtitles.color: 无效的颜色"), "{}", error);
+        let error = error_of("[remote]\nhttp_token = \"\"\n");
+        assert!(error.contains("remote.http_token: 不能为空"), "{}", error);
+    }
+
+    #[test]
+    fn binds_and_unbinds_keys() {
+        let config = apply_test(
+            "[bindings]\nSpace = \"none\"\n\
+             \"Ctrl+P\" = \"toggle-pause\"\n\"Ctrl++\" = \"zoom-in\"\n",
+        )
+        .unwrap();
+        let bindings = &config.key_bindings;
+        assert_eq!(bindings.action(VirtualKeyCode::Space, ModifiersState::empty()), None);
+        assert_eq!(
+            bindings.action(VirtualKeyCode::P, ModifiersState::CTRL),
+            Some(Action::TogglePause)
+        );
+        assert_eq!(
+            bindings.action(VirtualKeyCode::Plus, ModifiersState::CTRL),
+            Some(Action::ZoomIn)
+        );
+
+        let error = error_of("[bindings]\nSpace = \"pause\"\n");
+        assert!(error.contains("bindings.Space: 未知的操作: pause"), "{}", error);
+        let error = error_of("[bindings]\n\"Hyper+S\" = \"screenshot\"\n");
+        assert!(error.contains("bindings.Hyper+S: 未知的修饰键"), "{}", error);
+    }
+}
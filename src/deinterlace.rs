@@ -0,0 +1,155 @@
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// 去隔行开关，D 键按 自动 -> 开 -> 关 循环
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DeinterlaceMode {
+    /// 只处理解码器标记为隔行的帧
+    #[default]
+    Auto,
+    /// 所有帧都去隔行，用于没有正确标记场序的片源
+    On,
+    Off,
+}
+
+impl DeinterlaceMode {
+    pub fn next(self) -> Self {
+        match self {
+            DeinterlaceMode::Auto => DeinterlaceMode::On,
+            DeinterlaceMode::On => DeinterlaceMode::Off,
+            DeinterlaceMode::Off => DeinterlaceMode::Auto,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DeinterlaceMode::Auto => "自动",
+            DeinterlaceMode::On => "开",
+            DeinterlaceMode::Off => "关",
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => DeinterlaceMode::On,
+            2 => DeinterlaceMode::Off,
+            _ => DeinterlaceMode::Auto,
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            DeinterlaceMode::Auto => 0,
+            DeinterlaceMode::On => 1,
+            DeinterlaceMode::Off => 2,
+        }
+    }
+}
+
+impl FromStr for DeinterlaceMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "auto" => Ok(DeinterlaceMode::Auto),
+            "on" | "yes" => Ok(DeinterlaceMode::On),
+            "off" | "no" => Ok(DeinterlaceMode::Off),
+            _ => Err(format!("无效的去隔行模式: {}，可选 auto、on、off", s)),
+        }
+    }
+}
+
+/// 去隔行的实现方式：yadif、bwdif 在解码线程里经 libavfilter 处理，
+/// bob、linear 在渲染器的片段着色器里按场采样
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Deinterlacer {
+    #[default]
+    Yadif,
+    Bwdif,
+    /// 重复本场的行
+    Bob,
+    /// 用本场上下相邻的两行插值出另一场的行
+    Linear,
+}
+
+impl Deinterlacer {
+    pub fn is_gpu(self) -> bool {
+        matches!(self, Deinterlacer::Bob | Deinterlacer::Linear)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Deinterlacer::Yadif => "yadif",
+            Deinterlacer::Bwdif => "bwdif",
+            Deinterlacer::Bob => "bob",
+            Deinterlacer::Linear => "linear",
+        }
+    }
+
+    /// CPU 去隔行的滤镜描述，GPU 方式返回 None；
+    /// field_rate 为 true 时每一场输出一帧，only_interlaced 为 true 时逐行帧原样通过
+    pub fn filter_spec(self, field_rate: bool, only_interlaced: bool) -> Option<String> {
+        if self.is_gpu() {
+            return None;
+        }
+        let mode = if field_rate { "send_field" } else { "send_frame" };
+        let deint = if only_interlaced { "interlaced" } else { "all" };
+        Some(format!("{}=mode={}:parity=auto:deint={}", self.label(), mode, deint))
+    }
+}
+
+impl FromStr for Deinterlacer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "yadif" => Ok(Deinterlacer::Yadif),
+            "bwdif" => Ok(Deinterlacer::Bwdif),
+            "bob" => Ok(Deinterlacer::Bob),
+            "linear" => Ok(Deinterlacer::Linear),
+            _ => Err(format!("无效的去隔行方式: {}，可选 yadif、bwdif、bob、linear", s)),
+        }
+    }
+}
+
+/// GPU 去隔行时这一帧要显示的场
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Field {
+    Top,
+    Bottom,
+}
+
+impl Field {
+    pub fn other(self) -> Self {
+        match self {
+            Field::Top => Field::Bottom,
+            Field::Bottom => Field::Top,
+        }
+    }
+}
+
+/// 播放器和解码线程共享的去隔行设置，模式可以在播放中切换，实现方式启动时确定
+pub struct DeinterlaceSettings {
+    mode: AtomicU8,
+    pub deinterlacer: Deinterlacer,
+    /// 输出场频：25i/30i 的片源按 50p/60p 显示
+    pub field_rate: bool,
+}
+
+impl DeinterlaceSettings {
+    pub fn new(mode: DeinterlaceMode, deinterlacer: Deinterlacer, field_rate: bool) -> Self {
+        Self {
+            mode: AtomicU8::new(mode.to_u8()),
+            deinterlacer,
+            field_rate,
+        }
+    }
+
+    pub fn mode(&self) -> DeinterlaceMode {
+        DeinterlaceMode::from_u8(self.mode.load(Ordering::Relaxed))
+    }
+
+    pub fn set_mode(&self, mode: DeinterlaceMode) {
+        self.mode.store(mode.to_u8(), Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,162 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+use serde_json::{Map, Value};
+
+use crate::paths;
+
+const FILE_STATE_FILE_NAME: &str = "file_state.json";
+
+/// 按媒体文件记住的播放设置，保存在状态目录下的一个 JSON 对象中，以文件的绝对路径为键
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FileState {
+    /// 音频相对画面的延迟（毫秒），None 表示沿用配置中的默认值
+    pub audio_delay_ms: Option<i64>,
+    /// 上次关闭时的播放位置
+    pub resume: Option<ResumePosition>,
+}
+
+/// 续播位置连同记录时文件的大小和修改时间，文件被替换或修改后不再续播
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResumePosition {
+    pub position: Duration,
+    pub size: u64,
+    /// 修改时间，自 UNIX 纪元起的秒数
+    pub mtime: u64,
+}
+
+impl FileState {
+    /// 读取 media 上次保存的设置，没有记录时返回默认值
+    pub fn load(media: &Path) -> Self {
+        let states = file_path()
+            .map(|path| {
+                read_all(&path).unwrap_or_else(|e| {
+                    tracing::warn!("{}", e);
+                    Map::new()
+                })
+            })
+            .unwrap_or_default();
+        let Some(Value::Object(state)) = states.get(&key(media)) else {
+            return Self::default();
+        };
+        let resume = state.get("resume").and_then(|resume| {
+            Some(ResumePosition {
+                position: Duration::try_from_secs_f64(resume.get("position")?.as_f64()?).ok()?,
+                size: resume.get("size")?.as_u64()?,
+                mtime: resume.get("mtime")?.as_u64()?,
+            })
+        });
+        Self {
+            audio_delay_ms: state.get("audio_delay_ms").and_then(Value::as_i64),
+            resume,
+        }
+    }
+
+    /// 文件自记录以来没有变化时返回上次的播放位置
+    pub fn resume_position(&self, media: &Path) -> Option<Duration> {
+        let resume = self.resume?;
+        let (size, mtime) = fingerprint(media)?;
+        (resume.size == size && resume.mtime == mtime).then_some(resume.position)
+    }
+
+    /// 记录 media 当前的播放位置，None 表示清除续播记录
+    pub fn set_resume_position(&mut self, media: &Path, position: Option<Duration>) {
+        self.resume = position.and_then(|position| {
+            let (size, mtime) = fingerprint(media)?;
+            Some(ResumePosition {
+                position,
+                size,
+                mtime,
+            })
+        });
+    }
+
+    /// 保存 media 的设置，所有字段都为空时删除该文件的记录。
+    /// 已有的状态文件无法解析时不保存，以免覆盖掉其他文件的记录
+    pub fn save(&self, media: &Path) -> Result<(), anyhow::Error> {
+        let path = file_path().ok_or_else(|| anyhow::anyhow!("无法确定状态目录"))?;
+        let mut states = match read_all(&path) {
+            Ok(states) => states,
+            Err(e) => {
+                tracing::warn!("{}，不保存 {:?} 的播放设置", e, media);
+                return Ok(());
+            }
+        };
+        let mut state = Map::new();
+        if let Some(delay) = self.audio_delay_ms {
+            state.insert(String::from("audio_delay_ms"), Value::from(delay));
+        }
+        if let Some(resume) = self.resume {
+            let mut entry = Map::new();
+            entry.insert(String::from("position"), Value::from(resume.position.as_secs_f64()));
+            entry.insert(String::from("size"), Value::from(resume.size));
+            entry.insert(String::from("mtime"), Value::from(resume.mtime));
+            state.insert(String::from("resume"), Value::Object(entry));
+        }
+        if state.is_empty() {
+            states.remove(&key(media));
+        } else {
+            states.insert(key(media), Value::Object(state));
+        }
+        write_atomically(&path, &serde_json::to_string_pretty(&Value::Object(states))?)?;
+        tracing::info!("已保存 {:?} 的播放设置: {:?}", media, self);
+        Ok(())
+    }
+}
+
+/// 读取所有文件的记录，文件不存在时返回空表，无法读取或格式不对时返回错误
+fn read_all(path: &Path) -> Result<Map<String, Value>, anyhow::Error> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Map::new()),
+        Err(e) => anyhow::bail!("读取文件播放设置 {:?} 失败: {}", path, e),
+    };
+    match serde_json::from_str(&content) {
+        Ok(Value::Object(states)) => Ok(states),
+        _ => anyhow::bail!("文件播放设置格式错误: {:?}", path),
+    }
+}
+
+/// 先写入同目录下的临时文件再改名替换，写到一半被打断时原文件保持完整
+fn write_atomically(path: &Path, content: &str) -> Result<(), anyhow::Error> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // 带上进程号，同时运行的多个播放器不会写同一个临时文件
+    let temp_path = path.with_extension(format!("json.{}.tmp", std::process::id()));
+    let written =
+        write_synced(&temp_path, content).and_then(|()| std::fs::rename(&temp_path, path));
+    if written.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    written?;
+    Ok(())
+}
+
+/// 写入并等数据落盘，之后改名才不会换上一个空文件
+fn write_synced(path: &Path, content: &str) -> std::io::Result<()> {
+    let mut file = std::fs::File::create(path)?;
+    file.write_all(content.as_bytes())?;
+    file.sync_all()
+}
+
+/// 文件的大小和修改时间，用来判断记录之后文件是否变过
+fn fingerprint(media: &Path) -> Option<(u64, u64)> {
+    let metadata = std::fs::metadata(media).ok()?;
+    let mtime = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((metadata.len(), mtime.as_secs()))
+}
+
+/// 同一个文件用不同的相对路径打开时也对应同一条记录
+fn key(media: &Path) -> String {
+    media
+        .canonicalize()
+        .unwrap_or_else(|_| media.to_path_buf())
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn file_path() -> Option<PathBuf> {
+    paths::state_dir().map(|dir| dir.join(FILE_STATE_FILE_NAME))
+}
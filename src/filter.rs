@@ -0,0 +1,263 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use ffmpeg::filter::Graph;
+use ffmpeg::format::{Pixel, Sample};
+use ffmpeg::util::channel_layout::ChannelLayout;
+use ffmpeg::util::frame::{Audio, Video};
+use ffmpeg::Rational;
+
+/// 播放器和解码线程共享的滤镜链描述（libavfilter 的 filtergraph 字符串），
+/// 修改后解码线程在下一帧重建滤镜图；空字符串表示不使用滤镜
+pub struct FilterSpec {
+    spec: Mutex<String>,
+    /// 每次 set 加一，解码线程据此判断手上的滤镜图是否过期
+    generation: AtomicU64,
+}
+
+impl FilterSpec {
+    pub fn new(spec: &str) -> Self {
+        Self {
+            spec: Mutex::new(spec.trim().to_string()),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    pub fn get(&self) -> String {
+        self.spec.lock().unwrap().clone()
+    }
+
+    pub fn set(&self, spec: &str) {
+        *self.spec.lock().unwrap() = spec.trim().to_string();
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+}
+
+/// 构建滤镜图时的输入帧参数，解码帧的尺寸或像素格式变化时需要重建滤镜图
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct VideoInput {
+    width: u32,
+    height: u32,
+    format: Pixel,
+}
+
+impl VideoInput {
+    fn of(frame: &Video) -> Self {
+        Self {
+            width: frame.width(),
+            height: frame.height(),
+            format: frame.format(),
+        }
+    }
+}
+
+/// 一条 libavfilter 视频滤镜链：buffer -> spec -> buffersink
+pub struct VideoFilter {
+    graph: Graph,
+    input: VideoInput,
+    /// 滤镜链输出帧的时间基，yadif=1 这类改变帧率的滤镜会改变时间基
+    output_time_base: Rational,
+}
+
+impl VideoFilter {
+    /// 按第一帧的参数构建滤镜图，time_base 为输入帧 PTS 的时间基
+    pub fn new(spec: &str, frame: &Video, time_base: Rational) -> Result<Self, ffmpeg::Error> {
+        let input = VideoInput::of(frame);
+        let aspect = frame.aspect_ratio();
+        let aspect = if aspect.numerator() > 0 { aspect } else { Rational::new(1, 1) };
+        let mut graph = build_video_graph(spec, input, time_base, aspect)?;
+        let output_time_base = graph.get("out").unwrap().sink().time_base();
+        tracing::info!("视频滤镜: {}，输出时间基 {}", spec, output_time_base);
+        Ok(Self {
+            graph,
+            input,
+            output_time_base,
+        })
+    }
+
+    /// 用一组占位参数试建滤镜图，在真正应用前检查滤镜名和参数是否有效
+    pub fn check(spec: &str) -> Result<(), ffmpeg::Error> {
+        let input = VideoInput {
+            width: 64,
+            height: 64,
+            format: Pixel::YUV420P,
+        };
+        build_video_graph(spec, input, Rational::new(1, 25), Rational::new(1, 1)).map(|_| ())
+    }
+
+    /// 帧的尺寸和格式是否与构建滤镜图时一致
+    pub fn accepts(&self, frame: &Video) -> bool {
+        VideoInput::of(frame) == self.input
+    }
+
+    pub fn output_time_base(&self) -> Rational {
+        self.output_time_base
+    }
+
+    pub fn push(&mut self, frame: &Video) -> Result<(), ffmpeg::Error> {
+        self.graph.get("in").unwrap().source().add(frame)
+    }
+
+    /// 输入结束，之后 pull 取出滤镜内部缓存的剩余帧
+    pub fn flush(&mut self) -> Result<(), ffmpeg::Error> {
+        self.graph.get("in").unwrap().source().flush()
+    }
+
+    /// 取出一帧处理后的帧，滤镜需要更多输入或已经结束时返回 None
+    pub fn pull(&mut self) -> Option<Video> {
+        let mut frame = Video::empty();
+        self.graph
+            .get("out")
+            .unwrap()
+            .sink()
+            .frame(&mut frame)
+            .ok()
+            .map(|_| frame)
+    }
+}
+
+fn build_video_graph(
+    spec: &str,
+    input: VideoInput,
+    time_base: Rational,
+    aspect: Rational,
+) -> Result<Graph, ffmpeg::Error> {
+    let args = format!(
+        "video_size={}x{}:pix_fmt={}:time_base={}/{}:pixel_aspect={}/{}",
+        input.width,
+        input.height,
+        ffmpeg::ffi::AVPixelFormat::from(input.format) as i32,
+        time_base.numerator(),
+        time_base.denominator(),
+        aspect.numerator(),
+        aspect.denominator(),
+    );
+    build_graph("buffer", "buffersink", &args, spec)
+}
+
+/// 构建 source -> spec -> sink 的滤镜图，args 为源滤镜的参数
+fn build_graph(source: &str, sink: &str, args: &str, spec: &str) -> Result<Graph, ffmpeg::Error> {
+    let source = ffmpeg::filter::find(source).ok_or(ffmpeg::Error::FilterNotFound)?;
+    let sink = ffmpeg::filter::find(sink).ok_or(ffmpeg::Error::FilterNotFound)?;
+
+    let mut graph = Graph::new();
+    graph.add(&source, "in", args)?;
+    graph.add(&sink, "out", "")?;
+    graph.output("in", 0)?.input("out", 0)?.parse(spec)?;
+    graph.validate()?;
+    Ok(graph)
+}
+
+/// 音频帧的声道布局，解码器没有给出布局时按声道数取默认布局
+pub fn frame_channel_layout(frame: &Audio) -> ChannelLayout {
+    let layout = frame.channel_layout();
+    if layout.is_empty() {
+        ChannelLayout::default(frame.channels() as i32)
+    } else {
+        layout
+    }
+}
+
+/// 构建音频滤镜图时的输入帧参数
+#[derive(Clone, Copy, PartialEq)]
+struct AudioInput {
+    format: Sample,
+    channel_layout: ChannelLayout,
+    rate: u32,
+}
+
+impl AudioInput {
+    fn of(frame: &Audio) -> Self {
+        Self {
+            format: frame.format(),
+            channel_layout: frame_channel_layout(frame),
+            rate: frame.rate(),
+        }
+    }
+}
+
+/// 一条 libavfilter 音频滤镜链：abuffer -> spec -> abuffersink，
+/// 输出的采样格式、声道布局和采样率由滤镜链决定，之后再交给重采样器
+pub struct AudioFilter {
+    graph: Graph,
+    input: AudioInput,
+    /// 滤镜链输出帧的时间基，loudnorm、aresample 这类改变采样率的滤镜会改变时间基
+    output_time_base: Rational,
+}
+
+impl AudioFilter {
+    /// 按第一帧的参数构建滤镜图，time_base 为输入帧 PTS 的时间基
+    pub fn new(spec: &str, frame: &Audio, time_base: Rational) -> Result<Self, ffmpeg::Error> {
+        let input = AudioInput::of(frame);
+        let mut graph = build_audio_graph(spec, input, time_base)?;
+        let output_time_base = graph.get("out").unwrap().sink().time_base();
+        tracing::info!("音频滤镜: {}，输出时间基 {}", spec, output_time_base);
+        Ok(Self {
+            graph,
+            input,
+            output_time_base,
+        })
+    }
+
+    /// 用一组占位参数试建滤镜图，在真正应用前检查滤镜名和参数是否有效
+    pub fn check(spec: &str) -> Result<(), ffmpeg::Error> {
+        let input = AudioInput {
+            format: Sample::F32(ffmpeg::format::sample::Type::Packed),
+            channel_layout: ChannelLayout::STEREO,
+            rate: 48000,
+        };
+        build_audio_graph(spec, input, Rational::new(1, 48000)).map(|_| ())
+    }
+
+    /// 帧的采样格式、声道布局和采样率是否与构建滤镜图时一致
+    pub fn accepts(&self, frame: &Audio) -> bool {
+        AudioInput::of(frame) == self.input
+    }
+
+    pub fn output_time_base(&self) -> Rational {
+        self.output_time_base
+    }
+
+    pub fn push(&mut self, frame: &Audio) -> Result<(), ffmpeg::Error> {
+        self.graph.get("in").unwrap().source().add(frame)
+    }
+
+    /// 输入结束，之后 pull 取出滤镜内部缓存的剩余采样
+    pub fn flush(&mut self) -> Result<(), ffmpeg::Error> {
+        self.graph.get("in").unwrap().source().flush()
+    }
+
+    /// 取出一帧处理后的音频，滤镜需要更多输入或已经结束时返回 None
+    pub fn pull(&mut self) -> Option<Audio> {
+        let mut frame = Audio::empty();
+        self.graph
+            .get("out")
+            .unwrap()
+            .sink()
+            .frame(&mut frame)
+            .ok()
+            .map(|_| frame)
+    }
+}
+
+fn build_audio_graph(
+    spec: &str,
+    input: AudioInput,
+    time_base: Rational,
+) -> Result<Graph, ffmpeg::Error> {
+    let args = format!(
+        "time_base={}/{}:sample_rate={}:sample_fmt={}:channel_layout=0x{:x}",
+        time_base.numerator(),
+        time_base.denominator(),
+        input.rate,
+        input.format.name(),
+        input.channel_layout.bits(),
+    );
+    build_graph("abuffer", "abuffersink", &args, spec)
+}
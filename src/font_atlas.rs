@@ -0,0 +1,249 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use ab_glyph::{point, Font, FontVec, GlyphId, PxScale, ScaleFont};
+use glium::{
+    texture::{ClientFormat, MipmapsOption, RawImage2d, UncompressedFloatFormat},
+    Display, Rect, Texture2d,
+};
+
+/// 图集纹理边长，单位为像素
+const ATLAS_SIZE: u32 = 1024;
+/// 字形之间留出的空隙，避免线性采样时混入相邻字形
+const GLYPH_PADDING: u32 = 1;
+/// 左上角保留的纯色区域边长，用于绘制不带字形的矩形
+const SOLID_SIZE: u32 = 4;
+
+/// 字形在图集中的位置和排版度量，单位为像素
+#[derive(Copy, Clone, Debug)]
+pub struct GlyphInfo {
+    pub id: GlyphId,
+    /// 纹理坐标 [left, top, right, bottom]
+    pub uv: [f32; 4],
+    /// 字形左上角相对于基线上笔位置的偏移
+    pub offset: [f32; 2],
+    pub size: [f32; 2],
+    pub advance: f32,
+}
+
+/// 单通道字形图集：按需光栅化字形并打包到一张纹理中，OSD 用它逐字形绘制文本
+pub struct GlyphAtlas {
+    font: FontVec,
+    pixel_size: f32,
+    texture: Texture2d,
+    glyphs: HashMap<char, GlyphInfo>,
+    cursor_x: u32,
+    cursor_y: u32,
+    row_height: u32,
+}
+
+impl GlyphAtlas {
+    pub fn new(display: &Display, font: FontVec, pixel_size: f32) -> Self {
+        let texture = Texture2d::empty_with_format(
+            display,
+            UncompressedFloatFormat::U8,
+            MipmapsOption::NoMipmap,
+            ATLAS_SIZE,
+            ATLAS_SIZE,
+        )
+        .expect("Failed to create glyph atlas texture");
+
+        let mut atlas = Self {
+            font,
+            pixel_size,
+            texture,
+            glyphs: HashMap::new(),
+            cursor_x: 0,
+            cursor_y: 0,
+            row_height: 0,
+        };
+        atlas.clear();
+        atlas
+    }
+
+    pub fn texture(&self) -> &Texture2d {
+        &self.texture
+    }
+
+    pub fn pixel_size(&self) -> f32 {
+        self.pixel_size
+    }
+
+    /// 字号变化后清空图集，之后按新字号重新光栅化
+    pub fn set_pixel_size(&mut self, pixel_size: f32) {
+        if (pixel_size - self.pixel_size).abs() < 0.5 {
+            return;
+        }
+        tracing::debug!("字形图集字号: {} -> {}", self.pixel_size, pixel_size);
+        self.pixel_size = pixel_size;
+        self.clear();
+    }
+
+    pub fn ascent(&self) -> f32 {
+        self.font.as_scaled(self.scale()).ascent()
+    }
+
+    pub fn line_height(&self) -> f32 {
+        let font = self.font.as_scaled(self.scale());
+        font.height() + font.line_gap()
+    }
+
+    pub fn kern(&self, previous: GlyphId, next: GlyphId) -> f32 {
+        self.font.as_scaled(self.scale()).kern(previous, next)
+    }
+
+    /// 纯色区域中心的纹理坐标
+    pub fn solid_uv(&self) -> [f32; 2] {
+        let center = SOLID_SIZE as f32 / 2.0 / ATLAS_SIZE as f32;
+        [center, center]
+    }
+
+    /// 文本一行的宽度
+    pub fn measure(&mut self, text: &str) -> f32 {
+        let mut width = 0.0;
+        let mut previous: Option<GlyphId> = None;
+        for ch in text.chars() {
+            let Some(glyph) = self.glyph(ch) else {
+                continue;
+            };
+            if let Some(previous) = previous {
+                width += self.kern(previous, glyph.id);
+            }
+            width += glyph.advance;
+            previous = Some(glyph.id);
+        }
+        width
+    }
+
+    /// 查找字形，不在图集中时光栅化并上传
+    pub fn glyph(&mut self, ch: char) -> Option<GlyphInfo> {
+        if let Some(glyph) = self.glyphs.get(&ch) {
+            return Some(*glyph);
+        }
+
+        let glyph = match self.rasterize(ch) {
+            Some(glyph) => glyph,
+            None => {
+                // 图集已满：清空后重试一次，之前的字形在下次使用时重新光栅化
+                tracing::debug!("字形图集已满，清空重建");
+                self.clear();
+                self.rasterize(ch)?
+            }
+        };
+        self.glyphs.insert(ch, glyph);
+        Some(glyph)
+    }
+
+    fn scale(&self) -> PxScale {
+        PxScale::from(self.pixel_size.max(1.0))
+    }
+
+    fn rasterize(&mut self, ch: char) -> Option<GlyphInfo> {
+        let scale = self.scale();
+        let font = self.font.as_scaled(scale);
+        let id = font.glyph_id(ch);
+        let advance = font.h_advance(id);
+
+        let Some(outlined) = self
+            .font
+            .outline_glyph(id.with_scale_and_position(scale, point(0.0, 0.0)))
+        else {
+            // 空格等没有轮廓的字形只占位
+            return Some(GlyphInfo {
+                id,
+                uv: [0.0; 4],
+                offset: [0.0; 2],
+                size: [0.0; 2],
+                advance,
+            });
+        };
+
+        let bounds = outlined.px_bounds();
+        let width = bounds.width().ceil() as u32;
+        let height = bounds.height().ceil() as u32;
+        let (x, y) = self.allocate(width, height)?;
+
+        let mut coverage = vec![0u8; (width * height) as usize];
+        outlined.draw(|gx, gy, value| {
+            if gx < width && gy < height {
+                coverage[(gy * width + gx) as usize] = (value.clamp(0.0, 1.0) * 255.0) as u8;
+            }
+        });
+        self.write(x, y, width, height, coverage);
+
+        let atlas_size = ATLAS_SIZE as f32;
+        Some(GlyphInfo {
+            id,
+            uv: [
+                x as f32 / atlas_size,
+                y as f32 / atlas_size,
+                (x + width) as f32 / atlas_size,
+                (y + height) as f32 / atlas_size,
+            ],
+            offset: [bounds.min.x, bounds.min.y],
+            size: [width as f32, height as f32],
+            advance,
+        })
+    }
+
+    /// 按行依次摆放，当前行放不下时换到下一行
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        if width + GLYPH_PADDING > ATLAS_SIZE || height + GLYPH_PADDING > ATLAS_SIZE {
+            return None;
+        }
+        if self.cursor_x + width + GLYPH_PADDING > ATLAS_SIZE {
+            self.cursor_x = 0;
+            self.cursor_y += self.row_height + GLYPH_PADDING;
+            self.row_height = 0;
+        }
+        if self.cursor_y + height + GLYPH_PADDING > ATLAS_SIZE {
+            return None;
+        }
+
+        let position = (self.cursor_x, self.cursor_y);
+        self.cursor_x += width + GLYPH_PADDING;
+        self.row_height = self.row_height.max(height);
+        Some(position)
+    }
+
+    fn clear(&mut self) {
+        self.glyphs.clear();
+        self.write(
+            0,
+            0,
+            ATLAS_SIZE,
+            ATLAS_SIZE,
+            vec![0; (ATLAS_SIZE * ATLAS_SIZE) as usize],
+        );
+        self.write(
+            0,
+            0,
+            SOLID_SIZE,
+            SOLID_SIZE,
+            vec![255; (SOLID_SIZE * SOLID_SIZE) as usize],
+        );
+        self.cursor_x = SOLID_SIZE + GLYPH_PADDING;
+        self.cursor_y = 0;
+        self.row_height = SOLID_SIZE;
+    }
+
+    fn write(&self, x: u32, y: u32, width: u32, height: u32, data: Vec<u8>) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.texture.write(
+            Rect {
+                left: x,
+                bottom: y,
+                width,
+                height,
+            },
+            RawImage2d {
+                data: Cow::Owned(data),
+                width,
+                height,
+                format: ClientFormat::U8,
+            },
+        );
+    }
+}
@@ -0,0 +1,58 @@
+use std::path::PathBuf;
+
+use crate::paths;
+
+const GEOMETRY_FILE_NAME: &str = "window_geometry";
+
+/// 窗口化状态下的窗口位置和大小（物理像素）
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl WindowGeometry {
+    /// 读取上次保存的窗口几何信息，文件不存在或格式不对时返回 None
+    pub fn load() -> Option<Self> {
+        let path = Self::file_path()?;
+        let content = std::fs::read_to_string(&path).ok()?;
+        let geometry = Self::parse(&content);
+        if geometry.is_none() {
+            tracing::warn!("窗口几何信息格式错误: {:?}", path);
+        }
+        geometry
+    }
+
+    pub fn save(&self) -> Result<(), anyhow::Error> {
+        let path = Self::file_path().ok_or_else(|| anyhow::anyhow!("无法确定状态目录"))?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(
+            &path,
+            format!("{} {} {} {}\n", self.x, self.y, self.width, self.height),
+        )?;
+        tracing::info!("窗口几何信息已保存: {:?}", self);
+        Ok(())
+    }
+
+    fn parse(content: &str) -> Option<Self> {
+        let mut fields = content.split_whitespace();
+        let geometry = Self {
+            x: fields.next()?.parse().ok()?,
+            y: fields.next()?.parse().ok()?,
+            width: fields.next()?.parse().ok()?,
+            height: fields.next()?.parse().ok()?,
+        };
+        if geometry.width == 0 || geometry.height == 0 {
+            return None;
+        }
+        Some(geometry)
+    }
+
+    fn file_path() -> Option<PathBuf> {
+        paths::state_dir().map(|dir| dir.join(GEOMETRY_FILE_NAME))
+    }
+}
@@ -0,0 +1,446 @@
+use std::net::{IpAddr, SocketAddr};
+use std::rc::Rc;
+
+use anyhow::Context;
+use serde_json::{json, Value};
+use smol::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use smol::net::{TcpListener, TcpStream};
+
+use crate::ipc::{IpcCommand, IpcRequest, LoadMode, SeekMode};
+
+/// 遥控网页，由 GET / 返回
+const REMOTE_PAGE: &str = include_str!("remote.html");
+/// 请求头的最大行数和请求体的最大字节数，超出时拒绝请求
+const MAX_HEADERS: usize = 64;
+const MAX_BODY: usize = 64 * 1024;
+/// GET /api/status 返回的属性
+const STATUS_PROPERTIES: [&str; 9] = [
+    "position",
+    "duration",
+    "paused",
+    "volume",
+    "speed",
+    "path",
+    "title",
+    "playlist-pos",
+    "playlist-count",
+];
+
+/// 本地 HTTP 遥控服务器，在自己的线程里用 smol 处理连接，
+/// 请求转换成与 IPC 相同的命令交给渲染循环在主线程执行
+pub struct HttpServer {
+    local_address: SocketAddr,
+    request_receiver: smol::channel::Receiver<IpcRequest>,
+    shutdown_sender: smol::channel::Sender<()>,
+    server_thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl HttpServer {
+    /// 监听 address，端口为 0 时由系统分配。token 不为 None 时 /api/* 请求
+    /// 必须带上 Authorization: Bearer <token>
+    pub fn start(address: SocketAddr, token: Option<String>) -> Result<Self, anyhow::Error> {
+        let listener = std::net::TcpListener::bind(address)
+            .with_context(|| format!("无法监听 HTTP 地址 {}", address))?;
+        let local_address = listener.local_addr()?;
+        if !address.ip().is_loopback() && token.is_none() {
+            tracing::warn!("HTTP 遥控服务器监听在非本机地址 {}，局域网内的任何人都可以控制播放", address);
+        }
+        tracing::info!("HTTP 遥控服务器监听: http://{}/", local_address);
+        let access = Access { address: local_address, token };
+
+        let (request_sender, request_receiver) = smol::channel::unbounded();
+        let (shutdown_sender, shutdown_receiver) = smol::channel::bounded::<()>(1);
+
+        let server_thread = std::thread::Builder::new()
+            .name("http server thread".into())
+            .spawn(move || {
+                let access = Rc::new(access);
+                let executor = smol::LocalExecutor::new();
+                smol::block_on(executor.run(async {
+                    let listener = match TcpListener::try_from(listener) {
+                        Ok(listener) => listener,
+                        Err(e) => {
+                            tracing::error!("HTTP 套接字初始化失败: {}", e);
+                            return;
+                        }
+                    };
+
+                    let accept_loop = async {
+                        loop {
+                            let (stream, peer) = match listener.accept().await {
+                                Ok(connection) => connection,
+                                Err(e) => {
+                                    tracing::error!("接受 HTTP 连接失败: {}", e);
+                                    continue;
+                                }
+                            };
+                            tracing::debug!("HTTP 连接: {}", peer);
+                            executor
+                                .spawn(serve_connection(
+                                    stream,
+                                    access.clone(),
+                                    request_sender.clone(),
+                                ))
+                                .detach();
+                        }
+                    };
+
+                    // 发送端关闭（HttpServer 被 drop）时结束整个服务器
+                    let shutdown = async {
+                        let _ = shutdown_receiver.recv().await;
+                    };
+
+                    smol::future::or(accept_loop, shutdown).await;
+                    tracing::info!("HTTP 遥控服务器退出");
+                }));
+            })?;
+
+        Ok(Self {
+            local_address,
+            request_receiver,
+            shutdown_sender,
+            server_thread: Some(server_thread),
+        })
+    }
+
+    /// 实际监听的地址，启动时端口为 0 也能得到系统分配的端口
+    pub fn local_address(&self) -> SocketAddr {
+        self.local_address
+    }
+
+    /// 取出一条待执行的命令，没有时立即返回 None
+    pub fn try_recv(&self) -> Option<IpcRequest> {
+        self.request_receiver.try_recv().ok()
+    }
+}
+
+impl Drop for HttpServer {
+    fn drop(&mut self) {
+        tracing::info!("HttpServer drop");
+        self.shutdown_sender.close();
+        if let Some(server_thread) = self.server_thread.take() {
+            server_thread.join().unwrap();
+        }
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    host: Option<String>,
+    origin: Option<String>,
+    content_type: Option<String>,
+    authorization: Option<String>,
+    body: Vec<u8>,
+}
+
+/// 防止浏览器里的其他网页借用户之手控制播放器：
+/// 跨站的简单请求不经过 CORS 预检，DNS 重绑定可以绕过只监听本机地址的限制
+struct Access {
+    /// 实际监听的地址，Host 必须指向它
+    address: SocketAddr,
+    /// 局域网遥控（例如放映厅的平板）时使用的共享口令
+    token: Option<String>,
+}
+
+impl Access {
+    fn check(&self, request: &Request) -> Result<(), Response> {
+        let Some(host) = request.host.as_deref().filter(|host| self.allows_host(host)) else {
+            tracing::warn!("拒绝 Host 为 {:?} 的 HTTP 请求", request.host);
+            return Err(Response::error(403, "不允许的 Host"));
+        };
+        // 浏览器发出的跨站请求会带上 Origin，只接受来自遥控网页本身的请求
+        if let Some(origin) = &request.origin {
+            if origin.strip_prefix("http://") != Some(host) {
+                tracing::warn!("拒绝来自 {} 的跨站 HTTP 请求", origin);
+                return Err(Response::error(403, "不允许跨站请求"));
+            }
+        }
+        if !request.path.starts_with("/api/") {
+            return Ok(());
+        }
+        if let Some(token) = &self.token {
+            let bearer = request
+                .authorization
+                .as_deref()
+                .and_then(|value| value.strip_prefix("Bearer "));
+            if bearer != Some(token.as_str()) {
+                return Err(Response::error(401, "口令错误"));
+            }
+        }
+        // 跨站表单和 text/plain 请求不能带 application/json，必须先经过 CORS 预检
+        if request.method == "POST" {
+            let json = request.content_type.as_deref().is_some_and(|content_type| {
+                let mime = content_type.split(';').next().unwrap_or_default();
+                mime.trim().eq_ignore_ascii_case("application/json")
+            });
+            if !json {
+                return Err(Response::error(415, "请求体必须是 application/json"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Host 只能是监听的 IP 地址或 localhost 加监听端口；
+    /// 监听所有地址时接受任意 IP 字面量，DNS 重绑定只能使用域名
+    fn allows_host(&self, host: &str) -> bool {
+        let Some((name, port)) = host.rsplit_once(':') else {
+            return false;
+        };
+        if port.parse::<u16>().ok() != Some(self.address.port()) {
+            return false;
+        }
+        if name.eq_ignore_ascii_case("localhost") {
+            return true;
+        }
+        let name = name.strip_prefix('[').and_then(|name| name.strip_suffix(']')).unwrap_or(name);
+        match name.parse::<IpAddr>() {
+            Ok(ip) => ip == self.address.ip() || self.address.ip().is_unspecified(),
+            Err(_) => false,
+        }
+    }
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn json(status: u16, value: Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json; charset=utf-8",
+            body: value.to_string().into_bytes(),
+        }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Self::json(status, json!({ "error": message.into() }))
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            413 => "Payload Too Large",
+            415 => "Unsupported Media Type",
+            503 => "Service Unavailable",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+/// 每个连接只处理一个请求，响应后关闭连接
+async fn serve_connection(
+    stream: TcpStream,
+    access: Rc<Access>,
+    request_sender: smol::channel::Sender<IpcRequest>,
+) {
+    let response = match read_request(&stream).await {
+        Ok(request) => {
+            tracing::info!("HTTP 请求: {} {}", request.method, request.path);
+            match access.check(&request) {
+                Ok(()) => route(&request, &request_sender).await,
+                Err(response) => response,
+            }
+        }
+        Err(response) => response,
+    };
+
+    let mut writer = stream;
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.reason(),
+        response.content_type,
+        response.body.len(),
+    );
+    let written = async {
+        writer.write_all(head.as_bytes()).await?;
+        writer.write_all(&response.body).await?;
+        writer.flush().await
+    };
+    if let Err(e) = written.await {
+        tracing::warn!("写入 HTTP 响应失败: {}", e);
+    }
+}
+
+/// 读取请求行、请求头和按 Content-Length 给出的请求体
+async fn read_request(stream: &TcpStream) -> Result<Request, Response> {
+    let mut reader = BufReader::new(stream.clone());
+    let mut line = String::new();
+
+    read_line(&mut reader, &mut line)
+        .await
+        .map_err(|e| Response::error(400, format!("读取请求失败: {}", e)))?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(Response::error(400, "无效的请求行"));
+    };
+    let method = method.to_string();
+    // 忽略查询参数，参数都放在 JSON 请求体里
+    let path = target.split('?').next().unwrap_or(target).to_string();
+
+    let mut request = Request {
+        method,
+        path,
+        host: None,
+        origin: None,
+        content_type: None,
+        authorization: None,
+        body: Vec::new(),
+    };
+    let mut content_length = 0;
+    for _ in 0..MAX_HEADERS {
+        read_line(&mut reader, &mut line)
+            .await
+            .map_err(|e| Response::error(400, format!("读取请求头失败: {}", e)))?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            request.body = vec![0; content_length];
+            reader
+                .read_exact(&mut request.body)
+                .await
+                .map_err(|e| Response::error(400, format!("读取请求体失败: {}", e)))?;
+            return Ok(request);
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            let name = name.trim().to_ascii_lowercase();
+            let field = match name.as_str() {
+                "host" => Some(&mut request.host),
+                "origin" => Some(&mut request.origin),
+                "content-type" => Some(&mut request.content_type),
+                "authorization" => Some(&mut request.authorization),
+                _ => None,
+            };
+            if let Some(field) = field {
+                *field = Some(value.trim().to_string());
+            }
+            if name == "content-length" {
+                content_length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| Response::error(400, "无效的 Content-Length"))?;
+                if content_length > MAX_BODY {
+                    return Err(Response::error(413, "请求体过大"));
+                }
+            }
+        }
+    }
+    Err(Response::error(400, "请求头过多"))
+}
+
+async fn read_line(reader: &mut BufReader<TcpStream>, line: &mut String) -> std::io::Result<usize> {
+    line.clear();
+    reader.read_line(line).await
+}
+
+async fn route(request: &Request, request_sender: &smol::channel::Sender<IpcRequest>) -> Response {
+    let body = || -> Result<Value, Response> {
+        if request.body.is_empty() {
+            return Ok(Value::Null);
+        }
+        serde_json::from_slice(&request.body)
+            .map_err(|e| Response::error(400, format!("JSON 格式错误: {}", e)))
+    };
+
+    let command = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/" | "/index.html") => {
+            return Response {
+                status: 200,
+                content_type: "text/html; charset=utf-8",
+                body: REMOTE_PAGE.as_bytes().to_vec(),
+            };
+        }
+        ("GET", "/api/status") => return status(request_sender).await,
+        ("POST", "/api/play") => IpcCommand::Play,
+        ("POST", "/api/pause") => IpcCommand::Pause,
+        ("POST", path) if path.starts_with("/api/") => {
+            match body().and_then(|body| parse_command(path, &body)) {
+                Ok(command) => command,
+                Err(response) => return response,
+            }
+        }
+        (_, path) if path == "/" || path == "/index.html" || path.starts_with("/api/") => {
+            return Response::error(405, format!("不支持的请求方法: {}", request.method));
+        }
+        (_, path) => return Response::error(404, format!("未知路径: {}", path)),
+    };
+
+    let (ipc_request, reply_receiver) = IpcRequest::new(command);
+    if request_sender.send(ipc_request).await.is_err() {
+        return Response::error(503, "播放器已退出");
+    }
+    match reply_receiver.recv().await {
+        Ok(Ok(data)) => Response::json(200, json!({ "data": data })),
+        Ok(Err(e)) => Response::error(400, e),
+        Err(_) => Response::error(503, "播放器已退出"),
+    }
+}
+
+/// 解析带 JSON 请求体的命令
+fn parse_command(path: &str, body: &Value) -> Result<IpcCommand, Response> {
+    let number = |key: &str| {
+        body.get(key)
+            .and_then(Value::as_f64)
+            .ok_or_else(|| Response::error(400, format!("缺少数字字段 {}", key)))
+    };
+    let mode = |default: &'static str| body.get("mode").and_then(Value::as_str).unwrap_or(default);
+
+    let command = match path {
+        "/api/seek" => IpcCommand::Seek {
+            seconds: number("seconds")?,
+            mode: match mode("absolute") {
+                "absolute" => SeekMode::Absolute,
+                "relative" => SeekMode::Relative,
+                mode => return Err(Response::error(400, format!("无效的 seek 模式: {}", mode))),
+            },
+        },
+        "/api/volume" => IpcCommand::SetVolume(number("volume")?),
+        "/api/open" => {
+            let Some(url) = body.get("url").and_then(Value::as_str) else {
+                return Err(Response::error(400, "缺少字符串字段 url"));
+            };
+            IpcCommand::LoadFile {
+                path: url.into(),
+                mode: match mode("replace") {
+                    "replace" => LoadMode::Replace,
+                    "append" => LoadMode::Append,
+                    mode => return Err(Response::error(400, format!("无效的 open 模式: {}", mode))),
+                },
+            }
+        }
+        path => return Err(Response::error(404, format!("未知路径: {}", path))),
+    };
+    Ok(command)
+}
+
+/// 同时提交所有属性查询，主线程在同一轮事件循环里执行完，避免逐个往返
+async fn status(request_sender: &smol::channel::Sender<IpcRequest>) -> Response {
+    let mut replies = Vec::with_capacity(STATUS_PROPERTIES.len());
+    for name in STATUS_PROPERTIES {
+        let (request, reply_receiver) = IpcRequest::new(IpcCommand::GetProperty(name.to_string()));
+        if request_sender.send(request).await.is_err() {
+            return Response::error(503, "播放器已退出");
+        }
+        replies.push((name, reply_receiver));
+    }
+
+    let mut status = serde_json::Map::new();
+    for (name, reply_receiver) in replies {
+        match reply_receiver.recv().await {
+            Ok(Ok(value)) => {
+                status.insert(name.to_string(), value);
+            }
+            Ok(Err(e)) => return Response::error(500, e),
+            Err(_) => return Response::error(503, "播放器已退出"),
+        }
+    }
+    Response::json(200, json!({ "data": status }))
+}
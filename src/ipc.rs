@@ -0,0 +1,392 @@
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use anyhow::Context;
+use futures::FutureExt;
+use serde_json::{json, Value};
+use smol::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use smol::net::unix::{UnixListener, UnixStream};
+use smol::stream::StreamExt;
+
+/// IPC 客户端发来的命令，协议与 mpv 的 --input-ipc-server 类似：
+/// 每行一个 JSON 对象，例如 {"command": ["seek", 10, "absolute"], "request_id": 1}
+#[derive(Clone, Debug, PartialEq)]
+pub enum IpcCommand {
+    LoadFile {
+        path: PathBuf,
+        mode: LoadMode,
+    },
+    Play,
+    Pause,
+    Seek {
+        seconds: f64,
+        mode: SeekMode,
+    },
+    /// 音量百分比，100 为原始音量
+    SetVolume(f64),
+    SetSpeed(f64),
+    /// window 为 true 时截取窗口画面，否则按源分辨率截取
+    Screenshot {
+        window: bool,
+    },
+    Quit,
+    GetProperty(String),
+    SetProperty(String, Value),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoadMode {
+    /// 替换播放列表并立即播放
+    Replace,
+    /// 追加到播放列表末尾
+    Append,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeekMode {
+    Relative,
+    Absolute,
+}
+
+/// 推送给所有客户端的事件
+#[derive(Clone, Debug)]
+pub enum IpcEvent {
+    StartFile { path: PathBuf },
+    EndFile { reason: &'static str },
+    Pause,
+    Unpause,
+    Seek { position: f64 },
+    Shutdown,
+}
+
+impl IpcEvent {
+    fn to_json(&self) -> Value {
+        match self {
+            IpcEvent::StartFile { path } => {
+                json!({ "event": "start-file", "path": path.to_string_lossy() })
+            }
+            IpcEvent::EndFile { reason } => json!({ "event": "end-file", "reason": reason }),
+            IpcEvent::Pause => json!({ "event": "pause" }),
+            IpcEvent::Unpause => json!({ "event": "unpause" }),
+            IpcEvent::Seek { position } => json!({ "event": "seek", "position": position }),
+            IpcEvent::Shutdown => json!({ "event": "shutdown" }),
+        }
+    }
+}
+
+/// 一条等待主线程执行的命令，执行后通过 reply 把结果写回客户端
+pub struct IpcRequest {
+    pub command: IpcCommand,
+    reply_sender: smol::channel::Sender<Result<Value, String>>,
+}
+
+impl IpcRequest {
+    /// 创建请求和接收执行结果的通道，HTTP 服务器也通过它把命令交给主线程
+    pub fn new(command: IpcCommand) -> (Self, smol::channel::Receiver<Result<Value, String>>) {
+        let (reply_sender, reply_receiver) = smol::channel::bounded(1);
+        let request = Self {
+            command,
+            reply_sender,
+        };
+        (request, reply_receiver)
+    }
+
+    pub fn reply(self, result: Result<Value, String>) {
+        // 客户端已断开时忽略
+        let _ = self.reply_sender.try_send(result);
+    }
+}
+
+/// Unix 域套接字控制服务器，在自己的线程里接受连接和收发数据，
+/// 命令交给渲染循环在主线程执行，事件由主线程推送
+pub struct IpcServer {
+    path: PathBuf,
+    request_receiver: smol::channel::Receiver<IpcRequest>,
+    event_sender: smol::channel::Sender<Value>,
+    server_thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl IpcServer {
+    pub fn start(path: &Path) -> Result<Self, anyhow::Error> {
+        // 上次异常退出留下的套接字文件会导致 bind 失败
+        if path.exists() {
+            std::fs::remove_file(path)
+                .with_context(|| format!("无法删除旧的 IPC 套接字 {:?}", path))?;
+        }
+        let listener = std::os::unix::net::UnixListener::bind(path)
+            .with_context(|| format!("无法监听 IPC 套接字 {:?}", path))?;
+        tracing::info!("IPC 服务器监听: {:?}", path);
+
+        let (request_sender, request_receiver) = smol::channel::unbounded();
+        let (event_sender, event_receiver) = smol::channel::unbounded::<Value>();
+
+        let server_thread = std::thread::Builder::new()
+            .name("ipc server thread".into())
+            .spawn(move || {
+                let executor = smol::LocalExecutor::new();
+                smol::block_on(executor.run(async {
+                    let listener = match UnixListener::try_from(listener) {
+                        Ok(listener) => listener,
+                        Err(e) => {
+                            tracing::error!("IPC 套接字初始化失败: {}", e);
+                            return;
+                        }
+                    };
+                    let clients: Rc<RefCell<Vec<smol::channel::Sender<Value>>>> =
+                        Rc::new(RefCell::new(Vec::new()));
+
+                    let accept_loop = async {
+                        loop {
+                            let stream = match listener.accept().await {
+                                Ok((stream, _)) => stream,
+                                Err(e) => {
+                                    tracing::error!("接受 IPC 连接失败: {}", e);
+                                    continue;
+                                }
+                            };
+                            tracing::info!("IPC 客户端已连接");
+                            let (client_sender, client_receiver) = smol::channel::unbounded();
+                            clients.borrow_mut().push(client_sender);
+                            executor
+                                .spawn(serve_client(
+                                    stream,
+                                    request_sender.clone(),
+                                    client_receiver,
+                                ))
+                                .detach();
+                        }
+                    };
+
+                    // 事件通道关闭（IpcServer 被 drop）时结束整个服务器
+                    let broadcast_loop = async {
+                        while let Ok(event) = event_receiver.recv().await {
+                            clients
+                                .borrow_mut()
+                                .retain(|client| client.try_send(event.clone()).is_ok());
+                        }
+                    };
+
+                    smol::future::or(accept_loop, broadcast_loop).await;
+                    tracing::info!("IPC 服务器退出");
+                }));
+            })?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            request_receiver,
+            event_sender,
+            server_thread: Some(server_thread),
+        })
+    }
+
+    /// 取出一条待执行的命令，没有时立即返回 None
+    pub fn try_recv(&self) -> Option<IpcRequest> {
+        self.request_receiver.try_recv().ok()
+    }
+
+    pub fn broadcast(&self, event: IpcEvent) {
+        tracing::debug!("IPC 事件: {:?}", event);
+        if let Err(e) = self.event_sender.try_send(event.to_json()) {
+            tracing::error!("发送 IPC 事件失败: {}", e);
+        }
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        tracing::info!("IpcServer drop");
+        self.event_sender.close();
+        if let Some(server_thread) = self.server_thread.take() {
+            server_thread.join().unwrap();
+        }
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            tracing::warn!("删除 IPC 套接字失败: {}", e);
+        }
+    }
+}
+
+/// 逐行读取客户端命令并写回响应，同时把事件推送给该客户端
+async fn serve_client(
+    stream: UnixStream,
+    request_sender: smol::channel::Sender<IpcRequest>,
+    event_receiver: smol::channel::Receiver<Value>,
+) {
+    let mut lines = BufReader::new(stream.clone()).lines();
+    let mut writer = stream;
+
+    loop {
+        let message = futures::select! {
+            line = lines.next().fuse() => match line {
+                Some(Ok(line)) if line.trim().is_empty() => continue,
+                Some(Ok(line)) => handle_line(&line, &request_sender).await,
+                Some(Err(e)) => {
+                    tracing::warn!("读取 IPC 命令失败: {}", e);
+                    break;
+                }
+                None => break,
+            },
+            event = event_receiver.recv().fuse() => match event {
+                Ok(event) => event,
+                Err(_) => break,
+            },
+        };
+
+        let mut text = message.to_string();
+        text.push('\n');
+        if let Err(e) = writer.write_all(text.as_bytes()).await {
+            tracing::warn!("写入 IPC 响应失败: {}", e);
+            break;
+        }
+    }
+    tracing::info!("IPC 客户端已断开");
+}
+
+async fn handle_line(line: &str, request_sender: &smol::channel::Sender<IpcRequest>) -> Value {
+    let (command, request_id) = match parse_request(line) {
+        Ok(request) => request,
+        Err(e) => {
+            tracing::warn!("无效的 IPC 命令 {}: {}", line, e);
+            return json!({ "error": e });
+        }
+    };
+    tracing::info!("IPC 命令: {:?}", command);
+
+    let (request, reply_receiver) = IpcRequest::new(command);
+    let result = match request_sender.send(request).await {
+        Ok(()) => reply_receiver
+            .recv()
+            .await
+            .unwrap_or_else(|_| Err(String::from("播放器已退出"))),
+        Err(_) => Err(String::from("播放器已退出")),
+    };
+
+    let mut response = match result {
+        Ok(data) => json!({ "error": "success", "data": data }),
+        Err(e) => json!({ "error": e }),
+    };
+    if let Some(request_id) = request_id {
+        response["request_id"] = request_id;
+    }
+    response
+}
+
+/// 解析一行请求，返回命令和客户端提供的 request_id
+pub fn parse_request(line: &str) -> Result<(IpcCommand, Option<Value>), String> {
+    let request: Value = serde_json::from_str(line).map_err(|e| format!("JSON 格式错误: {}", e))?;
+    let request_id = request.get("request_id").cloned();
+    let Some(arguments) = request.get("command").and_then(Value::as_array) else {
+        return Err(String::from("缺少 command 数组"));
+    };
+    let Some(name) = arguments.first().and_then(Value::as_str) else {
+        return Err(String::from("command 的第一个元素必须是命令名"));
+    };
+    let argument = |index: usize| arguments.get(index);
+    let string_argument = |index: usize| {
+        argument(index)
+            .and_then(Value::as_str)
+            .ok_or_else(|| format!("{} 的第 {} 个参数必须是字符串", name, index))
+    };
+    let number_argument = |index: usize| {
+        argument(index)
+            .and_then(Value::as_f64)
+            .ok_or_else(|| format!("{} 的第 {} 个参数必须是数字", name, index))
+    };
+
+    let command = match name {
+        "loadfile" => IpcCommand::LoadFile {
+            path: PathBuf::from(string_argument(1)?),
+            mode: match argument(2).and_then(Value::as_str).unwrap_or("replace") {
+                "replace" => LoadMode::Replace,
+                "append" => LoadMode::Append,
+                mode => return Err(format!("无效的 loadfile 模式: {}", mode)),
+            },
+        },
+        "play" => IpcCommand::Play,
+        "pause" => IpcCommand::Pause,
+        "seek" => IpcCommand::Seek {
+            seconds: number_argument(1)?,
+            mode: match argument(2).and_then(Value::as_str).unwrap_or("relative") {
+                "relative" => SeekMode::Relative,
+                "absolute" => SeekMode::Absolute,
+                mode => return Err(format!("无效的 seek 模式: {}", mode)),
+            },
+        },
+        "set_volume" => IpcCommand::SetVolume(number_argument(1)?),
+        "set_speed" => IpcCommand::SetSpeed(number_argument(1)?),
+        "screenshot" => IpcCommand::Screenshot {
+            window: argument(1).and_then(Value::as_str) == Some("window"),
+        },
+        "quit" => IpcCommand::Quit,
+        "get_property" => IpcCommand::GetProperty(string_argument(1)?.to_string()),
+        "set_property" => IpcCommand::SetProperty(
+            string_argument(1)?.to_string(),
+            argument(2)
+                .cloned()
+                .ok_or_else(|| String::from("set_property 缺少属性值"))?,
+        ),
+        _ => return Err(format!("未知命令: {}", name)),
+    };
+    Ok((command, request_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_commands_with_defaults() {
+        let (command, request_id) =
+            parse_request(r#"{"command": ["seek", 10, "absolute"], "request_id": 7}"#).unwrap();
+        assert_eq!(command, IpcCommand::Seek { seconds: 10.0, mode: SeekMode::Absolute });
+        assert_eq!(request_id, Some(json!(7)));
+
+        let (command, request_id) = parse_request(r#"{"command": ["seek", -2.5]}"#).unwrap();
+        assert_eq!(command, IpcCommand::Seek { seconds: -2.5, mode: SeekMode::Relative });
+        assert_eq!(request_id, None);
+
+        let (command, _) = parse_request(r#"{"command": ["loadfile", "a.mkv"]}"#).unwrap();
+        assert_eq!(
+            command,
+            IpcCommand::LoadFile { path: PathBuf::from("a.mkv"), mode: LoadMode::Replace }
+        );
+        let (command, _) = parse_request(r#"{"command": ["screenshot", "window"]}"#).unwrap();
+        assert_eq!(command, IpcCommand::Screenshot { window: true });
+        let (command, _) = parse_request(r#"{"command": ["screenshot"]}"#).unwrap();
+        assert_eq!(command, IpcCommand::Screenshot { window: false });
+    }
+
+    #[test]
+    fn parses_properties() {
+        let line = r#"{"command": ["set_property", "pause", true], "request_id": "a"}"#;
+        let (command, request_id) = parse_request(line).unwrap();
+        assert_eq!(command, IpcCommand::SetProperty(String::from("pause"), json!(true)));
+        assert_eq!(request_id, Some(json!("a")));
+
+        let (command, _) = parse_request(r#"{"command": ["get_property", "volume"]}"#).unwrap();
+        assert_eq!(command, IpcCommand::GetProperty(String::from("volume")));
+    }
+
+    #[test]
+    fn rejects_malformed_requests() {
+        let error_of = |line| parse_request(line).unwrap_err();
+        assert!(error_of("{").starts_with("JSON 格式错误"));
+        assert_eq!(error_of(r#"{"command": "pause"}"#), "缺少 command 数组");
+        assert_eq!(error_of(r#"{"command": []}"#), "command 的第一个元素必须是命令名");
+        assert_eq!(error_of(r#"{"command": ["rewind"]}"#), "未知命令: rewind");
+        assert_eq!(error_of(r#"{"command": ["seek", "10"]}"#), "seek 的第 1 个参数必须是数字");
+        assert_eq!(error_of(r#"{"command": ["seek", 10, "frame"]}"#), "无效的 seek 模式: frame");
+        assert_eq!(
+            error_of(r#"{"command": ["loadfile", "a.mkv", "insert"]}"#),
+            "无效的 loadfile 模式: insert"
+        );
+        assert_eq!(
+            error_of(r#"{"command": ["get_property", 1]}"#),
+            "get_property 的第 1 个参数必须是字符串"
+        );
+        assert_eq!(
+            error_of(r#"{"command": ["set_property", "pause"]}"#),
+            "set_property 缺少属性值"
+        );
+    }
+}
@@ -0,0 +1,469 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use glium::glutin::event::{ModifiersState, VirtualKeyCode};
+
+/// 可以绑定到按键上的操作，配置文件中使用 name() 返回的名称
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    TogglePause,
+    CycleScaleMode,
+    ToggleFullscreen,
+    ExitFullscreen,
+    ToggleAlwaysOnTop,
+    /// 按视频尺寸的 50%、100%、200% 设置窗口大小
+    WindowScaleHalf,
+    WindowScaleOriginal,
+    WindowScaleDouble,
+    AudioDelayIncrease,
+    AudioDelayDecrease,
+    ZoomIn,
+    ZoomOut,
+    ResetView,
+    /// 源分辨率截图
+    Screenshot,
+    /// 截取窗口画面，包括字幕和 OSD
+    ScreenshotWindow,
+    ToggleSubtitles,
+    SubtitleDelayDecrease,
+    SubtitleDelayIncrease,
+    CycleAudioTrack,
+    CycleSubtitleTrack,
+    SetLoopA,
+    SetLoopB,
+    ToggleFileLoop,
+    PlaylistNext,
+    PlaylistPrevious,
+    CycleRepeat,
+    ToggleShuffle,
+    ToggleStats,
+    CycleDeinterlace,
+    CycleVisualization,
+    SeekToStart,
+}
+
+const ACTIONS: &[Action] = &[
+    Action::TogglePause,
+    Action::CycleScaleMode,
+    Action::ToggleFullscreen,
+    Action::ExitFullscreen,
+    Action::ToggleAlwaysOnTop,
+    Action::WindowScaleHalf,
+    Action::WindowScaleOriginal,
+    Action::WindowScaleDouble,
+    Action::AudioDelayIncrease,
+    Action::AudioDelayDecrease,
+    Action::ZoomIn,
+    Action::ZoomOut,
+    Action::ResetView,
+    Action::Screenshot,
+    Action::ScreenshotWindow,
+    Action::ToggleSubtitles,
+    Action::SubtitleDelayDecrease,
+    Action::SubtitleDelayIncrease,
+    Action::CycleAudioTrack,
+    Action::CycleSubtitleTrack,
+    Action::SetLoopA,
+    Action::SetLoopB,
+    Action::ToggleFileLoop,
+    Action::PlaylistNext,
+    Action::PlaylistPrevious,
+    Action::CycleRepeat,
+    Action::ToggleShuffle,
+    Action::ToggleStats,
+    Action::CycleDeinterlace,
+    Action::CycleVisualization,
+    Action::SeekToStart,
+];
+
+impl Action {
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::TogglePause => "toggle-pause",
+            Action::CycleScaleMode => "cycle-scale-mode",
+            Action::ToggleFullscreen => "toggle-fullscreen",
+            Action::ExitFullscreen => "exit-fullscreen",
+            Action::ToggleAlwaysOnTop => "toggle-always-on-top",
+            Action::WindowScaleHalf => "window-scale-50",
+            Action::WindowScaleOriginal => "window-scale-100",
+            Action::WindowScaleDouble => "window-scale-200",
+            Action::AudioDelayIncrease => "audio-delay-increase",
+            Action::AudioDelayDecrease => "audio-delay-decrease",
+            Action::ZoomIn => "zoom-in",
+            Action::ZoomOut => "zoom-out",
+            Action::ResetView => "reset-view",
+            Action::Screenshot => "screenshot",
+            Action::ScreenshotWindow => "screenshot-window",
+            Action::ToggleSubtitles => "toggle-subtitles",
+            Action::SubtitleDelayDecrease => "subtitle-delay-decrease",
+            Action::SubtitleDelayIncrease => "subtitle-delay-increase",
+            Action::CycleAudioTrack => "cycle-audio-track",
+            Action::CycleSubtitleTrack => "cycle-subtitle-track",
+            Action::SetLoopA => "set-loop-a",
+            Action::SetLoopB => "set-loop-b",
+            Action::ToggleFileLoop => "toggle-file-loop",
+            Action::PlaylistNext => "playlist-next",
+            Action::PlaylistPrevious => "playlist-prev",
+            Action::CycleRepeat => "cycle-repeat",
+            Action::ToggleShuffle => "toggle-shuffle",
+            Action::ToggleStats => "toggle-stats",
+            Action::CycleDeinterlace => "cycle-deinterlace",
+            Action::CycleVisualization => "cycle-visualization",
+            Action::SeekToStart => "seek-to-start",
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        ACTIONS
+            .iter()
+            .copied()
+            .find(|action| action.name() == name)
+            .ok_or_else(|| format!("未知的操作: {}", s))
+    }
+}
+
+/// 按键名称，配置文件中不区分大小写
+const KEY_NAMES: &[(&str, VirtualKeyCode)] = &[
+    ("A", VirtualKeyCode::A),
+    ("B", VirtualKeyCode::B),
+    ("C", VirtualKeyCode::C),
+    ("D", VirtualKeyCode::D),
+    ("E", VirtualKeyCode::E),
+    ("F", VirtualKeyCode::F),
+    ("G", VirtualKeyCode::G),
+    ("H", VirtualKeyCode::H),
+    ("I", VirtualKeyCode::I),
+    ("J", VirtualKeyCode::J),
+    ("K", VirtualKeyCode::K),
+    ("L", VirtualKeyCode::L),
+    ("M", VirtualKeyCode::M),
+    ("N", VirtualKeyCode::N),
+    ("O", VirtualKeyCode::O),
+    ("P", VirtualKeyCode::P),
+    ("Q", VirtualKeyCode::Q),
+    ("R", VirtualKeyCode::R),
+    ("S", VirtualKeyCode::S),
+    ("T", VirtualKeyCode::T),
+    ("U", VirtualKeyCode::U),
+    ("V", VirtualKeyCode::V),
+    ("W", VirtualKeyCode::W),
+    ("X", VirtualKeyCode::X),
+    ("Y", VirtualKeyCode::Y),
+    ("Z", VirtualKeyCode::Z),
+    ("0", VirtualKeyCode::Key0),
+    ("1", VirtualKeyCode::Key1),
+    ("2", VirtualKeyCode::Key2),
+    ("3", VirtualKeyCode::Key3),
+    ("4", VirtualKeyCode::Key4),
+    ("5", VirtualKeyCode::Key5),
+    ("6", VirtualKeyCode::Key6),
+    ("7", VirtualKeyCode::Key7),
+    ("8", VirtualKeyCode::Key8),
+    ("9", VirtualKeyCode::Key9),
+    ("F1", VirtualKeyCode::F1),
+    ("F2", VirtualKeyCode::F2),
+    ("F3", VirtualKeyCode::F3),
+    ("F4", VirtualKeyCode::F4),
+    ("F5", VirtualKeyCode::F5),
+    ("F6", VirtualKeyCode::F6),
+    ("F7", VirtualKeyCode::F7),
+    ("F8", VirtualKeyCode::F8),
+    ("F9", VirtualKeyCode::F9),
+    ("F10", VirtualKeyCode::F10),
+    ("F11", VirtualKeyCode::F11),
+    ("F12", VirtualKeyCode::F12),
+    ("Space", VirtualKeyCode::Space),
+    ("Esc", VirtualKeyCode::Escape),
+    ("Enter", VirtualKeyCode::Return),
+    ("Tab", VirtualKeyCode::Tab),
+    ("Backspace", VirtualKeyCode::Back),
+    ("Insert", VirtualKeyCode::Insert),
+    ("Delete", VirtualKeyCode::Delete),
+    ("Home", VirtualKeyCode::Home),
+    ("End", VirtualKeyCode::End),
+    ("PageUp", VirtualKeyCode::PageUp),
+    ("PageDown", VirtualKeyCode::PageDown),
+    ("Left", VirtualKeyCode::Left),
+    ("Right", VirtualKeyCode::Right),
+    ("Up", VirtualKeyCode::Up),
+    ("Down", VirtualKeyCode::Down),
+    ("Plus", VirtualKeyCode::Plus),
+    ("Equals", VirtualKeyCode::Equals),
+    ("Minus", VirtualKeyCode::Minus),
+    ("[", VirtualKeyCode::LBracket),
+    ("]", VirtualKeyCode::RBracket),
+    (",", VirtualKeyCode::Comma),
+    (".", VirtualKeyCode::Period),
+    ("/", VirtualKeyCode::Slash),
+    ("\\", VirtualKeyCode::Backslash),
+    (";", VirtualKeyCode::Semicolon),
+    ("'", VirtualKeyCode::Apostrophe),
+    ("`", VirtualKeyCode::Grave),
+    ("KP0", VirtualKeyCode::Numpad0),
+    ("KP1", VirtualKeyCode::Numpad1),
+    ("KP2", VirtualKeyCode::Numpad2),
+    ("KP3", VirtualKeyCode::Numpad3),
+    ("KP4", VirtualKeyCode::Numpad4),
+    ("KP5", VirtualKeyCode::Numpad5),
+    ("KP6", VirtualKeyCode::Numpad6),
+    ("KP7", VirtualKeyCode::Numpad7),
+    ("KP8", VirtualKeyCode::Numpad8),
+    ("KP9", VirtualKeyCode::Numpad9),
+    ("KPAdd", VirtualKeyCode::NumpadAdd),
+    ("KPSubtract", VirtualKeyCode::NumpadSubtract),
+    ("KPEnter", VirtualKeyCode::NumpadEnter),
+];
+
+/// 一个按键加上修饰键，写作 `Ctrl+Shift+S` 这样的形式
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub key: VirtualKeyCode,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl KeyChord {
+    pub fn new(key: VirtualKeyCode) -> Self {
+        Self {
+            key,
+            ctrl: false,
+            shift: false,
+            alt: false,
+        }
+    }
+
+    fn ctrl(self) -> Self {
+        Self { ctrl: true, ..self }
+    }
+
+    fn shift(self) -> Self {
+        Self { shift: true, ..self }
+    }
+
+    fn with_modifiers(key: VirtualKeyCode, modifiers: ModifiersState) -> Self {
+        Self {
+            key,
+            ctrl: modifiers.ctrl(),
+            shift: modifiers.shift(),
+            alt: modifiers.alt(),
+        }
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // 最后一段是按键本身，按键也可以是 "+"
+        let (modifiers, key) = if s.trim() == "+" {
+            ("", "+")
+        } else if let Some(modifiers) = s.strip_suffix("++") {
+            (modifiers, "+")
+        } else {
+            s.rsplit_once('+').unwrap_or(("", s))
+        };
+        let key = match key.trim() {
+            "+" => VirtualKeyCode::Plus,
+            "-" => VirtualKeyCode::Minus,
+            "=" => VirtualKeyCode::Equals,
+            key => KEY_NAMES
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(key))
+                .map(|&(_, code)| code)
+                .ok_or_else(|| format!("未知的按键: {}", key))?,
+        };
+        let mut chord = KeyChord::new(key);
+        for modifier in modifiers.split('+').map(str::trim).filter(|m| !m.is_empty()) {
+            match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => chord.ctrl = true,
+                "shift" => chord.shift = true,
+                "alt" => chord.alt = true,
+                _ => return Err(format!("未知的修饰键: {}，可选 Ctrl、Shift、Alt", modifier)),
+            }
+        }
+        Ok(chord)
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.shift {
+            write!(f, "Shift+")?;
+        }
+        if self.alt {
+            write!(f, "Alt+")?;
+        }
+        match KEY_NAMES.iter().find(|&&(_, code)| code == self.key) {
+            Some((name, _)) => write!(f, "{}", name),
+            None => write!(f, "{:?}", self.key),
+        }
+    }
+}
+
+/// 按键到操作的映射，配置文件的 [bindings] 表在默认绑定的基础上增加或覆盖
+#[derive(Clone, Debug)]
+pub struct KeyBindings {
+    bindings: HashMap<KeyChord, Action>,
+}
+
+impl KeyBindings {
+    /// 查找按键对应的操作。组合键没有绑定时先忽略 Shift（有些键盘上输入 + 需要按 Shift），
+    /// 再按不带修饰键的按键查找
+    pub fn action(&self, key: VirtualKeyCode, modifiers: ModifiersState) -> Option<Action> {
+        let chord = KeyChord::with_modifiers(key, modifiers);
+        [chord, KeyChord { shift: false, ..chord }, KeyChord::new(key)]
+            .iter()
+            .find_map(|chord| self.bindings.get(chord))
+            .copied()
+    }
+
+    /// 绑定按键，action 为 None 时解除该按键原有的绑定
+    pub fn bind(&mut self, chord: KeyChord, action: Option<Action>) {
+        match action {
+            Some(action) => self.bindings.insert(chord, action),
+            None => self.bindings.remove(&chord),
+        };
+    }
+
+    /// 绑定到 action 的按键中最简短的一个，用于 OSD 提示
+    pub fn key_for(&self, action: Action) -> Option<KeyChord> {
+        self.bindings
+            .iter()
+            .filter(|(_, &bound)| bound == action)
+            .map(|(&chord, _)| chord)
+            .min_by_key(|chord| {
+                let name = chord.to_string();
+                (name.len(), name)
+            })
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        use VirtualKeyCode::*;
+
+        let key = KeyChord::new;
+        let defaults = [
+            (key(Space), Action::TogglePause),
+            (key(M), Action::CycleScaleMode),
+            (key(F), Action::ToggleFullscreen),
+            (key(Escape), Action::ExitFullscreen),
+            (key(T), Action::ToggleAlwaysOnTop),
+            (key(Key0), Action::WindowScaleHalf),
+            (key(Key1), Action::WindowScaleOriginal),
+            (key(Key2), Action::WindowScaleDouble),
+            (key(Equals).ctrl(), Action::AudioDelayIncrease),
+            (key(Plus).ctrl(), Action::AudioDelayIncrease),
+            (key(NumpadAdd).ctrl(), Action::AudioDelayIncrease),
+            (key(Minus).ctrl(), Action::AudioDelayDecrease),
+            (key(NumpadSubtract).ctrl(), Action::AudioDelayDecrease),
+            (key(Equals), Action::ZoomIn),
+            (key(Plus), Action::ZoomIn),
+            (key(NumpadAdd), Action::ZoomIn),
+            (key(Minus), Action::ZoomOut),
+            (key(NumpadSubtract), Action::ZoomOut),
+            (key(Back), Action::ResetView),
+            (key(S), Action::Screenshot),
+            (key(S).shift(), Action::ScreenshotWindow),
+            (key(V), Action::ToggleSubtitles),
+            (key(Z), Action::SubtitleDelayDecrease),
+            (key(X), Action::SubtitleDelayIncrease),
+            (key(A), Action::CycleAudioTrack),
+            (key(J), Action::CycleSubtitleTrack),
+            (key(LBracket), Action::SetLoopA),
+            (key(RBracket), Action::SetLoopB),
+            (key(L), Action::ToggleFileLoop),
+            (key(N), Action::PlaylistNext),
+            (key(P), Action::PlaylistPrevious),
+            (key(R), Action::CycleRepeat),
+            (key(H), Action::ToggleShuffle),
+            (key(I), Action::ToggleStats),
+            (key(D), Action::CycleDeinterlace),
+            (key(W), Action::CycleVisualization),
+            (key(Home), Action::SeekToStart),
+        ];
+        Self {
+            bindings: defaults.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_modifiers_and_key_names() {
+        let chord: KeyChord = "ctrl+Shift+s".parse().unwrap();
+        assert_eq!(chord, KeyChord::new(VirtualKeyCode::S).ctrl().shift());
+        assert_eq!(chord.to_string(), "Ctrl+Shift+S");
+
+        let chord: KeyChord = " Alt + kpadd ".parse().unwrap();
+        let expected = KeyChord::new(VirtualKeyCode::NumpadAdd);
+        assert_eq!(chord, KeyChord { alt: true, ..expected });
+        assert_eq!(chord.to_string().parse::<KeyChord>(), Ok(chord));
+    }
+
+    #[test]
+    fn parses_plus_as_key() {
+        assert_eq!("+".parse(), Ok(KeyChord::new(VirtualKeyCode::Plus)));
+        assert_eq!("Ctrl++".parse(), Ok(KeyChord::new(VirtualKeyCode::Plus).ctrl()));
+        assert_eq!("Ctrl+-".parse(), Ok(KeyChord::new(VirtualKeyCode::Minus).ctrl()));
+        assert_eq!("=".parse(), Ok(KeyChord::new(VirtualKeyCode::Equals)));
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_modifiers() {
+        let error = "Ctrl+Foo".parse::<KeyChord>().unwrap_err();
+        assert!(error.contains("未知的按键: Foo"), "{}", error);
+        let error = "Super+S".parse::<KeyChord>().unwrap_err();
+        assert!(error.contains("未知的修饰键: Super"), "{}", error);
+        assert!("".parse::<KeyChord>().is_err());
+    }
+
+    #[test]
+    fn action_names_round_trip() {
+        for &action in ACTIONS {
+            assert_eq!(action.name().parse(), Ok(action));
+        }
+        assert_eq!(" Toggle-Pause ".parse(), Ok(Action::TogglePause));
+        assert!("pause".parse::<Action>().is_err());
+    }
+
+    #[test]
+    fn lookup_falls_back_to_key_without_modifiers() {
+        let bindings = KeyBindings::default();
+        let action = |key, modifiers| bindings.action(key, modifiers);
+        let ctrl_shift = ModifiersState::CTRL | ModifiersState::SHIFT;
+        let shift = ModifiersState::SHIFT;
+        assert_eq!(action(VirtualKeyCode::S, shift), Some(Action::ScreenshotWindow));
+        assert_eq!(action(VirtualKeyCode::Space, ModifiersState::ALT), Some(Action::TogglePause));
+        // 有些键盘上输入 + 需要按 Shift
+        assert_eq!(action(VirtualKeyCode::Plus, ctrl_shift), Some(Action::AudioDelayIncrease));
+        assert_eq!(action(VirtualKeyCode::Plus, ModifiersState::empty()), Some(Action::ZoomIn));
+    }
+
+    #[test]
+    fn bind_overrides_and_unbinds() {
+        let mut bindings = KeyBindings::default();
+        let empty = ModifiersState::empty();
+        bindings.bind(KeyChord::new(VirtualKeyCode::Space), None);
+        assert_eq!(bindings.action(VirtualKeyCode::Space, empty), None);
+
+        // P 默认绑定到上一项，覆盖后原来的操作不再能通过 P 触发
+        let p = KeyChord::new(VirtualKeyCode::P);
+        bindings.bind(p, Some(Action::TogglePause));
+        assert_eq!(bindings.action(VirtualKeyCode::P, empty), Some(Action::TogglePause));
+        assert_eq!(bindings.key_for(Action::TogglePause), Some(p));
+    }
+}
@@ -3,5 +3,23 @@ extern crate ffmpeg_next as ffmpeg;
 pub mod player;
 pub mod video;
 pub mod audio;
+pub mod clock;
+pub mod screenshot;
+pub mod subtitle;
+pub mod subtitle_file;
+pub mod playlist;
+pub mod stats;
+pub mod ipc;
+pub mod http;
+pub mod filter;
+pub mod deinterlace;
+pub mod loudness;
+pub mod visualization;
+pub mod paths;
+pub mod file_state;
+pub mod probe;
+pub mod thumbnail;
 
-pub use player::{Player, ControlCommand};
\ No newline at end of file
+pub use player::{Player, PlayerOptions, ControlCommand, LoopMode};
+pub use clock::PlaybackClock;
+pub use probe::{probe, MediaInfo};
\ No newline at end of file
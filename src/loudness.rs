@@ -0,0 +1,384 @@
+use std::f64::consts::PI;
+use std::str::FromStr;
+
+/// 输出端支持的最多声道数，音频回调按帧处理时用栈上数组暂存一帧采样
+pub const MAX_CHANNELS: usize = 8;
+
+/// ReplayGain 增益的参考响度，R128 标签以 -23 LUFS 为参考，两者相差 5 dB
+const R128_TO_REPLAYGAIN_DB: f32 = 5.0;
+/// 响度标准化的最大增益和最大衰减
+const MAX_NORMALIZE_GAIN_DB: f32 = 12.0;
+/// 标准化增益跟随测量结果变化的时间常数，避免响度起伏时音量忽大忽小
+const NORMALIZE_TIME_CONSTANT: f64 = 2.0;
+/// 峰值限制器的阈值（-1 dBFS）和释放时间
+const LIMITER_THRESHOLD: f32 = 0.891_250_9;
+const LIMITER_RELEASE: f64 = 0.1;
+/// EBU R128：400 ms 测量块，每 100 ms 一个，低于 -70 LUFS 的块不计入整体响度
+const SUBBLOCKS_PER_BLOCK: usize = 4;
+const ABSOLUTE_GATE: f64 = -70.0;
+const RELATIVE_GATE: f64 = -10.0;
+/// 块响度直方图：-70 到 +10 LUFS，每格 0.1 LU，在回调里统计时不需要分配内存
+const HISTOGRAM_BINS: usize = 800;
+const HISTOGRAM_STEP: f64 = 0.1;
+
+/// 使用 ReplayGain 标签的方式，album 模式在没有专辑增益时退回音轨增益
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReplayGainMode {
+    Off,
+    #[default]
+    Track,
+    Album,
+}
+
+impl FromStr for ReplayGainMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "off" | "no" => Ok(ReplayGainMode::Off),
+            "track" => Ok(ReplayGainMode::Track),
+            "album" => Ok(ReplayGainMode::Album),
+            _ => Err(format!("无效的 ReplayGain 模式: {}，可选 off、track、album", s)),
+        }
+    }
+}
+
+/// 从容器和音频流元数据中读到的 ReplayGain 标签，增益以 dB 为单位，峰值为线性幅度
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ReplayGain {
+    pub track_gain: Option<f32>,
+    pub track_peak: Option<f32>,
+    pub album_gain: Option<f32>,
+    pub album_peak: Option<f32>,
+}
+
+impl ReplayGain {
+    /// 读取一组元数据标签，后读到的覆盖先读到的，因此先读容器再读音频流；
+    /// 支持 REPLAYGAIN_* 标签和 Opus 的 R128_*_GAIN 标签（Q7.8 定点数，参考 -23 LUFS）
+    pub fn read<'a>(&mut self, tags: impl Iterator<Item = (&'a str, &'a str)>) {
+        for (key, value) in tags {
+            match key.to_ascii_uppercase().as_str() {
+                "REPLAYGAIN_TRACK_GAIN" => self.track_gain = parse_gain(value).or(self.track_gain),
+                "REPLAYGAIN_TRACK_PEAK" => self.track_peak = parse_peak(value).or(self.track_peak),
+                "REPLAYGAIN_ALBUM_GAIN" => self.album_gain = parse_gain(value).or(self.album_gain),
+                "REPLAYGAIN_ALBUM_PEAK" => self.album_peak = parse_peak(value).or(self.album_peak),
+                "R128_TRACK_GAIN" => self.track_gain = parse_r128(value).or(self.track_gain),
+                "R128_ALBUM_GAIN" => self.album_gain = parse_r128(value).or(self.album_gain),
+                _ => {}
+            }
+        }
+    }
+
+    /// 按模式选出的增益加上前置放大，有峰值标签时限制增益使峰值不超过满幅；
+    /// 没有可用的标签时返回 None
+    pub fn gain_db(&self, mode: ReplayGainMode, preamp: f32) -> Option<f32> {
+        let track = self.track_gain.map(|gain| (gain, self.track_peak));
+        let album = self.album_gain.map(|gain| (gain, self.album_peak));
+        let (gain, peak) = match mode {
+            ReplayGainMode::Off => return None,
+            ReplayGainMode::Track => track.or(album)?,
+            ReplayGainMode::Album => album.or(track)?,
+        };
+        let gain = gain + preamp;
+        Some(match peak {
+            Some(peak) => gain.min(-linear_to_db(peak)),
+            None => gain,
+        })
+    }
+}
+
+fn parse_gain(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let value = value
+        .strip_suffix("dB")
+        .or_else(|| value.strip_suffix("db"))
+        .or_else(|| value.strip_suffix("DB"))
+        .unwrap_or(value);
+    value.trim().parse().ok().filter(|gain: &f32| gain.is_finite())
+}
+
+fn parse_peak(value: &str) -> Option<f32> {
+    value.trim().parse().ok().filter(|peak: &f32| peak.is_finite() && *peak > 0.0)
+}
+
+fn parse_r128(value: &str) -> Option<f32> {
+    let gain: i32 = value.trim().parse().ok()?;
+    Some(gain as f32 / 256.0 + R128_TO_REPLAYGAIN_DB)
+}
+
+pub fn db_to_linear(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+pub fn linear_to_db(gain: f32) -> f32 {
+    20.0 * gain.log10()
+}
+
+/// 音频回调使用的增益来源
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LoudnessGain {
+    /// 固定增益（dB），来自 ReplayGain 标签，没有标签且未开启标准化时为 0
+    Fixed(f32),
+    /// 按实时测得的整体响度把音量拉向目标响度（LUFS）
+    Normalize(f32),
+}
+
+/// 输出端的响度处理：测量 EBU R128 响度，应用 ReplayGain 或标准化增益和音量，
+/// 增益使信号超过满幅时由峰值限制器压住。在 cpal 回调里逐帧调用，不分配内存
+pub struct LoudnessProcessor {
+    meter: LoudnessMeter,
+    limiter: PeakLimiter,
+    /// 当前应用的线性增益，None 表示还没有处理过任何帧
+    gain: Option<f32>,
+    /// 目标增益及其对应的增益来源，来源变化或测出新的响度时重新计算
+    target_gain: f32,
+    target_source: Option<LoudnessGain>,
+    /// 标准化增益每个采样向目标靠近的比例
+    smoothing: f32,
+}
+
+impl LoudnessProcessor {
+    pub fn new(channels: usize, rate: u32) -> Self {
+        Self {
+            meter: LoudnessMeter::new(channels, rate),
+            limiter: PeakLimiter::new(rate),
+            gain: None,
+            target_gain: 1.0,
+            target_source: None,
+            smoothing: (1.0 - (-1.0 / (NORMALIZE_TIME_CONSTANT * rate.max(1) as f64)).exp()) as f32,
+        }
+    }
+
+    /// 处理一帧交错采样（每个声道一个采样）
+    pub fn process(&mut self, frame: &mut [f32], source: LoudnessGain, volume: f32) {
+        let block_finished = self.meter.add_frame(frame);
+        if block_finished || self.target_source != Some(source) {
+            self.target_gain = match source {
+                LoudnessGain::Fixed(db) => db_to_linear(db),
+                LoudnessGain::Normalize(target) => match self.meter.integrated() {
+                    Some(loudness) => db_to_linear(
+                        (target - loudness).clamp(-MAX_NORMALIZE_GAIN_DB, MAX_NORMALIZE_GAIN_DB),
+                    ),
+                    None => 1.0,
+                },
+            };
+            self.target_source = Some(source);
+        }
+
+        // 固定增益直接生效，标准化增益随测量结果缓慢变化
+        let gain = match (self.gain, source) {
+            (Some(gain), LoudnessGain::Normalize(_)) => {
+                gain + (self.target_gain - gain) * self.smoothing
+            }
+            _ => self.target_gain,
+        };
+        self.gain = Some(gain);
+
+        let gain = gain * volume;
+        if gain != 1.0 {
+            for sample in frame.iter_mut() {
+                *sample *= gain;
+            }
+        }
+        if gain > 1.0 || self.limiter.is_reducing() {
+            self.limiter.process(frame);
+        }
+    }
+
+    /// 到目前为止测得的整体响度（LUFS），还没有足够的非静音数据时返回 None
+    pub fn integrated(&self) -> Option<f32> {
+        self.meter.integrated()
+    }
+
+    /// 当前应用的 ReplayGain 或标准化增益（dB），不含音量
+    pub fn gain_db(&self) -> f32 {
+        linear_to_db(self.gain.unwrap_or(1.0))
+    }
+}
+
+/// 直接 II 型转置结构的二阶 IIR 滤波器
+#[derive(Clone, Copy, Default)]
+struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    z1: f64,
+    z2: f64,
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b0 * x + self.z1;
+        self.z1 = self.b1 * x - self.a1 * y + self.z2;
+        self.z2 = self.b2 * x - self.a2 * y;
+        y
+    }
+}
+
+/// ITU-R BS.1770 的 K 计权滤波器：高频搁架加高通，按实际采样率计算系数
+fn k_weighting(rate: f64) -> [Biquad; 2] {
+    // 第一级：模拟头部声学效应的高频搁架滤波器
+    let f0 = 1681.974450955533;
+    let gain = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b0: (vh + vb * k / q + k * k) / a0,
+        b1: 2.0 * (k * k - vh) / a0,
+        b2: (vh - vb * k / q + k * k) / a0,
+        a1: 2.0 * (k * k - 1.0) / a0,
+        a2: (1.0 - k / q + k * k) / a0,
+        ..Biquad::default()
+    };
+
+    // 第二级：RLB 高通滤波器
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad {
+        b0: 1.0,
+        b1: -2.0,
+        b2: 1.0,
+        a1: 2.0 * (k * k - 1.0) / a0,
+        a2: (1.0 - k / q + k * k) / a0,
+        ..Biquad::default()
+    };
+
+    [shelf, high_pass]
+}
+
+fn energy_to_lufs(energy: f64) -> f64 {
+    -0.691 + 10.0 * energy.log10()
+}
+
+/// 实时 EBU R128 整体响度测量，单声道和立体声的声道权重都是 1
+struct LoudnessMeter {
+    filters: Vec<[Biquad; 2]>,
+    /// 每个 100 ms 子块的帧数
+    subblock_frames: usize,
+    /// 当前子块已累计的帧数和 K 计权后的平方和
+    frames: usize,
+    sum: f64,
+    /// 最近四个子块的均方能量，组成一个 400 ms 测量块
+    subblocks: [f64; SUBBLOCKS_PER_BLOCK],
+    subblock_count: usize,
+    /// 超过绝对门限的测量块按响度分格统计的块数和能量和
+    histogram_counts: Box<[u32]>,
+    histogram_energy: Box<[f64]>,
+    integrated: Option<f32>,
+}
+
+impl LoudnessMeter {
+    fn new(channels: usize, rate: u32) -> Self {
+        let rate = rate.max(1);
+        Self {
+            filters: vec![k_weighting(rate as f64); channels.clamp(1, MAX_CHANNELS)],
+            subblock_frames: (rate as usize / 10).max(1),
+            frames: 0,
+            sum: 0.0,
+            subblocks: [0.0; SUBBLOCKS_PER_BLOCK],
+            subblock_count: 0,
+            histogram_counts: vec![0; HISTOGRAM_BINS].into_boxed_slice(),
+            histogram_energy: vec![0.0; HISTOGRAM_BINS].into_boxed_slice(),
+            integrated: None,
+        }
+    }
+
+    /// 累计一帧采样，完成一个测量块时返回 true
+    fn add_frame(&mut self, frame: &[f32]) -> bool {
+        for ([shelf, high_pass], &sample) in self.filters.iter_mut().zip(frame) {
+            let weighted = high_pass.process(shelf.process(sample as f64));
+            self.sum += weighted * weighted;
+        }
+        self.frames += 1;
+        if self.frames < self.subblock_frames {
+            return false;
+        }
+
+        self.subblocks[self.subblock_count % SUBBLOCKS_PER_BLOCK] = self.sum / self.frames as f64;
+        self.subblock_count += 1;
+        self.frames = 0;
+        self.sum = 0.0;
+        if self.subblock_count < SUBBLOCKS_PER_BLOCK {
+            return false;
+        }
+
+        let energy = self.subblocks.iter().sum::<f64>() / SUBBLOCKS_PER_BLOCK as f64;
+        let loudness = energy_to_lufs(energy);
+        if loudness >= ABSOLUTE_GATE {
+            let bin = Self::bin(loudness);
+            self.histogram_counts[bin] += 1;
+            self.histogram_energy[bin] += energy;
+            self.integrated = self.compute_integrated();
+        }
+        true
+    }
+
+    fn bin(loudness: f64) -> usize {
+        (((loudness - ABSOLUTE_GATE) / HISTOGRAM_STEP).max(0.0) as usize).min(HISTOGRAM_BINS - 1)
+    }
+
+    /// 两级门限：先用超过绝对门限的块求出相对门限，再对超过相对门限的块求平均能量
+    fn compute_integrated(&self) -> Option<f32> {
+        let count: u64 = self.histogram_counts.iter().map(|&count| count as u64).sum();
+        if count == 0 {
+            return None;
+        }
+        let energy: f64 = self.histogram_energy.iter().sum();
+        let relative_gate = energy_to_lufs(energy / count as f64) + RELATIVE_GATE;
+
+        let start = Self::bin(relative_gate);
+        let count: u64 = self.histogram_counts[start..].iter().map(|&count| count as u64).sum();
+        let energy: f64 = self.histogram_energy[start..].iter().sum();
+        (count > 0).then(|| energy_to_lufs(energy / count as f64) as f32)
+    }
+
+    fn integrated(&self) -> Option<f32> {
+        self.integrated
+    }
+}
+
+/// 瞬时启动、指数释放的峰值限制器，把所有声道的峰值限制在阈值以下
+struct PeakLimiter {
+    gain: f32,
+    /// 每个采样恢复剩余增益的比例
+    release: f32,
+}
+
+impl PeakLimiter {
+    fn new(rate: u32) -> Self {
+        Self {
+            gain: 1.0,
+            release: (1.0 - (-1.0 / (LIMITER_RELEASE * rate.max(1) as f64)).exp()) as f32,
+        }
+    }
+
+    fn is_reducing(&self) -> bool {
+        self.gain < 1.0
+    }
+
+    fn process(&mut self, frame: &mut [f32]) {
+        let peak = frame.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        let limit = if peak > LIMITER_THRESHOLD { LIMITER_THRESHOLD / peak } else { 1.0 };
+        if limit < self.gain {
+            self.gain = limit;
+        } else {
+            self.gain += (limit - self.gain) * self.release;
+            // 恢复到接近 1 时直接结束限制，避免长时间做无意义的乘法
+            if self.gain > 0.9999 {
+                self.gain = 1.0;
+            }
+        }
+        if self.gain < 1.0 {
+            for sample in frame.iter_mut() {
+                *sample *= self.gain;
+            }
+        }
+    }
+}
@@ -1,28 +1,83 @@
 mod config;
+mod config_file;
+mod keybindings;
 mod renderer;
 mod player;
 mod audio;
 mod video;
+mod clock;
+mod presenter;
+mod paths;
+mod geometry;
+mod screenshot;
+mod subtitle;
+mod text;
+mod overlay;
+mod subtitle_file;
+mod cli;
+mod playlist;
+mod font_atlas;
+mod osd;
+mod stats;
+mod ipc;
+mod http;
+mod filter;
+mod deinterlace;
+mod loudness;
+mod file_state;
+mod visualization;
+mod visualizer;
+mod probe;
+mod thumbnail;
 
-use std::sync::{Arc, Mutex};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use ffmpeg_next as ffmpeg;
 use ffmpeg::util::frame::Video as VideoFrame;
 
-use glium::glutin::event::{Event, WindowEvent, KeyboardInput, ElementState, VirtualKeyCode};
+use glium::glutin::event::{Event, WindowEvent, KeyboardInput, ElementState, MouseButton, MouseScrollDelta, ModifiersState};
 use glium::glutin::event_loop::{ControlFlow, EventLoop};
 
+use clap::Parser;
+
+use cli::Cli;
 use config::Config;
 use renderer::Renderer;
-use player::Player;
+use player::{AudioDelay, LoopMode, Player, PlayerOptions, TrackKind, TrackPreferences};
+use clock::format_time;
+use playlist::{Playlist, RepeatMode};
+use osd::PlaybackInfo;
+use stats::{BitrateMeter, StatsSnapshot};
+use ipc::{IpcCommand, IpcEvent, IpcServer, LoadMode, SeekMode};
+use http::HttpServer;
+use serde_json::{json, Value};
+use presenter::{FrameQueue, PresentStats, Presentation, Presenter};
+use geometry::WindowGeometry;
+use file_state::FileState;
+use deinterlace::Field;
+use subtitle::{SubtitleCue, SubtitleTrack};
+use visualization::VisualizationMode;
+use keybindings::Action;
 
-const TARGET_FPS: u32 = 30;
-const FRAME_DURATION: Duration = Duration::from_micros((1_000_000f32 / TARGET_FPS as f32) as u64);
+/// 两次左键单击间隔小于该值视为双击
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+/// 滚轮每格或 +/- 每次的缩放倍率
+const ZOOM_STEP: f32 = 1.1;
+/// 字幕延迟每次调整的步长，单位毫秒
+const SUBTITLE_DELAY_STEP_MS: i64 = 100;
+/// Ctrl +/- 每次调整音频延迟的步长，单位毫秒
+const AUDIO_DELAY_STEP_MS: i64 = 50;
+/// 只有封面图时等待封面解码出第一帧的最长时间，超时后按没有画面处理
+const COVER_ART_TIMEOUT: Duration = Duration::from_secs(1);
+/// 统计信息面板的刷新间隔
+const STATS_REFRESH_INTERVAL: Duration = Duration::from_millis(500);
 
 fn main() {
-    // 初始化日志系统
+    // 初始化日志系统，日志写到标准错误，标准输出留给 --probe 等模式的输出
     tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
         .with_env_filter("info")
         .with_file(true)
         .with_line_number(true)
@@ -33,74 +88,153 @@ fn main() {
 
     tracing::info!("程序启动");
 
-    let config = Config::default();
+    let cli = Cli::parse();
+    if cli.audio_device_list {
+        for device in audio::output_devices() {
+            println!("{}", device);
+        }
+        return;
+    }
+    if cli.probe {
+        if !probe_media(&cli.paths) {
+            std::process::exit(1);
+        }
+        return;
+    }
+    if cli.thumbnails.is_some() || cli.storyboard.is_some() {
+        if !extract_thumbnails(&cli) {
+            std::process::exit(1);
+        }
+        return;
+    }
+    // 预读线程也要读取配置
+    let config = match cli.into_config() {
+        Ok(config) => Arc::new(config),
+        Err(e) => {
+            tracing::error!("{}", e);
+            return;
+        }
+    };
 
     tracing::info!("创建事件循环");
     let event_loop = EventLoop::new();
 
-    // 创建一个帧缓冲区来存储最新的视频帧
-    let frame_buffer = Arc::new(Mutex::new(None::<VideoFrame>));
-    let frame_buffer_clone = frame_buffer.clone();
+    let mut playlist = match Playlist::load(&config.playlist) {
+        Ok(playlist) => playlist,
+        Err(e) => {
+            tracing::error!("加载播放列表失败: {}", e);
+            return;
+        }
+    };
+    playlist.set_repeat(config.repeat);
+    if config.shuffle {
+        playlist.set_shuffle(true);
+    }
 
     tracing::info!("创建播放器");
-    let player = Player::start(
-        config.video_path.clone(),
-        Box::new(move |frame: &VideoFrame| {
-            if let Ok(mut buffer) = frame_buffer_clone.lock() {
-                *buffer = Some(frame.clone());
-            }
-        }),
-        Box::new(|playing| {
-            tracing::info!("播放状态改变: {}", if playing { "播放" } else { "暂停" });
-        }),
-    ).expect("Failed to start player");
-
-    let player = Arc::new(Mutex::new(player));
+    let first_subtitle_file = config.subtitle_file.as_deref();
+    let Some(mut session) = open_playable(&mut playlist, &config, first_subtitle_file) else {
+        tracing::error!("播放列表中没有可以播放的文件");
+        return;
+    };
+    // 预读的下一项，当前项的数据包读完后在后台线程中打开
+    let mut preloaded: Option<Preload> = None;
+    let mut preload_attempted = false;
 
-    // 等待第一帧
+    // 等待第一帧，只有音频时没有画面，按窗口尺寸创建渲染器
     tracing::info!("等待第一帧");
-    let mut first_frame = None;
-    while first_frame.is_none() {
-        if let Ok(buffer) = frame_buffer.lock() {
-            if let Some(frame) = &*buffer {
-                first_frame = Some(frame.clone());
-                break;
-            }
+    let wait_started = Instant::now();
+    let (video_width, video_height) = loop {
+        if let Some(size) = session.frame_queue.front_size() {
+            tracing::info!("收到第一帧，视频尺寸: {}x{}", size.0, size.1);
+            break size;
+        }
+        let player = &session.player;
+        if !player.has_video()
+            && (!player.has_cover_art() || wait_started.elapsed() >= COVER_ART_TIMEOUT)
+        {
+            tracing::info!("没有视频画面");
+            break (config.window_width, config.window_height);
         }
         std::thread::sleep(Duration::from_millis(10));
-    }
-
-    let first_frame = first_frame.unwrap();
-    let video_width = first_frame.width() as u32;
-    let video_height = first_frame.height() as u32;
-    tracing::info!("收到第一帧，视频尺寸: {}x{}", video_width, video_height);
+    };
 
     // 使用配置中的窗口尺寸创建渲染器
     tracing::info!("创建渲染器，窗口尺寸: {}x{}", config.window_width, config.window_height);
     let mut renderer = Renderer::new(&event_loop, &config, video_width, video_height);
 
-    // 手动设置窗口大小
-    renderer.handle_resize(glium::glutin::dpi::PhysicalSize::new(
-        config.window_width,
-        config.window_height
-    ));
+    if config.remember_geometry {
+        if let Some(geometry) = WindowGeometry::load() {
+            renderer.restore_geometry(&geometry);
+        }
+    }
+    if config.crop.is_some() {
+        renderer.set_crop(config.crop);
+    }
+    if let Some(scale) = config.fit_to_video {
+        renderer.fit_to_video(scale);
+    }
+    if config.fullscreen {
+        renderer.set_fullscreen(true);
+    }
 
-    tracing::info!("初始窗口尺寸设置为: {}x{}", config.window_width, config.window_height);
+    // 只有音频时使用的可视化方式，W 键切换后沿用到播放列表的后续项
+    let mut visualization = config.visualization;
+    update_visualization(&mut renderer, &session.player, visualization);
+    let visualization_interval = renderer.refresh_interval();
+    let mut last_visualization: Option<Instant> = None;
 
-    let mut frame_count = 0;
+    let mut presenter = Presenter::new(
+        session.frame_queue.clone(),
+        session.player.clock(),
+        renderer.refresh_interval(),
+    );
+    if session.player.resumed_from().is_some() {
+        show_opened_message(&mut renderer, &session.player, &config);
+    }
+    let mut last_stats = presenter.stats();
     let mut last_fps_update = Instant::now();
-    let mut last_frame_time = Instant::now();
+    let mut last_click: Option<Instant> = None;
+    let mut dragging = false;
+    let mut cursor_position: Option<(f64, f64)> = None;
+    let mut modifiers = ModifiersState::empty();
+    // 当前显示的解码帧及其 PTS，用于源分辨率截图
+    let mut current_frame: Option<(VideoFrame, Duration)> = None;
+    // 统计信息面板：是否显示、上次刷新时刻、码率估算和纹理上传耗时的滑动平均（微秒）
+    let mut stats_visible = false;
+    let mut last_stats_refresh: Option<Instant> = None;
+    let mut bitrate_meter = BitrateMeter::new();
+    let mut upload_time_us = 0u64;
+
+    let ipc_server = config.ipc_socket.as_deref().and_then(|path| {
+        IpcServer::start(path)
+            .map_err(|e| tracing::error!("启动 IPC 服务器失败: {}", e))
+            .ok()
+    });
+    let http_server = config.http_address.and_then(|address| {
+        HttpServer::start(address, config.http_token.clone())
+            .map_err(|e| tracing::error!("启动 HTTP 遥控服务器失败: {}", e))
+            .ok()
+    });
+    // 上一次事件循环时的播放状态和文件，变化时向 IPC 客户端推送事件
+    let mut last_playing = session.player.is_playing();
+    let mut last_media = session.player.media_path().to_path_buf();
+    if let Some(server) = &ipc_server {
+        server.broadcast(IpcEvent::StartFile { path: last_media.clone() });
+    }
 
     tracing::info!("进入主事件循环");
     event_loop.run(move |event, _, control_flow| {
-        *control_flow = ControlFlow::Poll;
-
         match event {
             Event::WindowEvent {
                 event: WindowEvent::CloseRequested,
                 ..
             } => {
                 tracing::info!("接收到退出事件");
+                if let Some(server) = &ipc_server {
+                    server.broadcast(IpcEvent::Shutdown);
+                }
+                save_geometry(&renderer, &config);
                 *control_flow = ControlFlow::Exit;
             }
             Event::WindowEvent {
@@ -114,18 +248,388 @@ fn main() {
                 },
                 ..
             } => {
-                match keycode {
-                    VirtualKeyCode::Space => {
-                        tracing::info!("空格键按下，切换播放状态");
-                        if let Ok(mut player) = player.lock() {
-                            player.toggle_pause_playing();
-                        }
+                let Some(action) = config.key_bindings.action(keycode, modifiers) else {
+                    return;
+                };
+                tracing::debug!("按键 {:?} -> {}", keycode, action.name());
+                match action {
+                    Action::TogglePause => {
+                        tracing::info!("切换播放状态");
+                        session.player.toggle_pause_playing();
+                        let message = if session.player.is_playing() { "播放" } else { "暂停" };
+                        renderer.osd().show_message(message);
+                        renderer.redraw();
                     }
-                    VirtualKeyCode::M => {
-                        tracing::info!("M键按下，切换缩放模式");
+                    Action::CycleScaleMode => {
+                        tracing::info!("切换缩放模式");
                         renderer.toggle_scale_mode();
+                        let message = format!("缩放模式: {}", renderer.scale_mode().label());
+                        renderer.osd().show_message(message);
+                        renderer.redraw();
+                    }
+                    Action::ToggleFullscreen => {
+                        tracing::info!("切换全屏");
+                        renderer.toggle_fullscreen();
+                        let message = if renderer.is_fullscreen() { "全屏" } else { "窗口" };
+                        renderer.osd().show_message(message);
+                        renderer.redraw();
+                    }
+                    Action::ExitFullscreen => {
+                        tracing::info!("退出全屏");
+                        renderer.set_fullscreen(false);
+                        renderer.osd().show_message("窗口");
+                        renderer.redraw();
+                    }
+                    Action::ToggleAlwaysOnTop => {
+                        tracing::info!("切换窗口置顶");
+                        renderer.toggle_always_on_top();
+                        let message = if renderer.is_always_on_top() {
+                            "窗口置顶: 开"
+                        } else {
+                            "窗口置顶: 关"
+                        };
+                        renderer.osd().show_message(message);
+                        renderer.redraw();
+                    }
+                    Action::WindowScaleHalf
+                    | Action::WindowScaleOriginal
+                    | Action::WindowScaleDouble => {
+                        let scale = match action {
+                            Action::WindowScaleHalf => 0.5,
+                            Action::WindowScaleOriginal => 1.0,
+                            _ => 2.0,
+                        };
+                        renderer.fit_to_video(scale);
+                        renderer.osd().show_message(format!("窗口大小: {:.0}%", scale * 100.0));
+                        renderer.redraw();
+                    }
+                    Action::AudioDelayIncrease | Action::AudioDelayDecrease => {
+                        let step = if action == Action::AudioDelayDecrease {
+                            -AUDIO_DELAY_STEP_MS
+                        } else {
+                            AUDIO_DELAY_STEP_MS
+                        };
+                        let delay = session.player.audio_delay().as_millis() + step;
+                        let delay =
+                            set_audio_delay(&mut session, &config, AudioDelay::from_millis(delay));
+                        // 单独的 +/- 用于缩放，提示里写明要按住 Ctrl
+                        renderer
+                            .osd()
+                            .show_message(format!("音频延迟: {}（Ctrl+= / Ctrl+- 调节）", delay));
+                        renderer.redraw();
+                    }
+                    Action::ZoomIn => {
+                        renderer.zoom_by(ZOOM_STEP);
+                        let message = format!("缩放: {:.0}%", renderer.zoom() * 100.0);
+                        renderer.osd().show_message(message);
+                        renderer.redraw();
+                    }
+                    Action::ZoomOut => {
+                        renderer.zoom_by(1.0 / ZOOM_STEP);
+                        let message = format!("缩放: {:.0}%", renderer.zoom() * 100.0);
+                        renderer.osd().show_message(message);
+                        renderer.redraw();
+                    }
+                    Action::Screenshot | Action::ScreenshotWindow => {
+                        let Some((frame, pts)) = &current_frame else {
+                            tracing::warn!("还没有可截图的画面");
+                            return;
+                        };
+                        let player = &session.player;
+                        let result = if action == Action::ScreenshotWindow {
+                            tracing::info!("截取窗口画面");
+                            let path = screenshot::screenshot_path(
+                                &config.screenshot_dir,
+                                player.media_path(),
+                                *pts,
+                                "window",
+                            );
+                            renderer.screenshot_window(&path).map(|_| path)
+                        } else {
+                            tracing::info!("截取源分辨率画面");
+                            player.screenshot(frame, *pts, &config.screenshot_dir)
+                        };
+                        match result {
+                            Ok(path) => {
+                                let message = format!("截图已保存: {}", path.display());
+                                renderer.osd().show_message(message);
+                            }
+                            Err(e) => {
+                                tracing::error!("截图失败: {}", e);
+                                renderer.osd().show_message(format!("截图失败: {}", e));
+                            }
+                        }
+                        renderer.redraw();
+                    }
+                    Action::ToggleSubtitles => {
+                        tracing::info!("切换字幕显示");
+                        renderer.toggle_subtitles();
+                        let message = if renderer.subtitles_visible() {
+                            "字幕: 显示"
+                        } else {
+                            "字幕: 隐藏"
+                        };
+                        renderer.osd().show_message(message);
+                        renderer.redraw();
+                    }
+                    Action::SubtitleDelayDecrease | Action::SubtitleDelayIncrease => {
+                        let step = if action == Action::SubtitleDelayDecrease {
+                            -SUBTITLE_DELAY_STEP_MS
+                        } else {
+                            SUBTITLE_DELAY_STEP_MS
+                        };
+                        let delay = session.subtitle_track.adjust_delay(step);
+                        renderer.osd().show_message(format!("字幕延迟: {} ms", delay));
+                        renderer.redraw();
+                    }
+                    Action::CycleAudioTrack => {
+                        let message = match session.player.cycle_track(TrackKind::Audio) {
+                            Ok(Some(track)) => {
+                                tracing::info!("切换音轨: {}", track);
+                                format!("音轨: {}", track)
+                            }
+                            Ok(None) => {
+                                tracing::info!("没有其他音轨可切换");
+                                String::from("没有其他音轨")
+                            }
+                            Err(e) => {
+                                tracing::error!("切换音轨失败: {}", e);
+                                format!("切换音轨失败: {}", e)
+                            }
+                        };
+                        renderer.osd().show_message(message);
+                        renderer.redraw();
+                    }
+                    Action::CycleSubtitleTrack => {
+                        if session.external_subtitles {
+                            tracing::warn!("正在使用外挂字幕，忽略内嵌字幕轨切换");
+                            renderer.osd().show_message("正在使用外挂字幕");
+                            renderer.redraw();
+                            return;
+                        }
+                        let message = match session.player.cycle_track(TrackKind::Subtitle) {
+                            Ok(Some(track)) => {
+                                tracing::info!("切换字幕轨: {}", track);
+                                // 旧字幕轨的字幕不再显示
+                                session.subtitle_track.clear();
+                                format!("字幕轨: {}", track)
+                            }
+                            Ok(None) => {
+                                tracing::info!("没有其他字幕轨可切换");
+                                String::from("没有其他字幕轨")
+                            }
+                            Err(e) => {
+                                tracing::error!("切换字幕轨失败: {}", e);
+                                format!("切换字幕轨失败: {}", e)
+                            }
+                        };
+                        renderer.osd().show_message(message);
+                        renderer.redraw();
+                    }
+                    Action::SetLoopA => {
+                        let position = session.player.clock().position();
+                        tracing::info!("设置循环起点 A: {}", format_time(position));
+                        session.loop_a = Some(position);
+                        let message = format!("循环起点 A: {}", format_time(position));
+                        renderer.osd().show_message(message);
+                        // 已经在片段循环中时直接更新起点
+                        if let LoopMode::Segment { b, .. } = session.player.loop_mode() {
+                            let segment = LoopMode::Segment { a: position, b };
+                            if let Err(e) = session.player.set_loop(segment) {
+                                tracing::warn!("设置循环区间失败: {}", e);
+                            }
+                        }
+                        renderer.redraw();
+                    }
+                    Action::SetLoopB => {
+                        let Some(a) = session.loop_a else {
+                            tracing::warn!("请先设置循环起点 A");
+                            renderer.osd().show_message("请先设置循环起点 A");
+                            renderer.redraw();
+                            return;
+                        };
+                        let b = session.player.clock().position();
+                        tracing::info!("设置循环终点 B: {}", format_time(b));
+                        let message = match session.player.set_loop(LoopMode::Segment { a, b }) {
+                            Ok(()) => format!("A-B 循环: {} - {}", format_time(a), format_time(b)),
+                            Err(e) => {
+                                tracing::warn!("设置循环区间失败: {}", e);
+                                String::from("循环终点必须晚于起点")
+                            }
+                        };
+                        renderer.osd().show_message(message);
+                        renderer.redraw();
+                    }
+                    Action::ToggleFileLoop => {
+                        let mode = match session.player.loop_mode() {
+                            LoopMode::Off => LoopMode::File,
+                            LoopMode::File | LoopMode::Segment { .. } => LoopMode::Off,
+                        };
+                        tracing::info!("循环模式: {:?}", mode);
+                        session.loop_a = None;
+                        if let Err(e) = session.player.set_loop(mode) {
+                            tracing::error!("设置循环模式失败: {}", e);
+                        }
+                        let message = match mode {
+                            LoopMode::File => "单文件循环: 开",
+                            _ => "循环: 关",
+                        };
+                        renderer.osd().show_message(message);
+                        renderer.redraw();
+                    }
+                    Action::PlaylistNext | Action::PlaylistPrevious => {
+                        let moved = if action == Action::PlaylistNext {
+                            tracing::info!("播放下一项");
+                            playlist.skip_next().is_some()
+                        } else {
+                            tracing::info!("播放上一项");
+                            playlist.skip_previous().is_some()
+                        };
+                        if !moved {
+                            tracing::info!("已经到达播放列表的边界");
+                            renderer.osd().show_message("已经到达播放列表的边界");
+                            renderer.redraw();
+                            return;
+                        }
+                        preload_attempted = false;
+                        let current = playlist.current().map(Path::to_path_buf);
+                        let next = current
+                            .and_then(|current| take_preloaded(&mut preloaded, &current))
+                            .or_else(|| open_playable(&mut playlist, &config, None));
+                        match next {
+                            Some(next) => {
+                                switch_session(&mut session, next, &mut presenter);
+                                update_visualization(&mut renderer, &session.player, visualization);
+                                show_opened_message(&mut renderer, &session.player, &config);
+                            }
+                            None => tracing::error!("播放列表中没有可以播放的文件"),
+                        }
+                    }
+                    Action::CycleRepeat => {
+                        playlist.set_repeat(playlist.repeat().next());
+                        let message = match playlist.repeat() {
+                            RepeatMode::Off => "列表循环: 关",
+                            RepeatMode::One => "列表循环: 单曲",
+                            RepeatMode::All => "列表循环: 全部",
+                        };
+                        renderer.osd().show_message(message);
+                        renderer.redraw();
+                        // 下一项可能已经改变，重新预读
+                        preloaded = None;
+                        preload_attempted = false;
+                    }
+                    Action::ToggleShuffle => {
+                        playlist.set_shuffle(!playlist.is_shuffled());
+                        let message = if playlist.is_shuffled() {
+                            "随机播放: 开"
+                        } else {
+                            "随机播放: 关"
+                        };
+                        renderer.osd().show_message(message);
+                        renderer.redraw();
+                        preloaded = None;
+                        preload_attempted = false;
+                    }
+                    Action::ToggleStats => {
+                        stats_visible = !stats_visible;
+                        tracing::info!("统计信息: {}", stats_visible);
+                        if !stats_visible {
+                            renderer.osd().set_stats(None);
+                        }
+                        // 下一次事件循环立即刷新面板
+                        last_stats_refresh = None;
+                        renderer.redraw();
+                    }
+                    Action::CycleDeinterlace => {
+                        let mode = session.player.deinterlace_mode().next();
+                        tracing::info!("去隔行: {:?}", mode);
+                        session.player.set_deinterlace_mode(mode);
+                        renderer.osd().show_message(format!("去隔行: {}", mode.label()));
+                        renderer.redraw();
+                    }
+                    Action::CycleVisualization => {
+                        let mode = renderer.visualization().next();
+                        tracing::info!("音频可视化: {:?}", mode);
+                        renderer.set_visualization(mode);
+                        // 有视频画面时只对当前文件生效
+                        if !session.player.has_video() {
+                            visualization = mode;
+                        }
+                        renderer.osd().show_message(format!("音频可视化: {}", mode.label()));
+                        renderer.redraw();
+                    }
+                    Action::SeekToStart => {
+                        tracing::info!("从头播放");
+                        match session.player.seek(Duration::ZERO) {
+                            Ok(()) => renderer.osd().show_message("从头播放"),
+                            Err(e) => tracing::error!("跳转失败: {}", e),
+                        }
+                        renderer.redraw();
+                    }
+                    Action::ResetView => {
+                        tracing::info!("重置缩放和平移");
+                        renderer.reset_view();
+                        renderer.osd().show_message("重置画面");
+                        renderer.redraw();
                     }
-                    _ => (),
+                }
+            }
+            Event::WindowEvent {
+                event: WindowEvent::ModifiersChanged(state),
+                ..
+            } => {
+                modifiers = state;
+            }
+            Event::WindowEvent {
+                event: WindowEvent::MouseWheel { delta, .. },
+                ..
+            } => {
+                let steps = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y,
+                    MouseScrollDelta::PixelDelta(position) => (position.y / 50.0) as f32,
+                };
+                if steps != 0.0 {
+                    renderer.zoom_by(ZOOM_STEP.powf(steps));
+                    renderer.redraw();
+                }
+            }
+            Event::WindowEvent {
+                event: WindowEvent::CursorMoved { position, .. },
+                ..
+            } => {
+                if let (true, Some((last_x, last_y))) = (dragging, cursor_position) {
+                    renderer.pan_by(position.x - last_x, position.y - last_y);
+                    renderer.redraw();
+                }
+                cursor_position = Some((position.x, position.y));
+            }
+            Event::WindowEvent {
+                event: WindowEvent::MouseInput {
+                    state: ElementState::Released,
+                    button: MouseButton::Left,
+                    ..
+                },
+                ..
+            } => {
+                dragging = false;
+            }
+            Event::WindowEvent {
+                event: WindowEvent::MouseInput {
+                    state: ElementState::Pressed,
+                    button: MouseButton::Left,
+                    ..
+                },
+                ..
+            } => {
+                dragging = true;
+                let now = Instant::now();
+                match last_click {
+                    Some(last) if now.duration_since(last) <= DOUBLE_CLICK_INTERVAL => {
+                        tracing::info!("双击，切换全屏");
+                        renderer.toggle_fullscreen();
+                        last_click = None;
+                    }
+                    _ => last_click = Some(now),
                 }
             }
             Event::WindowEvent {
@@ -134,30 +638,778 @@ fn main() {
             } => {
                 tracing::info!("窗口调整大小事件: {}x{}", new_size.width, new_size.height);
                 renderer.handle_resize(new_size);
+                renderer.redraw();
             }
             Event::MainEventsCleared => {
                 let now = Instant::now();
-                let elapsed = now.duration_since(last_frame_time);
-                
-                if elapsed >= FRAME_DURATION {
-                    if let Ok(buffer) = frame_buffer.lock() {
-                        if let Some(frame) = &*buffer {
-                            frame_count += 1;
-                            renderer.render_frame(frame);
-                            last_frame_time = now;
-
-                            if now.duration_since(last_fps_update) >= Duration::from_secs(1) {
-                                tracing::info!("FPS: {}", frame_count);
-                                frame_count = 0;
-                                last_fps_update = now;
+
+                // 执行 IPC 和 HTTP 客户端的命令，需要操作播放列表或事件循环的命令在这里处理
+                while let Some(request) = ipc_server
+                    .as_ref()
+                    .and_then(IpcServer::try_recv)
+                    .or_else(|| http_server.as_ref().and_then(HttpServer::try_recv))
+                {
+                    let result = match request.command.clone() {
+                        IpcCommand::Quit => {
+                            tracing::info!("IPC 请求退出");
+                            request.reply(Ok(Value::Null));
+                            if let Some(server) = &ipc_server {
+                                server.broadcast(IpcEvent::Shutdown);
                             }
+                            save_geometry(&renderer, &config);
+                            *control_flow = ControlFlow::Exit;
+                            return;
+                        }
+                        IpcCommand::LoadFile { path, mode: LoadMode::Append } => {
+                            playlist.push(path);
+                            // 下一项可能已经改变，重新预读
+                            preloaded = None;
+                            preload_attempted = false;
+                            Ok(Value::Null)
+                        }
+                        IpcCommand::LoadFile { path, mode: LoadMode::Replace } => {
+                            let mut replacement = Playlist::new(vec![path.clone()]);
+                            replacement.set_repeat(playlist.repeat());
+                            playlist = replacement;
+                            preloaded = None;
+                            preload_attempted = false;
+                            match open_playable(&mut playlist, &config, None) {
+                                Some(next) => {
+                                    switch_session(&mut session, next, &mut presenter);
+                                    update_visualization(&mut renderer, &session.player, visualization);
+                                    show_opened_message(&mut renderer, &session.player, &config);
+                                    Ok(Value::Null)
+                                }
+                                None => Err(format!("无法打开 {}", path.display())),
+                            }
+                        }
+                        command => execute_ipc_command(
+                            command,
+                            &mut session,
+                            &mut renderer,
+                            &current_frame,
+                            &playlist,
+                            &config,
+                        ),
+                    };
+                    if let (Ok(_), IpcCommand::Seek { .. }, Some(server)) =
+                        (&result, &request.command, &ipc_server)
+                    {
+                        let position = session.player.clock().position().as_secs_f64();
+                        server.broadcast(IpcEvent::Seek { position });
+                    }
+                    request.reply(result);
+                }
+
+                // 当前项的数据包读完后提前打开下一项，让解码线程预先填满缓冲区
+                if !preload_attempted && session.player.is_demux_finished() {
+                    preload_attempted = true;
+                    if let Some(path) = playlist.peek_next().map(Path::to_path_buf) {
+                        tracing::info!("预读下一项: {:?}", path);
+                        preloaded = Preload::start(path, Arc::clone(&config));
+                    }
+                }
+
+                // 下一项还在后台打开时先不切换，保持当前画面，避免在事件循环里等待
+                let preload_pending = preloaded.as_ref().is_some_and(|preload| {
+                    !preload.thread.is_finished()
+                        && playlist.peek_next() == Some(preload.path.as_path())
+                });
+                if session.player.is_finished() && !preload_pending {
+                    tracing::info!("当前项播放结束");
+                    let next = match playlist.advance().map(Path::to_path_buf) {
+                        Some(path) => take_preloaded(&mut preloaded, &path)
+                            .or_else(|| open_playable(&mut playlist, &config, None)),
+                        None => None,
+                    };
+                    preload_attempted = false;
+                    match next {
+                        Some(next) => {
+                            switch_session(&mut session, next, &mut presenter);
+                            update_visualization(&mut renderer, &session.player, visualization);
+                            show_opened_message(&mut renderer, &session.player, &config);
+                        }
+                        None => {
+                            tracing::info!("播放列表播放完毕");
+                            if let Some(server) = &ipc_server {
+                                server.broadcast(IpcEvent::EndFile { reason: "eof" });
+                                server.broadcast(IpcEvent::Shutdown);
+                            }
+                            save_geometry(&renderer, &config);
+                            *control_flow = ControlFlow::Exit;
+                            return;
+                        }
+                    }
+                }
+
+                if let Some(server) = &ipc_server {
+                    if session.player.media_path() != last_media {
+                        server.broadcast(IpcEvent::EndFile { reason: "stop" });
+                        last_media = session.player.media_path().to_path_buf();
+                        server.broadcast(IpcEvent::StartFile { path: last_media.clone() });
+                    }
+                    if session.player.is_playing() != last_playing {
+                        last_playing = session.player.is_playing();
+                        server.broadcast(if last_playing { IpcEvent::Unpause } else { IpcEvent::Pause });
+                    }
+                }
+
+                let position = session.player.clock().position();
+                let subtitles_changed =
+                    renderer.set_subtitles(session.subtitle_track.active_at(position));
+                let osd = renderer.osd();
+                let status_changed = osd.set_status(session.loop_status());
+                osd.set_playback(PlaybackInfo {
+                    position,
+                    duration: session.player.duration(),
+                    title: session.player.title().to_string(),
+                    paused: !session.player.is_playing(),
+                });
+                // 消息淡出和进度条走动都需要持续重绘
+                let mut osd_changed = osd.is_animating(now);
+                let stats_due = last_stats_refresh
+                    .is_none_or(|last| now.duration_since(last) >= STATS_REFRESH_INTERVAL);
+                if stats_visible && stats_due {
+                    let snapshot = session.player.stats().snapshot();
+                    let bitrate = bitrate_meter.update(snapshot.bytes_consumed, now);
+                    let mut lines = stats_lines(
+                        &snapshot,
+                        bitrate,
+                        position,
+                        presenter.stats(),
+                        Duration::from_micros(upload_time_us),
+                    );
+                    lines.push(loudness_line(&session.player));
+                    lines.push(format!("音频延迟: {}", session.player.audio_delay()));
+                    osd_changed |= renderer.osd().set_stats(Some(lines));
+                    last_stats_refresh = Some(now);
+                }
+
+                // 音频可视化按显示器刷新率取样重绘，暂停时保持最后的画面
+                let visualization_due = renderer.visualization() != VisualizationMode::Off
+                    && session.player.is_playing()
+                    && last_visualization
+                        .is_none_or(|last| now.duration_since(last) >= visualization_interval);
+                if visualization_due {
+                    let window = session.player.audio_window(visualizer::WINDOW_SAMPLES);
+                    renderer.set_audio_window(window);
+                    last_visualization = Some(now);
+                }
+
+                match presenter.poll(now) {
+                    Presentation::NewFrame(frame, pts, field) => {
+                        let upload_started = Instant::now();
+                        renderer.render_frame(&frame, field);
+                        let upload_time = upload_started.elapsed().as_micros() as u64;
+                        upload_time_us = stats::moving_average(upload_time_us, upload_time);
+                        current_frame = Some((frame, pts));
+                    }
+                    // 画面保持上一帧即可，字幕或 OSD 变化时才需要重新提交
+                    Presentation::Repeat | Presentation::Idle => {
+                        if subtitles_changed
+                            || status_changed
+                            || osd_changed
+                            || visualization_due
+                        {
+                            renderer.redraw();
                         }
                     }
-                } else {
-                    std::thread::sleep(FRAME_DURATION - elapsed);
                 }
+
+                if now.duration_since(last_fps_update) >= Duration::from_secs(1) {
+                    let stats = presenter.stats();
+                    tracing::info!(
+                        "FPS: {}, 丢帧: {}, 重复帧: {}",
+                        stats.presented - last_stats.presented,
+                        stats.dropped - last_stats.dropped,
+                        stats.repeated - last_stats.repeated
+                    );
+                    last_stats = stats;
+                    last_fps_update = now;
+                }
+
+                *control_flow = ControlFlow::WaitUntil(presenter.next_refresh());
             }
             _ => (),
         }
     });
 }
+
+/// 播放列表中一项的播放会话：播放器以及它专属的帧队列和字幕轨
+struct Session {
+    player: Player,
+    frame_queue: FrameQueue,
+    subtitle_track: SubtitleTrack,
+    /// 正在使用外挂字幕，此时忽略内嵌字幕轨切换
+    external_subtitles: bool,
+    /// 已标记但尚未配对的循环起点 A
+    loop_a: Option<Duration>,
+}
+
+impl Session {
+    fn open(
+        path: &Path,
+        config: &Config,
+        subtitle_file: Option<&Path>,
+        start_paused: bool,
+    ) -> Result<Self, anyhow::Error> {
+        // 解码线程按 PTS 把帧放入队列，由呈现器在刷新周期内挑选
+        let frame_queue = FrameQueue::new();
+        let frame_queue_clone = frame_queue.clone();
+
+        // 外挂字幕优先于内嵌字幕
+        let subtitle_path = subtitle_file.map(Path::to_path_buf).or_else(|| {
+            if config.auto_load_subtitles {
+                subtitle_file::find_sidecar(path)
+            } else {
+                None
+            }
+        });
+        let (subtitle_track, external_subtitles) =
+            match subtitle_path.map(|path| subtitle_file::load(&path)) {
+                Some(Ok(cues)) => (SubtitleTrack::from_cues(cues), true),
+                Some(Err(e)) => {
+                    tracing::error!("加载外挂字幕失败: {}", e);
+                    (SubtitleTrack::new(), false)
+                }
+                None => (SubtitleTrack::new(), false),
+            };
+        // 使用外挂字幕时播放器不解码内嵌字幕流，回调也不会往外挂字幕轨里混入内嵌字幕
+        let embedded_track = (!external_subtitles).then(|| subtitle_track.clone());
+
+        // 上次为该文件调整过的音频延迟优先于配置中的默认值
+        let audio_delay_ms = if config.remember_audio_delay {
+            FileState::load(path).audio_delay_ms
+        } else {
+            None
+        };
+
+        let player = Player::start(
+            path.to_path_buf(),
+            PlayerOptions {
+                track_preferences: TrackPreferences {
+                    audio_languages: config.preferred_audio_languages.clone(),
+                    subtitle_languages: config.preferred_subtitle_languages.clone(),
+                },
+                start_paused,
+                video_filter: config.video_filter.clone().unwrap_or_default(),
+                audio_filter: config.audio_filter.clone().unwrap_or_default(),
+                deinterlace: config.deinterlace,
+                deinterlacer: config.deinterlacer,
+                field_rate: config.deinterlace_field_rate,
+                replaygain: config.replaygain,
+                replaygain_preamp: config.replaygain_preamp,
+                normalize_target: config.normalize_target,
+                audio_device: config.audio_device.clone(),
+                audio_buffer: Some(config.audio_buffer),
+                audio_device_buffer: config.audio_device_buffer,
+                audio_delay: AudioDelay::from_millis(
+                    audio_delay_ms.unwrap_or(config.audio_delay_ms),
+                ),
+                resume: config.resume,
+                volume: Some(config.volume),
+                decoder_threads: config.decoder_threads,
+                external_subtitles,
+            },
+            Box::new(move |frame: &VideoFrame, pts: Duration, field: Option<Field>| {
+                frame_queue_clone.push(frame, pts, field);
+            }),
+            Box::new(move |cue: SubtitleCue| {
+                if let Some(track) = &embedded_track {
+                    track.push(cue);
+                }
+            }),
+            Box::new(|playing| {
+                tracing::info!("播放状态改变: {}", if playing { "播放" } else { "暂停" });
+            }),
+        )?;
+
+        Ok(Self {
+            player,
+            frame_queue,
+            subtitle_track,
+            external_subtitles,
+            loop_a: None,
+        })
+    }
+
+    /// 在 OSD 右上角常驻显示的循环状态
+    fn loop_status(&self) -> Option<String> {
+        match self.player.loop_mode() {
+            LoopMode::Segment { a, b } => {
+                Some(format!("A-B 循环: {} - {}", format_time(a), format_time(b)))
+            }
+            LoopMode::File => Some(String::from("单文件循环")),
+            LoopMode::Off => self.loop_a.map(|a| format!("A: {}", format_time(a))),
+        }
+    }
+}
+
+/// 在后台线程中打开的播放列表下一项。打开输入、探测流和初始化解码器可能很慢，
+/// 网络或慢速磁盘上的文件放在事件循环里打开会卡住画面
+struct Preload {
+    path: PathBuf,
+    thread: std::thread::JoinHandle<Option<Session>>,
+}
+
+impl Preload {
+    fn start(path: PathBuf, config: Arc<Config>) -> Option<Self> {
+        let thread_path = path.clone();
+        let thread = std::thread::Builder::new()
+            .name("preload thread".into())
+            .spawn(move || match Session::open(&thread_path, &config, None, true) {
+                Ok(next) => Some(next),
+                Err(e) => {
+                    tracing::error!("预读 {:?} 失败: {}", thread_path, e);
+                    None
+                }
+            });
+        match thread {
+            Ok(thread) => Some(Self { path, thread }),
+            Err(e) => {
+                tracing::error!("启动预读线程失败: {}", e);
+                None
+            }
+        }
+    }
+}
+
+/// 取出为 path 预读的会话，预读的不是这一项或者预读失败时返回 None。
+/// 预读线程还没结束时等待它打开完成
+fn take_preloaded(preloaded: &mut Option<Preload>, path: &Path) -> Option<Session> {
+    let preload = preloaded.take().filter(|preload| preload.path == path)?;
+    preload.thread.join().ok().flatten()
+}
+
+/// 从播放列表当前项开始打开第一个能播放的文件，跳过无法打开的项
+fn open_playable(
+    playlist: &mut Playlist,
+    config: &Config,
+    mut subtitle_file: Option<&Path>,
+) -> Option<Session> {
+    let mut path = playlist.current()?.to_path_buf();
+    for _ in 0..playlist.len() {
+        match Session::open(&path, config, subtitle_file.take(), false) {
+            Ok(session) => return Some(session),
+            Err(e) => tracing::error!("打开 {:?} 失败: {}", path, e),
+        }
+        path = playlist.skip_next()?.to_path_buf();
+    }
+    None
+}
+
+/// 切换到新的播放会话：先让新会话开始播放再释放旧会话，缩短切换间隙
+fn switch_session(session: &mut Session, mut next: Session, presenter: &mut Presenter) {
+    tracing::info!("切换到: {:?}", next.player.media_path());
+    // 运行时修改过的滤镜和去隔行模式沿用到下一项
+    let video_filter = session.player.video_filter();
+    if next.player.video_filter() != video_filter {
+        if let Err(e) = next.player.set_video_filter(&video_filter) {
+            tracing::warn!("沿用视频滤镜失败: {}", e);
+        }
+    }
+    let audio_filter = session.player.audio_filter();
+    if next.player.audio_filter() != audio_filter {
+        if let Err(e) = next.player.set_audio_filter(&audio_filter) {
+            tracing::warn!("沿用音频滤镜失败: {}", e);
+        }
+    }
+    next.player.set_deinterlace_mode(session.player.deinterlace_mode());
+    next.player.set_normalize_target(session.player.normalize_target());
+    if next.player.audio_device() != session.player.audio_device() {
+        next.player.set_audio_device(session.player.audio_device());
+    }
+    next.player.play();
+    presenter.set_source(next.frame_queue.clone(), next.player.clock());
+    let previous = std::mem::replace(session, next);
+    drop(previous);
+}
+
+/// 设置当前文件的音频延迟，需要时记住该文件的延迟，返回实际生效的延迟
+fn set_audio_delay(session: &mut Session, config: &Config, delay: AudioDelay) -> AudioDelay {
+    session.player.set_audio_delay(delay);
+    let delay = session.player.audio_delay();
+    let delay_ms = delay.as_millis();
+    if config.remember_audio_delay {
+        let media = session.player.media_path();
+        let mut state = FileState::load(media);
+        // 调回默认值时不再单独记录
+        state.audio_delay_ms = Some(delay_ms).filter(|&delay| delay != config.audio_delay_ms);
+        if let Err(e) = state.save(media) {
+            tracing::error!("保存音频延迟失败: {}", e);
+        }
+    }
+    delay
+}
+
+/// 逐个探测媒体文件并以 JSON 输出到标准输出，全部成功时返回 true
+fn probe_media(paths: &[PathBuf]) -> bool {
+    let mut ok = true;
+    for path in paths {
+        let value = match probe::probe(path) {
+            Ok(info) => info.to_json(),
+            Err(e) => {
+                tracing::error!("探测 {:?} 失败: {}", path, e);
+                ok = false;
+                json!({ "path": path.to_string_lossy(), "error": e.to_string() })
+            }
+        };
+        println!("{}", serde_json::to_string_pretty(&value).unwrap_or_default());
+    }
+    ok
+}
+
+/// 逐个文件截取缩略图或生成缩略图拼图，写到 --thumbnail-dir，全部成功时返回 true
+fn extract_thumbnails(cli: &Cli) -> bool {
+    let mut ok = true;
+    for path in &cli.paths {
+        if let Err(e) = extract_thumbnails_for(cli, path) {
+            tracing::error!("为 {:?} 生成缩略图失败: {}", path, e);
+            ok = false;
+        }
+    }
+    ok
+}
+
+fn extract_thumbnails_for(cli: &Cli, path: &Path) -> Result<(), anyhow::Error> {
+    let stem = path
+        .file_stem()
+        .map_or_else(|| "thumbnail".into(), |stem| stem.to_string_lossy());
+    let format = cli.thumbnail_format;
+    let extension = format.extension();
+
+    if let Some(count) = cli.storyboard {
+        let storyboard =
+            thumbnail::storyboard(path, count, cli.storyboard_columns, cli.thumbnail_size)?;
+        let image_name = format!("{}_storyboard.{}", stem, extension);
+        storyboard.image.save(&cli.thumbnail_dir.join(&image_name), format)?;
+        // 索引和拼图放在同一目录，用相对路径引用拼图
+        let vtt_path = cli.thumbnail_dir.join(format!("{}_storyboard.vtt", stem));
+        std::fs::write(&vtt_path, storyboard.webvtt(&image_name))?;
+        tracing::info!("缩略图索引已保存: {:?}", vtt_path);
+    } else if let Some(count) = cli.thumbnails {
+        for (i, thumbnail) in thumbnail::thumbnails(path, count, cli.thumbnail_size)?
+            .iter()
+            .enumerate()
+        {
+            let name = format!("{}_thumb_{:03}.{}", stem, i + 1, extension);
+            tracing::info!("缩略图 {} 取自 {}", name, format_time(thumbnail.position));
+            thumbnail.image.save(&cli.thumbnail_dir.join(name), format)?;
+        }
+    }
+    Ok(())
+}
+
+/// 打开文件后提示标题，续播时提示续播位置和从头播放的按键
+fn show_opened_message(renderer: &mut Renderer, player: &Player, config: &Config) {
+    let Some(position) = player.resumed_from() else {
+        renderer.osd().show_message(player.title().to_string());
+        return;
+    };
+    let message = match config.key_bindings.key_for(Action::SeekToStart) {
+        Some(key) => format!("从 {} 继续播放（按 {} 从头播放）", format_time(position), key),
+        None => format!("从 {} 继续播放", format_time(position)),
+    };
+    renderer.osd().show_message(message);
+}
+
+/// 只有音频或封面图时显示音频可视化，有视频画面或没有音频时关闭
+fn update_visualization(renderer: &mut Renderer, player: &Player, mode: VisualizationMode) {
+    if player.has_video() || !player.has_audio() {
+        renderer.set_visualization(VisualizationMode::Off);
+        return;
+    }
+    renderer.set_visualization(mode);
+    if !player.has_cover_art() {
+        // 不再显示上一项的最后一帧画面
+        renderer.clear_video();
+    }
+}
+
+/// 执行只涉及当前播放会话的 IPC 命令，返回响应数据
+fn execute_ipc_command(
+    command: IpcCommand,
+    session: &mut Session,
+    renderer: &mut Renderer,
+    current_frame: &Option<(VideoFrame, Duration)>,
+    playlist: &Playlist,
+    config: &Config,
+) -> Result<Value, String> {
+    let player = &mut session.player;
+    match command {
+        IpcCommand::Play => player.play(),
+        IpcCommand::Pause => {
+            if player.is_playing() {
+                player.toggle_pause_playing();
+            }
+        }
+        IpcCommand::Seek { seconds, mode } => {
+            let target = match mode {
+                SeekMode::Relative => player.clock().position().as_secs_f64() + seconds,
+                SeekMode::Absolute => seconds,
+            };
+            if !target.is_finite() {
+                return Err(format!("无效的跳转位置: {}", seconds));
+            }
+            player
+                .seek(Duration::from_secs_f64(target.max(0.0)))
+                .map_err(|e| e.to_string())?;
+            // 远程跳转不弹消息，只显示进度条
+            renderer.osd().show_progress();
+        }
+        IpcCommand::SetVolume(percent) => {
+            if !percent.is_finite() {
+                return Err(format!("无效的音量: {}", percent));
+            }
+            player.set_volume((percent / 100.0) as f32);
+        }
+        IpcCommand::SetSpeed(speed) => {
+            if !speed.is_finite() || speed <= 0.0 {
+                return Err(format!("无效的播放速度: {}", speed));
+            }
+            player.set_speed(speed as f32);
+        }
+        IpcCommand::Screenshot { window } => {
+            let Some((frame, pts)) = current_frame else {
+                return Err(String::from("还没有可截图的画面"));
+            };
+            let result = if window {
+                let path = screenshot::screenshot_path(
+                    &config.screenshot_dir,
+                    player.media_path(),
+                    *pts,
+                    "window",
+                );
+                renderer.screenshot_window(&path).map(|_| path)
+            } else {
+                player.screenshot(frame, *pts, &config.screenshot_dir)
+            };
+            let path = result.map_err(|e| e.to_string())?;
+            return Ok(json!({ "path": path.to_string_lossy() }));
+        }
+        IpcCommand::GetProperty(name) => return ipc_property(&name, session, playlist),
+        IpcCommand::SetProperty(name, value) => {
+            let command = match (name.as_str(), &value) {
+                ("pause", Value::Bool(true)) => IpcCommand::Pause,
+                ("pause", Value::Bool(false)) => IpcCommand::Play,
+                ("volume", Value::Number(volume)) => {
+                    IpcCommand::SetVolume(volume.as_f64().unwrap_or(f64::NAN))
+                }
+                ("speed", Value::Number(speed)) => {
+                    IpcCommand::SetSpeed(speed.as_f64().unwrap_or(f64::NAN))
+                }
+                ("vf", Value::String(spec)) => {
+                    player.set_video_filter(spec).map_err(|e| e.to_string())?;
+                    return Ok(Value::Null);
+                }
+                ("af", Value::String(spec)) => {
+                    player.set_audio_filter(spec).map_err(|e| e.to_string())?;
+                    return Ok(Value::Null);
+                }
+                ("deinterlace", Value::String(mode)) => {
+                    player.set_deinterlace_mode(mode.parse()?);
+                    return Ok(Value::Null);
+                }
+                ("normalize", Value::Null) => {
+                    player.set_normalize_target(None);
+                    return Ok(Value::Null);
+                }
+                ("normalize", Value::Number(target)) => {
+                    let target = target.as_f64().unwrap_or(f64::NAN);
+                    if !target.is_finite() {
+                        return Err(format!("无效的目标响度: {}", target));
+                    }
+                    player.set_normalize_target(Some(target as f32));
+                    return Ok(Value::Null);
+                }
+                ("audio-device", Value::Null) => {
+                    player.set_audio_device(None);
+                    return Ok(Value::Null);
+                }
+                ("audio-device", Value::String(device)) => {
+                    player.set_audio_device(Some(device.clone()));
+                    return Ok(Value::Null);
+                }
+                ("audio-delay", Value::Number(delay)) => {
+                    let Some(delay) = delay.as_f64().filter(|delay| delay.is_finite()) else {
+                        return Err(format!("无效的音频延迟: {}", delay));
+                    };
+                    set_audio_delay(session, config, AudioDelay::from_millis(delay.round() as i64));
+                    return Ok(Value::Null);
+                }
+                (
+                    "pause" | "volume" | "speed" | "vf" | "af" | "deinterlace" | "normalize"
+                    | "audio-device" | "audio-delay",
+                    _,
+                ) => {
+                    return Err(format!("属性 {} 的值类型错误: {}", name, value));
+                }
+                _ => return Err(format!("属性不可写或不存在: {}", name)),
+            };
+            return execute_ipc_command(command, session, renderer, current_frame, playlist, config);
+        }
+        IpcCommand::LoadFile { .. } | IpcCommand::Quit => {
+            return Err(String::from("该命令只能由事件循环执行"));
+        }
+    }
+    Ok(Value::Null)
+}
+
+/// IPC 可查询的属性
+fn ipc_property(name: &str, session: &Session, playlist: &Playlist) -> Result<Value, String> {
+    let player = &session.player;
+    let value = match name {
+        "position" => json!(player.clock().position().as_secs_f64()),
+        "duration" => json!(player.duration().map(|duration| duration.as_secs_f64())),
+        "paused" => json!(!player.is_playing()),
+        "volume" => json!(player.volume() as f64 * 100.0),
+        "speed" => json!(player.speed()),
+        "vf" => json!(player.video_filter()),
+        "af" => json!(player.audio_filter()),
+        "deinterlace" => json!(format!("{:?}", player.deinterlace_mode()).to_lowercase()),
+        "normalize" => json!(player.normalize_target()),
+        "audio-device" => json!(player.audio_device()),
+        "audio-delay" => json!(player.audio_delay().as_millis()),
+        "output-latency" => json!(player.output_latency().map(|latency| latency.as_secs_f64())),
+        "audio-underruns" => json!(player.underruns()),
+        "audio-device-list" => Value::Array(
+            audio::output_devices()
+                .into_iter()
+                .map(|device| {
+                    json!({
+                        "host": device.host,
+                        "name": device.name,
+                        "default": device.default,
+                    })
+                })
+                .collect(),
+        ),
+        "loudness" => json!({
+            "integrated": player.integrated_loudness(),
+            "gain": player.loudness_gain(),
+            "replaygain": player.replaygain(),
+        }),
+        "path" => json!(player.media_path().to_string_lossy()),
+        "title" => json!(player.title()),
+        "playlist-pos" => json!(playlist.current_index()),
+        "playlist-count" => json!(playlist.len()),
+        "track-list" => Value::Array(
+            player
+                .tracks()
+                .iter()
+                .map(|track| {
+                    // 视频轨固定使用容器推荐的流，不报告选中状态
+                    let selected = match track.kind {
+                        TrackKind::Video => Value::Null,
+                        kind => json!(player.selected_track(kind) == Some(track.index)),
+                    };
+                    json!({
+                        "id": track.index,
+                        "type": format!("{:?}", track.kind).to_lowercase(),
+                        "codec": track.codec,
+                        "lang": track.language,
+                        "title": track.title,
+                        "default": track.default,
+                        "selected": selected,
+                    })
+                })
+                .collect(),
+        ),
+        _ => return Err(format!("未知属性: {}", name)),
+    };
+    Ok(value)
+}
+
+/// 统计信息面板的内容
+fn stats_lines(
+    snapshot: &StatsSnapshot,
+    bitrate: f64,
+    position: Duration,
+    present_stats: PresentStats,
+    upload_time: Duration,
+) -> Vec<String> {
+    let mut lines = vec![format!("容器: {}", snapshot.container)];
+    if let Some(video) = &snapshot.video {
+        lines.push(format!(
+            "视频: {} {}x{} {}",
+            video.codec, video.width, video.height, video.pixel_format
+        ));
+        lines.push(format!("色彩空间: {} / {}", video.color_space, video.color_range));
+        lines.push(format!("解码线程: {}", video.decoder_threads));
+    }
+    if let Some(audio) = &snapshot.audio {
+        lines.push(format!(
+            "音频: {} {} Hz {} 声道 -> {} Hz {} 声道",
+            audio.codec,
+            audio.sample_rate,
+            audio.channels,
+            audio.output_sample_rate,
+            audio.output_channels
+        ));
+        lines.push(format!("输出设备: {}", audio.output_device));
+    }
+    lines.push(format!("码率: {:.0} kbps", bitrate / 1000.0));
+    lines.push(format!(
+        "数据包队列: 视频 {} / 音频 {}",
+        snapshot.video_packets_queued, snapshot.audio_packets_queued
+    ));
+    let fill = if snapshot.ring_buffer_capacity > 0 {
+        snapshot.ring_buffer_len as f64 / snapshot.ring_buffer_capacity as f64 * 100.0
+    } else {
+        0.0
+    };
+    lines.push(format!(
+        "音频缓冲: {} / {} ({:.0}%) 欠载: {}",
+        snapshot.ring_buffer_len, snapshot.ring_buffer_capacity, fill, snapshot.underruns
+    ));
+    let latency = match snapshot.output_latency {
+        Some(latency) => format!("{:.1} ms", latency.as_secs_f64() * 1000.0),
+        None => String::from("未知"),
+    };
+    lines.push(format!("输出延迟: {}", latency));
+    // 正数表示声音超前于画面
+    let offset = match snapshot.audio_position {
+        Some(audio_position) => format!(
+            "{:+.1} ms",
+            (audio_position.as_secs_f64() - position.as_secs_f64()) * 1000.0
+        ),
+        None => String::from("未知"),
+    };
+    lines.push(format!("音画偏差: {}", offset));
+    lines.push(format!(
+        "丢帧: {} 迟到帧: {} 重复帧: {}",
+        present_stats.dropped, snapshot.late_frames, present_stats.repeated
+    ));
+    lines.push(format!(
+        "解码: {:.2} ms 上传: {:.2} ms",
+        snapshot.decode_time.as_secs_f64() * 1000.0,
+        upload_time.as_secs_f64() * 1000.0
+    ));
+    lines
+}
+
+/// 统计信息面板中的响度一行：测得的整体响度和应用的增益及其来源
+fn loudness_line(player: &Player) -> String {
+    let integrated = match player.integrated_loudness() {
+        Some(loudness) => format!("{:.1} LUFS", loudness),
+        None => String::from("测量中"),
+    };
+    let source = if player.replaygain().is_some() {
+        "ReplayGain"
+    } else if player.normalize_target().is_some() {
+        "标准化"
+    } else {
+        "无"
+    };
+    format!("响度: {} 增益: {:+.1} dB ({})", integrated, player.loudness_gain(), source)
+}
+
+fn save_geometry(renderer: &Renderer, config: &Config) {
+    if config.remember_geometry {
+        if let Some(geometry) = renderer.geometry() {
+            if let Err(e) = geometry.save() {
+                tracing::error!("保存窗口几何信息失败: {}", e);
+            }
+        }
+    }
+}
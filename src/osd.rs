@@ -0,0 +1,424 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use glium::{
+    implement_vertex, index::NoIndices, index::PrimitiveType, uniform, Blend, Display,
+    DrawParameters, Frame, Program, Surface, VertexBuffer,
+};
+
+use crate::font_atlas::GlyphAtlas;
+use crate::text;
+
+/// 消息和进度条保持完全显示的时长
+const OSD_TIMEOUT: Duration = Duration::from_secs(2);
+/// 超时后淡出的时长
+const OSD_FADE: Duration = Duration::from_millis(500);
+/// OSD 字号相对于窗口高度的比例
+const OSD_FONT_SCALE: f32 = 0.04;
+/// OSD 文字距窗口边缘的距离，单位为像素
+const OSD_MARGIN: f32 = 16.0;
+/// 文字阴影的偏移，单位为像素
+const SHADOW_OFFSET: f32 = 2.0;
+
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const SHADOW_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.8];
+const PANEL_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.55];
+const BAR_BACKGROUND_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.3];
+
+#[derive(Copy, Clone, Debug)]
+struct OsdVertex {
+    position: [f32; 2],
+    tex_coords: [f32; 2],
+    color: [f32; 4],
+}
+
+implement_vertex!(OsdVertex, position, tex_coords, color);
+
+/// 进度条显示的播放信息，由渲染循环每帧更新
+#[derive(Clone, Debug, Default)]
+pub struct PlaybackInfo {
+    pub position: Duration,
+    pub duration: Option<Duration>,
+    pub title: String,
+    pub paused: bool,
+}
+
+/// 屏幕显示层：左上角的临时消息、右上角的常驻状态以及底部带标题和时间的进度条，
+/// 文字通过字形图集逐字形绘制，作为视频和字幕之后的最后一个绘制通道
+pub struct Osd {
+    program: Program,
+    atlas: Option<GlyphAtlas>,
+    message: Option<String>,
+    message_shown: Option<Instant>,
+    progress_shown: Option<Instant>,
+    status: Option<String>,
+    /// 统计信息面板的各行文字，None 表示不显示
+    stats: Option<Vec<String>>,
+    playback: PlaybackInfo,
+    /// 上一次绘制包含会淡出的内容，完全淡出后还需要再画一次把它擦掉
+    transient_drawn: bool,
+}
+
+impl Osd {
+    pub fn new(display: &Display, font: Option<&Path>) -> Self {
+        let vertex_shader_src = include_str!("shaders/osd_vertex_shader.glsl");
+        let fragment_shader_src = include_str!("shaders/osd_fragment_shader.glsl");
+        let program = Program::from_source(display, vertex_shader_src, fragment_shader_src, None)
+            .expect("Failed to create OSD shader program");
+
+        let pixel_size = display.gl_window().window().inner_size().height as f32 * OSD_FONT_SCALE;
+        let atlas =
+            text::load_font(font).map(|font| GlyphAtlas::new(display, font, pixel_size.max(12.0)));
+
+        Self {
+            program,
+            atlas,
+            message: None,
+            message_shown: None,
+            progress_shown: None,
+            status: None,
+            stats: None,
+            playback: PlaybackInfo::default(),
+            transient_drawn: false,
+        }
+    }
+
+    /// 显示一条临时消息，同时显示进度条，超时后淡出
+    pub fn show_message(&mut self, message: impl Into<String>) {
+        let message = message.into();
+        tracing::debug!("OSD: {}", message);
+        let now = Instant::now();
+        self.message = Some(message);
+        self.message_shown = Some(now);
+        self.progress_shown = Some(now);
+    }
+
+    /// 只显示进度条，不显示消息，超时后淡出
+    pub fn show_progress(&mut self) {
+        self.progress_shown = Some(Instant::now());
+    }
+
+    /// 设置右上角的常驻状态文字，内容变化时返回 true
+    pub fn set_status(&mut self, status: Option<String>) -> bool {
+        if status == self.status {
+            return false;
+        }
+        self.status = status;
+        true
+    }
+
+    /// 设置左上角统计信息面板的内容，内容变化时返回 true
+    pub fn set_stats(&mut self, stats: Option<Vec<String>>) -> bool {
+        if stats == self.stats {
+            return false;
+        }
+        self.stats = stats;
+        true
+    }
+
+    pub fn set_playback(&mut self, playback: PlaybackInfo) {
+        self.playback = playback;
+    }
+
+    /// 消息或进度条仍在显示或淡出，渲染循环需要持续重绘
+    pub fn is_animating(&self, now: Instant) -> bool {
+        let message_alpha = fade_alpha(self.message_shown, now);
+        let progress_alpha = self.progress_alpha(now);
+        if message_alpha == 0.0 && progress_alpha == 0.0 {
+            return self.transient_drawn;
+        }
+        // 暂停时常驻的进度条不会变化，不需要重绘
+        message_alpha > 0.0 || !self.playback.paused || progress_alpha < 1.0
+    }
+
+    pub fn draw(&mut self, display: &Display, target: &mut Frame, surface_size: (u32, u32)) {
+        let now = Instant::now();
+        let message_alpha = fade_alpha(self.message_shown, now);
+        let progress_alpha = self.progress_alpha(now);
+        self.transient_drawn = message_alpha > 0.0 || progress_alpha > 0.0;
+
+        let Some(atlas) = &mut self.atlas else {
+            return;
+        };
+        let (surface_width, surface_height) = surface_size;
+        if surface_width == 0 || surface_height == 0 {
+            return;
+        }
+        atlas.set_pixel_size((surface_height as f32 * OSD_FONT_SCALE).max(12.0));
+
+        let mut batch = QuadBatch::new(surface_width as f32, surface_height as f32);
+
+        if let (Some(message), true) = (&self.message, message_alpha > 0.0) {
+            let baseline = OSD_MARGIN + atlas.ascent();
+            batch.text(atlas, message, OSD_MARGIN, baseline, message_alpha);
+        }
+
+        if let Some(status) = &self.status {
+            let width = atlas.measure(status);
+            let x = surface_width as f32 - OSD_MARGIN - width;
+            let baseline = OSD_MARGIN + atlas.ascent();
+            batch.text(atlas, status, x, baseline, 1.0);
+        }
+
+        if let Some(lines) = &self.stats {
+            // 面板放在消息行下方，避免和临时消息重叠
+            let top = OSD_MARGIN + atlas.line_height() * 1.5;
+            Self::layout_stats(&mut batch, atlas, lines, top);
+        }
+
+        if progress_alpha > 0.0 {
+            Self::layout_progress(&mut batch, atlas, &self.playback, progress_alpha);
+        }
+
+        if batch.vertices.is_empty() {
+            return;
+        }
+
+        let vertex_buffer = VertexBuffer::new(display, &batch.vertices)
+            .expect("Failed to create OSD vertex buffer");
+        let uniforms = uniform! {
+            atlas_tex: atlas.texture(),
+        };
+        let parameters = DrawParameters {
+            blend: Blend::alpha_blending(),
+            ..Default::default()
+        };
+        target
+            .draw(
+                &vertex_buffer,
+                NoIndices(PrimitiveType::TrianglesList),
+                &self.program,
+                &uniforms,
+                &parameters,
+            )
+            .unwrap();
+    }
+
+    /// 暂停时进度条常驻
+    fn progress_alpha(&self, now: Instant) -> f32 {
+        if self.playback.paused && self.progress_shown.is_some() {
+            1.0
+        } else {
+            fade_alpha(self.progress_shown, now)
+        }
+    }
+
+    /// 半透明底色上逐行绘制统计信息
+    fn layout_stats(batch: &mut QuadBatch, atlas: &mut GlyphAtlas, lines: &[String], top: f32) {
+        let padding = atlas.pixel_size() * 0.5;
+        let line_height = atlas.line_height();
+        let width = lines
+            .iter()
+            .map(|line| atlas.measure(line))
+            .fold(0.0, f32::max);
+        let height = line_height * lines.len() as f32;
+
+        batch.rect(
+            atlas,
+            [
+                OSD_MARGIN,
+                top,
+                width + padding * 2.0,
+                height + padding * 2.0,
+            ],
+            PANEL_COLOR,
+        );
+        let mut baseline = top + padding + atlas.ascent();
+        for line in lines {
+            batch.text(atlas, line, OSD_MARGIN + padding, baseline, 1.0);
+            baseline += line_height;
+        }
+    }
+
+    /// 底部面板：第一行左侧标题、右侧时间，第二行进度条
+    fn layout_progress(
+        batch: &mut QuadBatch,
+        atlas: &mut GlyphAtlas,
+        playback: &PlaybackInfo,
+        alpha: f32,
+    ) {
+        let padding = atlas.pixel_size() * 0.5;
+        let bar_height = (atlas.pixel_size() * 0.25).max(3.0);
+        let line_height = atlas.line_height();
+        let panel_height = padding * 3.0 + line_height + bar_height;
+        let panel_top = batch.height - panel_height;
+
+        batch.rect(
+            atlas,
+            [0.0, panel_top, batch.width, panel_height],
+            with_alpha(PANEL_COLOR, alpha),
+        );
+
+        let time = match playback.duration {
+            Some(duration) => format!(
+                "{} / {}",
+                format_clock(playback.position),
+                format_clock(duration)
+            ),
+            None => format_clock(playback.position),
+        };
+        let time_width = atlas.measure(&time);
+        let baseline = panel_top + padding + atlas.ascent();
+        batch.text(
+            atlas,
+            &time,
+            batch.width - padding - time_width,
+            baseline,
+            alpha,
+        );
+
+        let title_width = batch.width - padding * 3.0 - time_width;
+        let title = truncate_to_width(atlas, &playback.title, title_width);
+        batch.text(atlas, &title, padding, baseline, alpha);
+
+        let bar_top = panel_top + padding * 2.0 + line_height;
+        let bar_width = batch.width - padding * 2.0;
+        batch.rect(
+            atlas,
+            [padding, bar_top, bar_width, bar_height],
+            with_alpha(BAR_BACKGROUND_COLOR, alpha),
+        );
+        if let Some(duration) = playback.duration.filter(|duration| !duration.is_zero()) {
+            let fraction =
+                (playback.position.as_secs_f32() / duration.as_secs_f32()).clamp(0.0, 1.0);
+            batch.rect(
+                atlas,
+                [padding, bar_top, bar_width * fraction, bar_height],
+                with_alpha(TEXT_COLOR, alpha),
+            );
+        }
+    }
+}
+
+/// 收集本帧 OSD 的所有四边形，坐标以窗口像素为单位（原点在左上角）
+struct QuadBatch {
+    width: f32,
+    height: f32,
+    vertices: Vec<OsdVertex>,
+}
+
+impl QuadBatch {
+    fn new(width: f32, height: f32) -> Self {
+        Self {
+            width,
+            height,
+            vertices: Vec::new(),
+        }
+    }
+
+    /// 带阴影的单行文本，baseline 为基线的 y 坐标
+    fn text(&mut self, atlas: &mut GlyphAtlas, text: &str, x: f32, baseline: f32, alpha: f32) {
+        let shadow = with_alpha(SHADOW_COLOR, alpha);
+        self.glyphs(
+            atlas,
+            text,
+            x + SHADOW_OFFSET,
+            baseline + SHADOW_OFFSET,
+            shadow,
+        );
+        self.glyphs(atlas, text, x, baseline, with_alpha(TEXT_COLOR, alpha));
+    }
+
+    fn glyphs(
+        &mut self,
+        atlas: &mut GlyphAtlas,
+        text: &str,
+        x: f32,
+        baseline: f32,
+        color: [f32; 4],
+    ) {
+        let mut caret = x;
+        let mut previous = None;
+        for ch in text.chars() {
+            let Some(glyph) = atlas.glyph(ch) else {
+                continue;
+            };
+            if let Some(previous) = previous {
+                caret += atlas.kern(previous, glyph.id);
+            }
+            previous = Some(glyph.id);
+
+            if glyph.size[0] > 0.0 && glyph.size[1] > 0.0 {
+                self.quad(
+                    [
+                        caret + glyph.offset[0],
+                        baseline + glyph.offset[1],
+                        glyph.size[0],
+                        glyph.size[1],
+                    ],
+                    glyph.uv,
+                    color,
+                );
+            }
+            caret += glyph.advance;
+        }
+    }
+
+    /// 纯色矩形 [x, y, width, height]
+    fn rect(&mut self, atlas: &GlyphAtlas, rect: [f32; 4], color: [f32; 4]) {
+        let [u, v] = atlas.solid_uv();
+        self.quad(rect, [u, v, u, v], color);
+    }
+
+    fn quad(&mut self, rect: [f32; 4], uv: [f32; 4], color: [f32; 4]) {
+        let [x, y, width, height] = rect;
+        let left = x / self.width * 2.0 - 1.0;
+        let right = (x + width) / self.width * 2.0 - 1.0;
+        let top = 1.0 - y / self.height * 2.0;
+        let bottom = 1.0 - (y + height) / self.height * 2.0;
+        let [u_left, v_top, u_right, v_bottom] = uv;
+
+        let vertex = |position: [f32; 2], tex_coords: [f32; 2]| OsdVertex {
+            position,
+            tex_coords,
+            color,
+        };
+        self.vertices.extend_from_slice(&[
+            vertex([left, top], [u_left, v_top]),
+            vertex([left, bottom], [u_left, v_bottom]),
+            vertex([right, bottom], [u_right, v_bottom]),
+            vertex([left, top], [u_left, v_top]),
+            vertex([right, bottom], [u_right, v_bottom]),
+            vertex([right, top], [u_right, v_top]),
+        ]);
+    }
+}
+
+/// 超时前完全不透明，之后线性淡出
+fn fade_alpha(shown: Option<Instant>, now: Instant) -> f32 {
+    let Some(shown) = shown else {
+        return 0.0;
+    };
+    let elapsed = now.saturating_duration_since(shown);
+    if elapsed <= OSD_TIMEOUT {
+        return 1.0;
+    }
+    (1.0 - (elapsed - OSD_TIMEOUT).as_secs_f32() / OSD_FADE.as_secs_f32()).max(0.0)
+}
+
+fn with_alpha(color: [f32; 4], alpha: f32) -> [f32; 4] {
+    [color[0], color[1], color[2], color[3] * alpha]
+}
+
+/// 标题过长时截断并以省略号结尾
+fn truncate_to_width(atlas: &mut GlyphAtlas, text: &str, max_width: f32) -> String {
+    if atlas.measure(text) <= max_width {
+        return text.to_string();
+    }
+    let mut truncated: String = text.to_string();
+    while !truncated.is_empty() && atlas.measure(&format!("{}…", truncated)) > max_width {
+        truncated.pop();
+    }
+    format!("{}…", truncated)
+}
+
+/// 进度条使用的 H:MM:SS 格式
+fn format_clock(position: Duration) -> String {
+    let seconds = position.as_secs();
+    let hours = seconds / 3600;
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, seconds / 60 % 60, seconds % 60)
+    } else {
+        format!("{:02}:{:02}", seconds / 60, seconds % 60)
+    }
+}
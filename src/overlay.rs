@@ -0,0 +1,127 @@
+use glium::{
+    index::PrimitiveType, texture::RawImage2d, uniform, Blend, Display, DrawParameters, Frame,
+    IndexBuffer, Program, Surface, Texture2d, VertexBuffer,
+};
+
+use crate::renderer::Vertex;
+
+/// 已上传到 GPU 的叠加层图像
+pub struct OverlayImage {
+    texture: Texture2d,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl OverlayImage {
+    /// rgba 为按行自上而下排列的非预乘 RGBA 像素
+    pub fn new(display: &Display, width: u32, height: u32, rgba: Vec<u8>) -> Self {
+        let image = RawImage2d::from_raw_rgba(rgba, (width, height));
+        let texture = Texture2d::new(display, image).expect("Failed to create overlay texture");
+        Self {
+            texture,
+            width,
+            height,
+        }
+    }
+}
+
+/// 叠加层绘制通道：在视频画面之上以 alpha 混合绘制字幕、OSD 等图像
+pub struct OverlayPass {
+    program: Program,
+    index_buffer: IndexBuffer<u16>,
+}
+
+impl OverlayPass {
+    pub fn new(display: &Display) -> Self {
+        let vertex_shader_src = include_str!("shaders/overlay_vertex_shader.glsl");
+        let fragment_shader_src = include_str!("shaders/overlay_fragment_shader.glsl");
+
+        let program = Program::from_source(display, vertex_shader_src, fragment_shader_src, None)
+            .expect("Failed to create overlay shader program");
+
+        let index_buffer = IndexBuffer::new(
+            display,
+            PrimitiveType::TrianglesList,
+            &[0u16, 1, 2, 0, 2, 3],
+        )
+        .expect("Failed to create overlay index buffer");
+
+        Self {
+            program,
+            index_buffer,
+        }
+    }
+
+    /// 在标准化设备坐标矩形 [left, bottom, right, top] 内绘制图像
+    pub fn draw(
+        &self,
+        display: &Display,
+        target: &mut Frame,
+        image: &OverlayImage,
+        rect: [f32; 4],
+        opacity: f32,
+    ) {
+        let [left, bottom, right, top] = rect;
+        let vertex_buffer = VertexBuffer::new(
+            display,
+            &[
+                Vertex {
+                    position: [left, bottom],
+                    tex_coords: [0.0, 1.0],
+                },
+                Vertex {
+                    position: [right, bottom],
+                    tex_coords: [1.0, 1.0],
+                },
+                Vertex {
+                    position: [right, top],
+                    tex_coords: [1.0, 0.0],
+                },
+                Vertex {
+                    position: [left, top],
+                    tex_coords: [0.0, 0.0],
+                },
+            ],
+        )
+        .expect("Failed to create overlay vertex buffer");
+
+        let uniforms = uniform! {
+            overlay_tex: &image.texture,
+            opacity: opacity,
+        };
+
+        let parameters = DrawParameters {
+            blend: Blend::alpha_blending(),
+            ..Default::default()
+        };
+
+        target
+            .draw(
+                &vertex_buffer,
+                &self.index_buffer,
+                &self.program,
+                &uniforms,
+                &parameters,
+            )
+            .unwrap();
+    }
+}
+
+/// 把窗口像素矩形（原点在左上角）换算为标准化设备坐标 [left, bottom, right, top]
+pub fn pixel_rect_to_ndc(
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    surface_width: u32,
+    surface_height: u32,
+) -> [f32; 4] {
+    let surface_width = surface_width.max(1) as f32;
+    let surface_height = surface_height.max(1) as f32;
+    [
+        x / surface_width * 2.0 - 1.0,
+        1.0 - (y + height) / surface_height * 2.0,
+        (x + width) / surface_width * 2.0 - 1.0,
+        1.0 - y / surface_height * 2.0,
+    ]
+}
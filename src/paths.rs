@@ -0,0 +1,21 @@
+use std::path::PathBuf;
+
+const APP_DIR_NAME: &str = "player";
+
+/// 状态目录：`$XDG_STATE_HOME/player`，未设置时回退到 `~/.local/state/player`
+pub fn state_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_STATE_HOME", ".local/state")
+}
+
+/// 配置目录：`$XDG_CONFIG_HOME/player`，未设置时回退到 `~/.config/player`
+pub fn config_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_CONFIG_HOME", ".config")
+}
+
+fn xdg_dir(env_key: &str, home_fallback: &str) -> Option<PathBuf> {
+    let base = std::env::var_os(env_key)
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(home_fallback)))?;
+    Some(base.join(APP_DIR_NAME))
+}
@@ -1,103 +1,666 @@
 extern crate ffmpeg_next as ffmpeg;
 
-use std::path::PathBuf;
+use std::cell::{Cell, RefCell};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
 use futures::{future::OptionFuture, FutureExt};
 
-use super::{audio, video};
+use super::{audio, subtitle, video};
+use super::audio::AudioSettings;
+use super::clock::PlaybackClock;
+use super::deinterlace::{DeinterlaceMode, DeinterlaceSettings, Deinterlacer, Field};
+use super::file_state::FileState;
+use super::filter::{AudioFilter, FilterSpec, VideoFilter};
+use super::loudness::{ReplayGain, ReplayGainMode};
+use super::screenshot;
+use super::stats::PlaybackStats;
+use super::visualization::AudioWindow;
 
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
+
+/// 音量上限，1.0 为原始音量
+const MAX_VOLUME: f32 = 2.0;
+/// 播放速度范围
+const MIN_SPEED: f32 = 0.25;
+const MAX_SPEED: f32 = 4.0;
+/// 响度标准化目标的范围（LUFS）
+const MIN_NORMALIZE_TARGET: f32 = -70.0;
+const MAX_NORMALIZE_TARGET: f32 = 0.0;
+/// 音频延迟的范围（毫秒）。画面和声音错开后，解封装线程要在一条数据包队列里
+/// 多攒出这段时间的数据包才能喂到另一条流：两条队列各 128 个包，
+/// 60 fps 视频约 2.1 s，AAC 音频约 2.7 s，再加上 200 ms 的环形缓冲区。
+/// 超出时队列被塞满，解封装线程阻塞，另一条流欠载或卡顿，这里留出余量
+const MAX_AUDIO_DELAY_MS: i64 = 1500;
+/// 播放位置离开头或结尾不到该时长时不记录续播位置，下次从头播放
+const RESUME_MARGIN: Duration = Duration::from_secs(10);
 
 #[derive(Clone, Copy, Debug)]
 pub enum ControlCommand {
     Play,
     Pause,
+    /// 切换到指定流索引的音轨或字幕轨，由解封装线程处理
+    SelectTrack(TrackKind, usize),
+    /// 设置循环模式，解封装线程负责跳转，解码线程丢弃片段终点之后的帧
+    SetLoop(LoopMode),
+    /// 跳转到指定位置，由解封装线程处理
+    Seek(Duration),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LoopMode {
+    #[default]
+    Off,
+    /// 播放到文件末尾后从头开始
+    File,
+    /// 在 A、B 两点之间循环
+    Segment { a: Duration, b: Duration },
+}
+
+impl LoopMode {
+    /// 片段循环的终点，解码线程丢弃该时间之后的帧
+    pub fn segment_end(&self) -> Option<Duration> {
+        match self {
+            LoopMode::Segment { b, .. } => Some(*b),
+            _ => None,
+        }
+    }
+}
+
+/// 解封装线程通过数据包通道发给解码线程的消息
+pub enum PacketMessage {
+    Packet(ffmpeg::codec::packet::packet::Packet),
+    /// 输入跳转到了新位置：冲刷解码器并丢弃该位置之前的帧
+    Discontinuity(Duration),
+    /// 用户跳转：与 Discontinuity 相同，但播放时钟已经由 Player::seek 拨到了新位置
+    Seek(Duration),
+    /// 输入结束：冲刷解码器，播放完剩余数据后报告结束
+    EndOfStream,
+}
+
+/// 音频相对画面的延迟，调节范围为前后各 MAX_AUDIO_DELAY_MS 毫秒
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AudioDelay {
+    /// 声音晚于画面
+    Late(Duration),
+    /// 声音早于画面
+    Early(Duration),
+}
+
+impl AudioDelay {
+    /// 按毫秒构造，正数表示声音晚于画面
+    pub fn from_millis(delay_ms: i64) -> Self {
+        let delay = Duration::from_millis(delay_ms.unsigned_abs());
+        if delay_ms < 0 {
+            Self::Early(delay)
+        } else {
+            Self::Late(delay)
+        }
+    }
+
+    /// 换算成毫秒，正数表示声音晚于画面
+    pub fn as_millis(self) -> i64 {
+        match self {
+            Self::Late(delay) => delay.as_millis() as i64,
+            Self::Early(delay) => -(delay.as_millis() as i64),
+        }
+    }
+}
+
+impl Default for AudioDelay {
+    fn default() -> Self {
+        Self::Late(Duration::ZERO)
+    }
+}
+
+impl std::fmt::Display for AudioDelay {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:+} ms", self.as_millis())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrackKind {
+    Audio,
+    Video,
+    Subtitle,
+}
+
+/// 容器中一条媒体流的描述信息
+#[derive(Clone, Debug)]
+pub struct TrackInfo {
+    pub kind: TrackKind,
+    /// 流在容器中的索引，select_track 使用该索引
+    pub index: usize,
+    pub codec: String,
+    /// 流元数据中的语言标签，例如 eng、chi
+    pub language: Option<String>,
+    pub title: Option<String>,
+    /// 容器是否把该流标记为默认流
+    pub default: bool,
+}
+
+impl std::fmt::Display for TrackInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{} {}", self.index, self.codec)?;
+        if let Some(language) = &self.language {
+            write!(f, " [{}]", language)?;
+        }
+        if let Some(title) = &self.title {
+            write!(f, " {}", title)?;
+        }
+        Ok(())
+    }
+}
+
+/// 启动时选择音轨和字幕轨的语言偏好，按顺序匹配，都不匹配时使用容器推荐的流
+#[derive(Clone, Debug, Default)]
+pub struct TrackPreferences {
+    pub audio_languages: Vec<String>,
+    pub subtitle_languages: Vec<String>,
+}
+
+/// 启动播放器的选项
+#[derive(Clone, Debug, Default)]
+pub struct PlayerOptions {
+    pub track_preferences: TrackPreferences,
+    /// 以暂停状态启动：照常解封装和解码，但时钟不走、音频不输出，
+    /// 用于提前打开播放列表的下一项，调用 play 后无缝接上
+    pub start_paused: bool,
+    /// libavfilter 视频滤镜链，例如 yadif,hqdn3d,eq=contrast=1.1；空字符串表示不使用滤镜
+    pub video_filter: String,
+    /// libavfilter 音频滤镜链，例如 highpass=f=80,loudnorm；空字符串表示不使用滤镜
+    pub audio_filter: String,
+    pub deinterlace: DeinterlaceMode,
+    pub deinterlacer: Deinterlacer,
+    /// 去隔行时按场频输出
+    pub field_rate: bool,
+    /// 使用容器或音频流元数据中的 ReplayGain/R128 增益
+    pub replaygain: ReplayGainMode,
+    /// 叠加在 ReplayGain 增益上的前置放大（dB）
+    pub replaygain_preamp: f32,
+    /// 没有 ReplayGain 标签时按实时测得的响度标准化到的目标响度（LUFS），None 表示不标准化
+    pub normalize_target: Option<f32>,
+    /// 音频输出设备名，None 表示系统默认设备
+    pub audio_device: Option<String>,
+    /// 音频环形缓冲区时长，None 表示使用默认值
+    pub audio_buffer: Option<Duration>,
+    /// cpal 设备缓冲区帧数，None 表示使用后端默认值
+    pub audio_device_buffer: Option<u32>,
+    /// 音频相对画面的延迟
+    pub audio_delay: AudioDelay,
+    /// 从上次关闭时的位置继续播放，并在关闭时记录当前位置
+    pub resume: bool,
+    /// 初始音量，1.0 为原始音量，None 表示原始音量
+    pub volume: Option<f32>,
+    /// 视频解码线程数，None 表示使用所有 CPU 核心
+    pub decoder_threads: Option<usize>,
+    /// 使用外挂字幕：不选择内嵌字幕轨，也不启动字幕解码线程
+    pub external_subtitles: bool,
+}
+
+/// 解封装和各解码线程的结束状态
+#[derive(Default)]
+struct EndOfStream {
+    demuxer: AtomicBool,
+    video: Arc<AtomicBool>,
+    audio: Arc<AtomicBool>,
 }
 
 pub struct Player {
     control_sender: smol::channel::Sender<ControlCommand>,
     demuxer_thread: Option<std::thread::JoinHandle<()>>,
     playing: bool,
-    playing_changed_callback: Box<dyn Fn(bool)>,
+    playing_changed_callback: Box<dyn Fn(bool) + Send>,
+    clock: PlaybackClock,
+    path: PathBuf,
+    tracks: Vec<TrackInfo>,
+    audio_track: Option<usize>,
+    subtitle_track: Option<usize>,
+    end_of_stream: Arc<EndOfStream>,
+    loop_mode: LoopMode,
+    duration: Option<Duration>,
+    title: String,
+    stats: Arc<PlaybackStats>,
+    audio_settings: Arc<AudioSettings>,
+    video_filter: Arc<FilterSpec>,
+    deinterlace: Arc<DeinterlaceSettings>,
+    /// 有会动的视频流；只有封面图或没有视频流时为 false
+    has_video: bool,
+    /// 有作为封面的附加图片流
+    has_cover_art: bool,
+    resume: bool,
+    /// 启动时续播的位置
+    resumed_from: Option<Duration>,
+    /// 开始播放过，只预读而没有播放过的文件关闭时不改动续播记录
+    started: bool,
+    /// 暂停期间跳转过，解封装线程只转发了画面和字幕，恢复播放时要重新跳转让音频跟上
+    paused_seek: bool,
 }
 
 impl Player {
     pub fn start(
         path: PathBuf,
-        video_frame_callback: impl FnMut(&ffmpeg::util::frame::Video) + Send + 'static,
-        playing_changed_callback: impl Fn(bool) + 'static,
+        options: PlayerOptions,
+        video_frame_callback: impl FnMut(&ffmpeg::util::frame::Video, Duration, Option<Field>)
+            + Send
+            + 'static,
+        subtitle_callback: impl Fn(subtitle::SubtitleCue) + Send + Sync + 'static,
+        playing_changed_callback: impl Fn(bool) + Send + 'static,
     ) -> Result<Self, anyhow::Error> {
         info!("开始播放视频文件: {:?}", path);
         let (control_sender, control_receiver) = smol::channel::unbounded();
 
+        let clock = PlaybackClock::new();
+        if options.start_paused {
+            clock.pause();
+        }
+        clock.set_audio_delay_ms(
+            options.audio_delay.as_millis().clamp(-MAX_AUDIO_DELAY_MS, MAX_AUDIO_DELAY_MS),
+        );
+        let video_clock = clock.clone();
+        let audio_clock = clock.clone();
+        let media_path = path.clone();
+        let start_paused = options.start_paused;
+        let resume = options.resume;
+        let preferences = options.track_preferences;
+
+        let end_of_stream = Arc::new(EndOfStream::default());
+        let demuxer_end_of_stream = end_of_stream.clone();
+
+        info!("初始化输入上下文");
+        let mut input_context = ffmpeg::format::input(&path)?;
+
+        // 容器时长以 AV_TIME_BASE（微秒）为单位，未知时为负数
+        let duration = u64::try_from(input_context.duration())
+            .ok()
+            .filter(|&duration| duration > 0)
+            .map(Duration::from_micros);
+        let title = input_context
+            .metadata()
+            .get("title")
+            .filter(|title| !title.trim().is_empty())
+            .map(str::to_string)
+            .or_else(|| path.file_name().map(|name| name.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| path.to_string_lossy().into_owned());
+        info!("媒体标题: {}，时长: {:?}", title, duration);
+
+        let stats = Arc::new(PlaybackStats::new());
+        let format = input_context.format();
+        stats.set_container(format!("{} ({})", format.name(), format.description()));
+        let demuxer_stats = stats.clone();
+        let audio_settings = Arc::new(AudioSettings::new(&options.audio_filter));
+        audio_settings.set_normalize_target(
+            options
+                .normalize_target
+                .map(|target| target.clamp(MIN_NORMALIZE_TARGET, MAX_NORMALIZE_TARGET)),
+        );
+        audio_settings.set_device(options.audio_device);
+        if let Some(buffer) = options.audio_buffer {
+            audio_settings.set_buffer_duration(buffer);
+        }
+        audio_settings.set_device_buffer_frames(options.audio_device_buffer);
+        if let Some(volume) = options.volume {
+            audio_settings.set_volume(volume.clamp(0.0, MAX_VOLUME));
+        }
+        let decoder_threads = options.decoder_threads.unwrap_or_else(num_cpus::get).max(1);
+        let replaygain_mode = options.replaygain;
+        let replaygain_preamp = options.replaygain_preamp;
+        let demuxer_audio_settings = audio_settings.clone();
+        let video_filter = Arc::new(FilterSpec::new(&options.video_filter));
+        let demuxer_video_filter = video_filter.clone();
+        let deinterlace = Arc::new(DeinterlaceSettings::new(
+            options.deinterlace,
+            options.deinterlacer,
+            options.field_rate,
+        ));
+        let demuxer_deinterlace = deinterlace.clone();
+
+        // 音乐文件的封面以附加图片的形式作为视频流存放，只有一个数据包
+        let video_stream = input_context.streams().best(ffmpeg::media::Type::Video);
+        let has_cover_art = video_stream.as_ref().is_some_and(is_attached_picture);
+        let has_video = video_stream.is_some() && !has_cover_art;
+        info!("视频流: {}，封面: {}", has_video, has_cover_art);
+
+        let tracks = collect_tracks(&input_context);
+        for track in &tracks {
+            info!("{:?} 轨道: {}", track.kind, track);
+        }
+
+        let audio_track = preferred_track(&tracks, TrackKind::Audio, &preferences.audio_languages)
+            .or_else(|| best_stream(&input_context, ffmpeg::media::Type::Audio));
+        if video_stream.is_none() && audio_track.is_none() {
+            anyhow::bail!("{:?} 没有可以播放的音频或视频流", path);
+        }
+        let subtitle_track = if options.external_subtitles {
+            None
+        } else {
+            preferred_track(&tracks, TrackKind::Subtitle, &preferences.subtitle_languages)
+                .or_else(|| best_stream(&input_context, ffmpeg::media::Type::Subtitle))
+        };
+
+        if let Some(index) = audio_track {
+            audio_settings.set_replaygain(replaygain_db(
+                &input_context,
+                index,
+                replaygain_mode,
+                replaygain_preamp,
+            ));
+        }
+
+        let subtitle_callback: Arc<dyn Fn(subtitle::SubtitleCue) + Send + Sync> =
+            Arc::new(subtitle_callback);
+
         let demuxer_thread =
             std::thread::Builder::new().name("demuxer thread".into()).spawn(move || {
                 smol::block_on(async move {
-                    info!("初始化输入上下文");
-                    let mut input_context = ffmpeg::format::input(&path).unwrap();
-
                     info!("查找最佳视频流");
-                    let video_stream =
-                        input_context.streams().best(ffmpeg::media::Type::Video).unwrap();
-                    let video_stream_index = video_stream.index();
-                    info!("视频流索引: {}", video_stream_index);
-                    let video_playback_thread = video::VideoPlaybackThread::start(
-                        &video_stream,
-                        Box::new(video_frame_callback),
-                    )
-                    .unwrap();
-
-                    info!("查找最佳音频流");
-                    let audio_stream =
-                        input_context.streams().best(ffmpeg::media::Type::Audio).unwrap();
-                    let audio_stream_index = audio_stream.index();
-                    info!("音频流索引: {}", audio_stream_index);
-                    let audio_playback_thread =
-                        audio::AudioPlaybackThread::start(&audio_stream).unwrap();
+                    let video_stream = input_context.streams().best(ffmpeg::media::Type::Video);
+                    let video_stream_index = video_stream.as_ref().map(|stream| stream.index());
+                    info!("视频流索引: {:?}", video_stream_index);
+                    // 封面图也交给视频线程解码，显示为一帧静止画面
+                    let video_playback_thread = video_stream.map(|stream| {
+                        video::VideoPlaybackThread::start(
+                            &stream,
+                            video_clock,
+                            demuxer_end_of_stream.video.clone(),
+                            demuxer_stats.clone(),
+                            demuxer_video_filter,
+                            demuxer_deinterlace,
+                            decoder_threads,
+                            Box::new(video_frame_callback),
+                        )
+                        .unwrap()
+                    });
+                    if video_playback_thread.is_none() {
+                        demuxer_end_of_stream.video.store(true, Ordering::SeqCst);
+                    }
+
+                    // 没有音频流或音频解码器打不开时只播放画面，音频视为已经播放完毕
+                    info!("音频流索引: {:?}", audio_track);
+                    let audio_stream_index = Cell::new(audio_track);
+                    let audio_playback_thread = RefCell::new(
+                        audio_track.and_then(|index| input_context.stream(index)).and_then(
+                            |stream| {
+                                audio::AudioPlaybackThread::start(
+                                    &stream,
+                                    start_paused,
+                                    demuxer_end_of_stream.audio.clone(),
+                                    demuxer_stats.clone(),
+                                    demuxer_audio_settings.clone(),
+                                    audio_clock.clone(),
+                                )
+                                .map_err(|e| error!("音频解码器初始化失败: {}", e))
+                                .ok()
+                            },
+                        ),
+                    );
+                    if audio_playback_thread.borrow().is_none() {
+                        audio_stream_index.set(None);
+                        demuxer_end_of_stream.audio.store(true, Ordering::SeqCst);
+                    }
+
+                    info!("字幕流索引: {:?}", subtitle_track);
+                    let subtitle_stream_index = Cell::new(subtitle_track);
+                    let subtitle_playback_thread = RefCell::new(
+                        subtitle_track
+                            .and_then(|index| input_context.stream(index))
+                            .and_then(|stream| {
+                                start_subtitle_thread(&stream, subtitle_callback.clone())
+                            }),
+                    );
+
+                    // 切换请求由数据包转发循环在两个数据包之间处理，此时可以访问输入上下文
+                    let pending_selection: Cell<Option<(TrackKind, usize)>> = Cell::new(None);
+                    let loop_mode = Cell::new(LoopMode::Off);
+                    let (seek_sender, seek_receiver) = smol::channel::unbounded::<Duration>();
+                    // 暂停期间跳转时只转发视频和字幕包，让画面和字幕停在新位置；
+                    // 暂停的音频线程不取数据包，恢复播放时 Player 会重新跳转一次让音频跟上
+                    let previewing = Cell::new(false);
+                    let (preview_sender, preview_receiver) = smol::channel::bounded::<()>(1);
+                    let send_audio = {
+                        let audio_playback_thread = &audio_playback_thread;
+                        let previewing = &previewing;
+                        let preview_receiver = &preview_receiver;
+                        move |message: PacketMessage| async move {
+                            let audio = audio_playback_thread.borrow();
+                            let Some(thread) = audio.as_ref() else {
+                                return;
+                            };
+                            if previewing.get() {
+                                return;
+                            }
+                            // 暂停前音频通道可能已经满了，进入预览时放弃发送
+                            let preview_started = async {
+                                while !previewing.get() {
+                                    if preview_receiver.recv().await.is_err() {
+                                        futures::future::pending::<()>().await;
+                                    }
+                                }
+                            };
+                            smol::future::or(
+                                async {
+                                    thread.send_packet_message(message).await;
+                                },
+                                preview_started,
+                            )
+                            .await;
+                        }
+                    };
 
                     let mut playing = true;
+                    let mut forwarding_done = false;
 
                     let packet_forwarder_impl = async {
                         debug!("开始转发数据包");
-                        for (stream, packet) in input_context.packets() {
-                            if stream.index() == audio_stream_index {
+                        loop {
+                            if let Some((kind, index)) = pending_selection.take() {
+                                let Some(stream) = input_context.stream(index) else {
+                                    error!("流索引不存在: {}", index);
+                                    continue;
+                                };
+                                match kind {
+                                    TrackKind::Audio => {
+                                        // 暂停期间切换时新线程同样保持静音，等 Play 再输出
+                                        match audio::AudioPlaybackThread::start(
+                                            &stream,
+                                            previewing.get(),
+                                            demuxer_end_of_stream.audio.clone(),
+                                            demuxer_stats.clone(),
+                                            demuxer_audio_settings.clone(),
+                                            audio_clock.clone(),
+                                        ) {
+                                            Ok(thread) => {
+                                                info!("切换音轨: {}", index);
+                                                demuxer_audio_settings.set_replaygain(
+                                                    replaygain_db(
+                                                        &input_context,
+                                                        index,
+                                                        replaygain_mode,
+                                                        replaygain_preamp,
+                                                    ),
+                                                );
+                                                // 旧线程在替换后被 drop 并等待结束
+                                                audio_playback_thread.replace(Some(thread));
+                                                audio_stream_index.set(Some(index));
+                                            }
+                                            Err(e) => error!("切换音轨失败: {}", e),
+                                        }
+                                    }
+                                    TrackKind::Subtitle => {
+                                        if let Some(thread) =
+                                            start_subtitle_thread(&stream, subtitle_callback.clone())
+                                        {
+                                            info!("切换字幕轨: {}", index);
+                                            subtitle_playback_thread.replace(Some(thread));
+                                            subtitle_stream_index.set(Some(index));
+                                        }
+                                    }
+                                    TrackKind::Video => warn!("不支持切换视频轨"),
+                                }
+                            }
+
+                            let mut user_seek = seek_receiver.try_recv().ok();
+                            let mut packet = ffmpeg::codec::packet::packet::Packet::empty();
+                            let mut loop_start = None;
+                            if user_seek.is_none() {
+                                // 片段终点按视频流判断，只有音频和封面时按音频流判断，
+                                // 只有封面没有音频时仍按封面所在的流判断
+                                let loop_stream = match video_stream_index {
+                                    Some(index) if has_video => Some(index),
+                                    _ => audio_stream_index.get().or(video_stream_index),
+                                };
+                                loop_start = match packet.read(&mut input_context) {
+                                    Ok(()) => match loop_mode.get() {
+                                        LoopMode::Segment { a, b }
+                                            if loop_stream.is_some_and(|stream| {
+                                                packet_reaches(&input_context, &packet, stream, b)
+                                            }) =>
+                                        {
+                                            Some(a)
+                                        }
+                                        _ => None,
+                                    },
+                                    Err(ffmpeg::Error::Eof) => match loop_mode.get() {
+                                        LoopMode::Off => {
+                                            debug!("数据包转发完成");
+                                            if let Some(thread) = &video_playback_thread {
+                                                thread
+                                                    .send_packet_message(PacketMessage::EndOfStream)
+                                                    .await;
+                                            }
+                                            send_audio(PacketMessage::EndOfStream).await;
+                                            demuxer_end_of_stream.demuxer.store(true, Ordering::SeqCst);
+
+                                            // 读完后等待跳转请求，收到后从新位置继续转发
+                                            match seek_receiver.recv().await {
+                                                Ok(position) => user_seek = Some(position),
+                                                Err(_) => break,
+                                            }
+                                            None
+                                        }
+                                        LoopMode::File => Some(Duration::ZERO),
+                                        LoopMode::Segment { a, .. } => Some(a),
+                                    },
+                                    Err(e) => {
+                                        debug!("读取数据包失败: {}", e);
+                                        continue;
+                                    }
+                                };
+                            }
+
+                            if let Some(position) = user_seek {
+                                info!("跳转到 {:?}", position);
+                                // 跳转失败时仍通知解码线程，它们会丢弃目标位置之前的帧
+                                if let Err(e) = seek_input(&mut input_context, position) {
+                                    error!("跳转失败: {}", e);
+                                }
+                                if let Some(thread) = &video_playback_thread {
+                                    thread.send_packet_message(PacketMessage::Seek(position)).await;
+                                }
+                                send_audio(PacketMessage::Seek(position)).await;
+                                continue;
+                            }
+
+                            if let Some(position) = loop_start {
+                                info!("循环播放，跳转到 {:?}", position);
+                                if let Err(e) = seek_input(&mut input_context, position) {
+                                    // 无法循环时按不循环处理，读到末尾后正常结束
+                                    error!("跳转失败，取消循环: {}", e);
+                                    loop_mode.set(LoopMode::Off);
+                                    continue;
+                                }
+                                if let Some(thread) = &video_playback_thread {
+                                    thread
+                                        .send_packet_message(PacketMessage::Discontinuity(position))
+                                        .await;
+                                }
+                                send_audio(PacketMessage::Discontinuity(position)).await;
+                                continue;
+                            }
+
+                            let stream_index = packet.stream();
+                            if Some(stream_index) == audio_stream_index.get() {
                                 debug!("转发音频包");
-                                audio_playback_thread.receive_packet(packet).await;
-                            } else if stream.index() == video_stream_index {
-                                debug!("转发视频包");
-                                video_playback_thread.receive_packet(packet).await;
+                                send_audio(PacketMessage::Packet(packet)).await;
+                            } else if Some(stream_index) == video_stream_index {
+                                if let Some(thread) = &video_playback_thread {
+                                    debug!("转发视频包");
+                                    thread.receive_packet(packet).await;
+                                }
+                            } else if Some(stream_index) == subtitle_stream_index.get() {
+                                if let Some(subtitle_playback_thread) =
+                                    subtitle_playback_thread.borrow().as_ref()
+                                {
+                                    debug!("转发字幕包");
+                                    subtitle_playback_thread.receive_packet(packet).await;
+                                }
                             }
                         }
-                        debug!("数据包转发完成");
+                        debug!("数据包转发结束");
                     }
                     .fuse()
                     .shared();
 
                     loop {
-                        let packet_forwarder: OptionFuture<_> =
-                            if playing { Some(packet_forwarder_impl.clone()) } else { None }.into();
+                        let forwarding = playing || previewing.get();
+                        let packet_forwarder: OptionFuture<_> = if forwarding && !forwarding_done {
+                            Some(packet_forwarder_impl.clone())
+                        } else {
+                            None
+                        }
+                        .into();
 
                         smol::pin!(packet_forwarder);
 
                         futures::select! {
                             _ = packet_forwarder => {
                                 debug!("播放器播放完成");
+                                forwarding_done = true;
                             },
                             received_command = control_receiver.recv().fuse() => {
                                 match received_command {
+                                    Ok(ControlCommand::SelectTrack(kind, index)) => {
+                                        info!("收到切换轨道命令: {:?} {}", kind, index);
+                                        pending_selection.set(Some((kind, index)));
+                                    }
+                                    Ok(ControlCommand::Seek(position)) => {
+                                        info!("收到跳转命令: {:?}", position);
+                                        if !playing && !previewing.get() {
+                                            info!("暂停期间跳转，只解码画面和字幕");
+                                            previewing.set(true);
+                                            let _ = preview_sender.try_send(());
+                                        }
+                                        if let Err(e) = seek_sender.send(position).await {
+                                            error!("发送跳转请求失败: {}", e);
+                                        }
+                                    }
                                     Ok(command) => {
                                         info!("收到控制命令: {:?}", command);
-                                        video_playback_thread.send_control_message(command).await;
-                                        audio_playback_thread.send_control_message(command).await;
+                                        if let Some(thread) = &video_playback_thread {
+                                            thread.send_control_message(command).await;
+                                        }
+                                        if let Some(thread) = &*audio_playback_thread.borrow() {
+                                            thread.send_control_message(command).await;
+                                        }
                                         match command {
                                             ControlCommand::Play => {
                                                 info!("继续播放");
                                                 playing = true;
+                                                previewing.set(false);
                                             },
                                             ControlCommand::Pause => {
                                                 info!("暂停播放");
                                                 playing = false;
                                             }
+                                            ControlCommand::SetLoop(mode) => {
+                                                info!("循环模式: {:?}", mode);
+                                                loop_mode.set(mode);
+                                            }
+                                            ControlCommand::SelectTrack(..)
+                                            | ControlCommand::Seek(_) => {}
                                         }
                                     }
                                     Err(e) => {
@@ -111,26 +674,402 @@ impl Player {
                 })
             })?;
 
-        let playing = true;
+        let playing = !start_paused;
         playing_changed_callback(playing);
 
-        Ok(Self {
+        let mut player = Self {
             control_sender,
             demuxer_thread: Some(demuxer_thread),
             playing,
             playing_changed_callback: Box::new(playing_changed_callback),
-        })
+            clock,
+            path: media_path,
+            tracks,
+            audio_track,
+            subtitle_track,
+            end_of_stream,
+            loop_mode: LoopMode::Off,
+            duration,
+            title,
+            stats,
+            audio_settings,
+            video_filter,
+            deinterlace,
+            has_video,
+            has_cover_art,
+            resume,
+            resumed_from: None,
+            started: playing,
+            paused_seek: false,
+        };
+        if resume {
+            player.resume_from_last_position()?;
+        }
+        Ok(player)
+    }
+
+    /// 跳转到上次关闭时记录的位置，文件变过或记录的位置太靠近结尾时从头播放
+    fn resume_from_last_position(&mut self) -> Result<(), anyhow::Error> {
+        let Some(position) = FileState::load(&self.path).resume_position(&self.path) else {
+            return Ok(());
+        };
+        if self
+            .duration
+            .is_some_and(|duration| position + RESUME_MARGIN >= duration)
+        {
+            return Ok(());
+        }
+        info!("从上次的位置继续播放: {:?}", position);
+        self.seek(position)?;
+        self.resumed_from = Some(position);
+        Ok(())
+    }
+
+    /// 启动时续播的位置，None 表示从头播放
+    pub fn resumed_from(&self) -> Option<Duration> {
+        self.resumed_from
+    }
+
+    /// 记录当前位置供下次续播，播放完毕或位置靠近开头、结尾时清除记录
+    fn save_resume_position(&self) {
+        let position = self.clock.position();
+        let near_end = self
+            .duration
+            .is_some_and(|duration| position + RESUME_MARGIN >= duration);
+        let position = if self.is_finished() || near_end || position < RESUME_MARGIN {
+            None
+        } else {
+            Some(position)
+        };
+        let mut state = FileState::load(&self.path);
+        if state.resume.map(|resume| resume.position) == position {
+            return;
+        }
+        state.set_resume_position(&self.path, position);
+        if let Err(e) = state.save(&self.path) {
+            error!("保存续播位置失败: {}", e);
+        }
+    }
+
+    pub fn loop_mode(&self) -> LoopMode {
+        self.loop_mode
+    }
+
+    /// 设置整个文件循环或 A-B 片段循环，片段终点必须晚于起点
+    pub fn set_loop(&mut self, mode: LoopMode) -> Result<(), anyhow::Error> {
+        if let LoopMode::Segment { a, b } = mode {
+            if b <= a {
+                anyhow::bail!("循环终点 {:?} 必须晚于起点 {:?}", b, a);
+            }
+        }
+        info!("设置循环模式: {:?}", mode);
+        self.control_sender.send_blocking(ControlCommand::SetLoop(mode))?;
+        self.loop_mode = mode;
+        Ok(())
+    }
+
+    /// 所有数据包都已读完，解码线程仍在消耗缓冲的数据，此时适合预读播放列表的下一项
+    pub fn is_demux_finished(&self) -> bool {
+        self.end_of_stream.demuxer.load(Ordering::SeqCst)
+    }
+
+    /// 音频缓冲区播放完毕且最后一帧视频已到显示时间
+    pub fn is_finished(&self) -> bool {
+        self.end_of_stream.demuxer.load(Ordering::SeqCst)
+            && self.end_of_stream.video.load(Ordering::SeqCst)
+            && self.end_of_stream.audio.load(Ordering::SeqCst)
+    }
+
+    /// 跳转到 position：播放时钟立即拨到目标位置，解封装线程随后跳转输入，
+    /// 解码线程丢弃跳转前的数据。暂停时也会立即解码并显示目标位置的画面
+    pub fn seek(&mut self, position: Duration) -> Result<(), anyhow::Error> {
+        let position = match self.duration {
+            Some(duration) => position.min(duration),
+            None => position,
+        };
+        info!("跳转到 {:?}", position);
+        // 播放结束后也可以跳转，之后重新等待输入结束
+        self.end_of_stream.demuxer.store(false, Ordering::SeqCst);
+        self.clock.set_position(position);
+        if !self.playing {
+            self.paused_seek = true;
+        }
+        self.control_sender.send_blocking(ControlCommand::Seek(position))?;
+        Ok(())
+    }
+
+    /// 当前音量，1.0 为原始音量
+    pub fn volume(&self) -> f32 {
+        self.audio_settings.volume()
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        let volume = volume.clamp(0.0, MAX_VOLUME);
+        info!("音量: {:.0}%", volume * 100.0);
+        self.audio_settings.set_volume(volume);
+    }
+
+    /// 音频相对画面的延迟
+    pub fn audio_delay(&self) -> AudioDelay {
+        AudioDelay::from_millis(self.clock.audio_delay_ms())
+    }
+
+    /// 设置音频延迟，通过挪动画面的时钟实现，不会跳转或打断声音
+    pub fn set_audio_delay(&mut self, delay: AudioDelay) {
+        let delay_ms = delay.as_millis().clamp(-MAX_AUDIO_DELAY_MS, MAX_AUDIO_DELAY_MS);
+        info!("音频延迟: {} ms", delay_ms);
+        self.clock.set_audio_delay_ms(delay_ms);
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.audio_settings.speed()
+    }
+
+    /// 改变播放速度，声音通过重采样变速，音调随之改变
+    pub fn set_speed(&mut self, speed: f32) {
+        let speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+        info!("播放速度: {}", speed);
+        self.clock.set_rate(speed as f64);
+        self.audio_settings.set_speed(speed);
+    }
+
+    /// 当前的视频滤镜链，空字符串表示不使用滤镜
+    pub fn video_filter(&self) -> String {
+        self.video_filter.get()
+    }
+
+    /// 替换视频滤镜链，解码线程从下一帧开始使用新的滤镜图；
+    /// 滤镜名或参数无效时返回错误，保留原来的滤镜链
+    pub fn set_video_filter(&mut self, spec: &str) -> Result<(), anyhow::Error> {
+        if !spec.trim().is_empty() {
+            VideoFilter::check(spec)
+                .map_err(|e| anyhow::anyhow!("无效的视频滤镜 {}: {}", spec, e))?;
+        }
+        info!("视频滤镜: {:?}", spec);
+        self.video_filter.set(spec);
+        Ok(())
+    }
+
+    /// 当前的音频滤镜链，空字符串表示不使用滤镜
+    pub fn audio_filter(&self) -> String {
+        self.audio_settings.filter().get()
+    }
+
+    /// 替换音频滤镜链，音频线程从下一帧开始使用新的滤镜图，旧滤镜中缓存的少量采样会被丢弃；
+    /// 滤镜名或参数无效时返回错误，保留原来的滤镜链
+    pub fn set_audio_filter(&mut self, spec: &str) -> Result<(), anyhow::Error> {
+        if !spec.trim().is_empty() {
+            AudioFilter::check(spec)
+                .map_err(|e| anyhow::anyhow!("无效的音频滤镜 {}: {}", spec, e))?;
+        }
+        info!("音频滤镜: {:?}", spec);
+        self.audio_settings.filter().set(spec);
+        Ok(())
+    }
+
+    /// 选择的音频输出设备名，None 表示系统默认设备
+    pub fn audio_device(&self) -> Option<String> {
+        self.audio_settings.device()
+    }
+
+    /// 切换音频输出设备，音频线程重建输出流和重采样器，播放位置不变；
+    /// 找不到指定设备时使用默认设备
+    pub fn set_audio_device(&mut self, device: Option<String>) {
+        info!("音频输出设备: {:?}", device);
+        self.audio_settings.set_device(device);
+    }
+
+    /// 音频输出的总延迟：环形缓冲区中尚未播出的时长加上设备延迟，输出流打开前为 None；
+    /// 其中的设备延迟已经由播放时钟扣除，画面按实际听到的声音呈现
+    pub fn output_latency(&self) -> Option<Duration> {
+        self.stats.output_latency()
+    }
+
+    /// cpal 回调因缓冲区没有数据而输出静音的次数
+    pub fn underruns(&self) -> u64 {
+        self.stats.underruns()
+    }
+
+    /// 当前音轨应用的 ReplayGain 增益（dB），没有标签或关闭 ReplayGain 时返回 None
+    pub fn replaygain(&self) -> Option<f32> {
+        self.audio_settings.replaygain()
+    }
+
+    /// 响度标准化的目标响度（LUFS），None 表示不标准化
+    pub fn normalize_target(&self) -> Option<f32> {
+        self.audio_settings.normalize_target()
+    }
+
+    /// 开启或关闭响度标准化，只在当前音轨没有 ReplayGain 标签时生效
+    pub fn set_normalize_target(&mut self, target: Option<f32>) {
+        let target = target.map(|target| target.clamp(MIN_NORMALIZE_TARGET, MAX_NORMALIZE_TARGET));
+        info!("响度标准化目标: {:?} LUFS", target);
+        self.audio_settings.set_normalize_target(target);
+    }
+
+    /// 从开始播放到现在测得的 EBU R128 整体响度（LUFS），还没有足够的有声数据时返回 None
+    pub fn integrated_loudness(&self) -> Option<f32> {
+        self.audio_settings.integrated_loudness()
+    }
+
+    /// 当前实际应用的 ReplayGain 或标准化增益（dB），不含音量
+    pub fn loudness_gain(&self) -> f32 {
+        self.audio_settings.loudness_gain()
+    }
+
+    pub fn deinterlace_mode(&self) -> DeinterlaceMode {
+        self.deinterlace.mode()
+    }
+
+    /// 切换去隔行模式，解码线程从下一帧开始生效
+    pub fn set_deinterlace_mode(&mut self, mode: DeinterlaceMode) {
+        info!("去隔行: {:?} ({:?})", mode, self.deinterlace.deinterlacer);
+        self.deinterlace.set_mode(mode);
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// 开始播放，用于启动以暂停状态预读的播放器
+    pub fn play(&mut self) {
+        if !self.playing {
+            self.toggle_pause_playing();
+        }
+    }
+
+    pub fn media_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 解码线程和输出线程共享的统计数据，供统计信息叠加层读取
+    pub fn stats(&self) -> Arc<PlaybackStats> {
+        self.stats.clone()
+    }
+
+    /// 容器记录的总时长，直播流等未知时长的输入为 None
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    /// 容器元数据中的标题，没有时使用文件名
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// 容器中所有音频、视频和字幕流
+    pub fn tracks(&self) -> &[TrackInfo] {
+        &self.tracks
+    }
+
+    /// 当前选中的流索引，视频轨固定为容器推荐的流
+    pub fn selected_track(&self, kind: TrackKind) -> Option<usize> {
+        match kind {
+            TrackKind::Audio => self.audio_track,
+            TrackKind::Subtitle => self.subtitle_track,
+            TrackKind::Video => None,
+        }
+    }
+
+    /// 在不重启播放的情况下切换音轨或字幕轨，index 为流在容器中的索引。
+    /// 暂停时原地跳转一次，让解封装线程立即重建解码线程并刷新画面和字幕
+    pub fn select_track(&mut self, kind: TrackKind, index: usize) -> Result<(), anyhow::Error> {
+        if kind == TrackKind::Video {
+            anyhow::bail!("不支持切换视频轨");
+        }
+        if !self.tracks.iter().any(|track| track.kind == kind && track.index == index) {
+            anyhow::bail!("没有索引为 {} 的{:?}轨", index, kind);
+        }
+
+        info!("选择 {:?} 轨道: {}", kind, index);
+        self.control_sender.send_blocking(ControlCommand::SelectTrack(kind, index))?;
+        match kind {
+            TrackKind::Audio => self.audio_track = Some(index),
+            TrackKind::Subtitle => self.subtitle_track = Some(index),
+            TrackKind::Video => {}
+        }
+        if !self.playing {
+            self.seek(self.clock.position())?;
+        }
+        Ok(())
+    }
+
+    /// 循环切换到同类型的下一条轨道，返回新选中的轨道
+    pub fn cycle_track(&mut self, kind: TrackKind) -> Result<Option<TrackInfo>, anyhow::Error> {
+        let candidates: Vec<&TrackInfo> =
+            self.tracks.iter().filter(|track| track.kind == kind).collect();
+        if candidates.len() < 2 {
+            return Ok(None);
+        }
+
+        let current = self.selected_track(kind);
+        let position = candidates.iter().position(|track| Some(track.index) == current);
+        let next_position = position.map_or(0, |position| (position + 1) % candidates.len());
+        let next = candidates[next_position].clone();
+        self.select_track(kind, next.index)?;
+        Ok(Some(next))
+    }
+
+    /// 以源分辨率保存当前显示的解码帧，返回截图文件路径
+    pub fn screenshot(
+        &self,
+        frame: &ffmpeg::util::frame::Video,
+        pts: Duration,
+        dir: &Path,
+    ) -> Result<PathBuf, anyhow::Error> {
+        let path = screenshot::screenshot_path(dir, &self.path, pts, "");
+        screenshot::save_video_frame(frame, &path)?;
+        Ok(path)
+    }
+
+    /// 有会动的视频画面，只有音频或封面图时为 false
+    pub fn has_video(&self) -> bool {
+        self.has_video
+    }
+
+    pub fn has_cover_art(&self) -> bool {
+        self.has_cover_art
+    }
+
+    /// 有可以播放的音轨，只有画面或封面图时为 false
+    pub fn has_audio(&self) -> bool {
+        self.audio_track.is_some()
+    }
+
+    /// 截取正在播出的 len 个音频采样，用于音频可视化
+    pub fn audio_window(&self, len: usize) -> Option<AudioWindow> {
+        // 播放时钟按音频延迟挪动过画面，这里挪回正在播出的声音的位置
+        let position = self.clock.position();
+        let position = match self.audio_delay() {
+            AudioDelay::Late(delay) => position.saturating_sub(delay),
+            AudioDelay::Early(delay) => position + delay,
+        };
+        self.audio_settings.tap().window(position, len)
+    }
+
+    /// 播放时钟，渲染循环用它来决定帧的呈现时机
+    pub fn clock(&self) -> PlaybackClock {
+        self.clock.clone()
     }
 
     pub fn toggle_pause_playing(&mut self) {
         if self.playing {
             info!("切换到暂停状态");
             self.playing = false;
+            self.clock.pause();
             self.control_sender.send_blocking(ControlCommand::Pause).unwrap();
         } else {
             info!("切换到播放状态");
             self.playing = true;
+            self.started = true;
+            let position = self.clock.position();
+            self.clock.resume();
             self.control_sender.send_blocking(ControlCommand::Play).unwrap();
+            if std::mem::take(&mut self.paused_seek) {
+                if let Err(e) = self.seek(position) {
+                    error!("跳转失败: {}", e);
+                }
+            }
         }
         (self.playing_changed_callback)(self.playing);
     }
@@ -139,10 +1078,119 @@ impl Player {
 impl Drop for Player {
     fn drop(&mut self) {
         info!("Player dropped");
+        if self.resume && self.started {
+            self.save_resume_position();
+        }
         self.control_sender.close();
         if let Some(decoder_thread) = self.demuxer_thread.take() {
             info!("等待解码线程结束");
             decoder_thread.join().unwrap();
         }
     }
-}
\ No newline at end of file
+}
+
+/// position 以 AV_TIME_BASE（微秒）为单位传给 FFmpeg，跳到目标之前最近的关键帧
+pub(crate) fn seek_input(
+    input_context: &mut ffmpeg::format::context::Input,
+    position: Duration,
+) -> Result<(), ffmpeg::Error> {
+    let timestamp = position.as_micros() as i64;
+    input_context.seek(timestamp, ..timestamp)
+}
+
+/// 读取容器和指定音频流元数据中的 ReplayGain 标签，音频流的标签优先
+fn replaygain_db(
+    input_context: &ffmpeg::format::context::Input,
+    index: usize,
+    mode: ReplayGainMode,
+    preamp: f32,
+) -> Option<f32> {
+    let mut replaygain = ReplayGain::default();
+    replaygain.read(input_context.metadata().iter());
+    if let Some(stream) = input_context.stream(index) {
+        replaygain.read(stream.metadata().iter());
+    }
+    let gain = replaygain.gain_db(mode, preamp);
+    info!("音轨 {} 的 ReplayGain: {:?}，应用增益: {:?} dB", index, replaygain, gain);
+    gain
+}
+
+pub(crate) fn is_attached_picture(stream: &ffmpeg::format::stream::Stream) -> bool {
+    stream
+        .disposition()
+        .contains(ffmpeg::format::stream::Disposition::ATTACHED_PIC)
+}
+
+/// 数据包属于 stream 且 PTS 已经到达 end
+fn packet_reaches(
+    input_context: &ffmpeg::format::context::Input,
+    packet: &ffmpeg::codec::packet::packet::Packet,
+    stream: usize,
+    end: Duration,
+) -> bool {
+    let Some(time_base) = input_context.stream(stream).map(|stream| stream.time_base()) else {
+        return false;
+    };
+    packet.stream() == stream
+        && packet
+            .pts()
+            .is_some_and(|pts| pts as f64 * f64::from(time_base) >= end.as_secs_f64())
+}
+
+fn collect_tracks(input_context: &ffmpeg::format::context::Input) -> Vec<TrackInfo> {
+    input_context
+        .streams()
+        .filter_map(|stream| {
+            let parameters = stream.parameters();
+            let kind = match parameters.medium() {
+                ffmpeg::media::Type::Audio => TrackKind::Audio,
+                ffmpeg::media::Type::Video => TrackKind::Video,
+                ffmpeg::media::Type::Subtitle => TrackKind::Subtitle,
+                _ => return None,
+            };
+            let metadata = stream.metadata();
+            Some(TrackInfo {
+                kind,
+                index: stream.index(),
+                codec: parameters.id().name().to_string(),
+                language: metadata.get("language").map(str::to_string),
+                title: metadata.get("title").map(str::to_string),
+                default: stream
+                    .disposition()
+                    .contains(ffmpeg::format::stream::Disposition::DEFAULT),
+            })
+        })
+        .collect()
+}
+
+/// 按语言偏好顺序查找第一条匹配的轨道
+fn preferred_track(tracks: &[TrackInfo], kind: TrackKind, languages: &[String]) -> Option<usize> {
+    languages.iter().find_map(|language| {
+        tracks
+            .iter()
+            .find(|track| {
+                track.kind == kind
+                    && track
+                        .language
+                        .as_deref()
+                        .is_some_and(|track_language| track_language.eq_ignore_ascii_case(language))
+            })
+            .map(|track| track.index)
+    })
+}
+
+fn best_stream(
+    input_context: &ffmpeg::format::context::Input,
+    medium: ffmpeg::media::Type,
+) -> Option<usize> {
+    input_context.streams().best(medium).map(|stream| stream.index())
+}
+
+fn start_subtitle_thread(
+    stream: &ffmpeg::format::stream::Stream,
+    subtitle_callback: Arc<dyn Fn(subtitle::SubtitleCue) + Send + Sync>,
+) -> Option<subtitle::SubtitlePlaybackThread> {
+    subtitle::SubtitlePlaybackThread::start(stream, Box::new(move |cue| subtitle_callback(cue)))
+        .map_err(|e| error!("字幕解码器初始化失败: {}", e))
+        .ok()
+}
@@ -0,0 +1,360 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+
+/// 展开目录时视为媒体文件的扩展名
+const MEDIA_EXTENSIONS: &[&str] = &[
+    "mp4", "m4v", "mkv", "webm", "mov", "avi", "flv", "wmv", "ts", "m2ts", "mts", "mpg", "mpeg",
+    "ogv", "3gp", "mp3", "m4a", "aac", "flac", "wav", "ogg", "opus", "wma",
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RepeatMode {
+    /// 播放到列表末尾后停止
+    #[default]
+    Off,
+    /// 重复播放当前项
+    One,
+    /// 播放到末尾后从头开始
+    All,
+}
+
+impl RepeatMode {
+    pub fn next(self) -> Self {
+        match self {
+            RepeatMode::Off => RepeatMode::All,
+            RepeatMode::All => RepeatMode::One,
+            RepeatMode::One => RepeatMode::Off,
+        }
+    }
+}
+
+impl FromStr for RepeatMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "off" | "no" => Ok(RepeatMode::Off),
+            "one" | "file" => Ok(RepeatMode::One),
+            "all" | "inf" => Ok(RepeatMode::All),
+            _ => Err(format!("无效的循环模式: {}，可选 off、one、all", s)),
+        }
+    }
+}
+
+/// 播放列表，支持上一项/下一项、随机播放和循环模式
+pub struct Playlist {
+    items: Vec<PathBuf>,
+    /// 播放顺序，元素为 items 的下标，关闭随机播放时按原顺序排列
+    order: Vec<usize>,
+    /// 当前项在 order 中的位置
+    position: usize,
+    shuffle: bool,
+    repeat: RepeatMode,
+    rng_state: u64,
+}
+
+impl Playlist {
+    pub fn new(items: Vec<PathBuf>) -> Self {
+        let order = (0..items.len()).collect();
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+        Self {
+            items,
+            order,
+            position: 0,
+            shuffle: false,
+            repeat: RepeatMode::Off,
+            rng_state: seed | 1,
+        }
+    }
+
+    /// 展开输入中的媒体文件、目录、通配符以及 M3U/M3U8/PLS 播放列表文件
+    pub fn load(inputs: &[PathBuf]) -> Result<Self, anyhow::Error> {
+        let mut items = Vec::new();
+        for input in inputs {
+            expand_input(input, &mut items)?;
+        }
+        if items.is_empty() {
+            anyhow::bail!("播放列表为空: {:?}", inputs);
+        }
+        tracing::info!("播放列表共 {} 项", items.len());
+        Ok(Self::new(items))
+    }
+
+    /// 追加到列表末尾，随机播放时插入到尚未播放的部分
+    pub fn push(&mut self, item: PathBuf) {
+        tracing::info!("添加到播放列表: {:?}", item);
+        self.items.push(item);
+        let index = self.items.len() - 1;
+        if self.shuffle && self.position + 1 < self.order.len() {
+            let remaining = (self.order.len() - self.position) as u64;
+            let offset = 1 + (self.next_random() % remaining) as usize;
+            self.order.insert(self.position + offset, index);
+        } else {
+            self.order.push(index);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn items(&self) -> &[PathBuf] {
+        &self.items
+    }
+
+    /// 当前项在 items 中的下标
+    pub fn current_index(&self) -> Option<usize> {
+        self.order.get(self.position).copied()
+    }
+
+    pub fn current(&self) -> Option<&Path> {
+        self.current_index().map(|index| self.items[index].as_path())
+    }
+
+    pub fn repeat(&self) -> RepeatMode {
+        self.repeat
+    }
+
+    pub fn set_repeat(&mut self, repeat: RepeatMode) {
+        tracing::info!("播放列表循环模式: {:?}", repeat);
+        self.repeat = repeat;
+    }
+
+    pub fn is_shuffled(&self) -> bool {
+        self.shuffle
+    }
+
+    /// 开启随机播放时打乱顺序并把当前项放在最前面，关闭时恢复原顺序
+    pub fn set_shuffle(&mut self, shuffle: bool) {
+        tracing::info!("随机播放: {}", shuffle);
+        self.shuffle = shuffle;
+        let current = self.current_index();
+
+        self.order = (0..self.items.len()).collect();
+        if shuffle {
+            for i in (1..self.order.len()).rev() {
+                let j = (self.next_random() % (i as u64 + 1)) as usize;
+                self.order.swap(i, j);
+            }
+            if let Some(current) = current {
+                let position = self.order.iter().position(|&index| index == current).unwrap();
+                self.order.swap(0, position);
+            }
+        }
+        self.position = current
+            .and_then(|current| self.order.iter().position(|&index| index == current))
+            .unwrap_or(0);
+    }
+
+    /// 当前项自然播放结束后要播放的项，不移动当前位置，用于提前打开下一项
+    pub fn peek_next(&self) -> Option<&Path> {
+        self.following_position(true)
+            .map(|position| self.items[self.order[position]].as_path())
+    }
+
+    /// 当前项自然播放结束后前进，单曲循环时停留在当前项
+    pub fn advance(&mut self) -> Option<&Path> {
+        self.position = self.following_position(true)?;
+        self.current()
+    }
+
+    /// 手动切换到下一项，单曲循环时也会前进
+    pub fn skip_next(&mut self) -> Option<&Path> {
+        self.position = self.following_position(false)?;
+        self.current()
+    }
+
+    /// 手动切换到上一项，列表循环时从第一项回到最后一项
+    pub fn skip_previous(&mut self) -> Option<&Path> {
+        if self.order.is_empty() {
+            return None;
+        }
+        self.position = match self.position.checked_sub(1) {
+            Some(position) => position,
+            None if self.repeat == RepeatMode::All => self.order.len() - 1,
+            None => return None,
+        };
+        self.current()
+    }
+
+    fn following_position(&self, natural_end: bool) -> Option<usize> {
+        if self.order.is_empty() {
+            return None;
+        }
+        if natural_end && self.repeat == RepeatMode::One {
+            return Some(self.position);
+        }
+        let next = self.position + 1;
+        if next < self.order.len() {
+            Some(next)
+        } else if self.repeat != RepeatMode::Off {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    /// xorshift64，随机播放只需要不可预测的顺序
+    fn next_random(&mut self) -> u64 {
+        let mut x = self.rng_state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.rng_state = x;
+        x
+    }
+}
+
+pub fn is_media_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            MEDIA_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+        })
+}
+
+fn expand_input(input: &Path, items: &mut Vec<PathBuf>) -> Result<(), anyhow::Error> {
+    let text = input.to_string_lossy();
+
+    if is_url(&text) {
+        items.push(input.to_path_buf());
+    } else if input.is_dir() {
+        let mut entries: Vec<PathBuf> = std::fs::read_dir(input)
+            .with_context(|| format!("无法读取目录 {:?}", input))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file() && is_media_file(path))
+            .collect();
+        entries.sort();
+        tracing::info!("目录 {:?} 中找到 {} 个媒体文件", input, entries.len());
+        items.extend(entries);
+    } else if text.contains(['*', '?', '[']) && !input.exists() {
+        let mut matched = 0;
+        for entry in glob::glob(&text).with_context(|| format!("无效的通配符 {}", text))? {
+            match entry {
+                Ok(path) if path.is_file() => {
+                    items.push(path);
+                    matched += 1;
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("通配符匹配失败: {}", e),
+            }
+        }
+        tracing::info!("通配符 {} 匹配到 {} 个文件", text, matched);
+    } else {
+        let extension = input
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("m3u") | Some("m3u8") => items.extend(parse_m3u(&read_playlist(input)?, input)),
+            Some("pls") => items.extend(parse_pls(&read_playlist(input)?, input)),
+            _ => items.push(input.to_path_buf()),
+        }
+    }
+    Ok(())
+}
+
+fn read_playlist(path: &Path) -> Result<String, anyhow::Error> {
+    let data = std::fs::read(path).with_context(|| format!("无法读取播放列表 {:?}", path))?;
+    let text = String::from_utf8_lossy(&data);
+    Ok(text.trim_start_matches('\u{feff}').to_string())
+}
+
+/// 解析 M3U/M3U8，忽略注释和扩展标签，相对路径相对于播放列表所在目录
+pub fn parse_m3u(text: &str, playlist_path: &Path) -> Vec<PathBuf> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| resolve_entry(line, playlist_path))
+        .collect()
+}
+
+/// 解析 PLS，按 FileN 的序号排序
+pub fn parse_pls(text: &str, playlist_path: &Path) -> Vec<PathBuf> {
+    let mut entries: Vec<(u32, PathBuf)> = text
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.trim().split_once('=')?;
+            let key = key.trim();
+            // 按字节切片会在非 ASCII 的键（例如“标题1”）中间切开
+            if !key.get(..4).is_some_and(|prefix| prefix.eq_ignore_ascii_case("file")) {
+                return None;
+            }
+            let number = key[4..].parse().ok()?;
+            Some((number, resolve_entry(value.trim(), playlist_path)))
+        })
+        .collect();
+    entries.sort_by_key(|(number, _)| *number);
+    entries.into_iter().map(|(_, path)| path).collect()
+}
+
+fn resolve_entry(entry: &str, playlist_path: &Path) -> PathBuf {
+    let entry = entry.strip_prefix("file://").unwrap_or(entry);
+    let path = PathBuf::from(entry);
+    if is_url(entry) || path.is_absolute() {
+        return path;
+    }
+    playlist_path
+        .parent()
+        .map_or(path.clone(), |dir| dir.join(&path))
+}
+
+fn is_url(text: &str) -> bool {
+    text.split_once("://")
+        .is_some_and(|(scheme, _)| {
+            !scheme.is_empty() && scheme.chars().all(|c| c.is_ascii_alphanumeric() || c == '+')
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn m3u_skips_comments_and_resolves_relative_paths() {
+        let text = "#EXTM3U\n#EXTINF:10,Intro\nintro.mp4\n\n  /abs/clip.mkv  \n\
+                    http://example.com/live.m3u8\nfile:///srv/video.webm\n";
+        let entries = parse_m3u(text, Path::new("/playlists/list.m3u"));
+        assert_eq!(
+            entries,
+            [
+                PathBuf::from("/playlists/intro.mp4"),
+                PathBuf::from("/abs/clip.mkv"),
+                PathBuf::from("http://example.com/live.m3u8"),
+                PathBuf::from("/srv/video.webm"),
+            ]
+        );
+    }
+
+    #[test]
+    fn pls_orders_entries_by_number() {
+        let text = "[playlist]\nFile2=b.mp4\nTitle1=A\nfile1 = a.mp4\nFile10=c.mp4\n\
+                    NumberOfEntries=3\nVersion=2\n";
+        let entries = parse_pls(text, Path::new("/playlists/list.pls"));
+        assert_eq!(
+            entries,
+            [
+                PathBuf::from("/playlists/a.mp4"),
+                PathBuf::from("/playlists/b.mp4"),
+                PathBuf::from("/playlists/c.mp4"),
+            ]
+        );
+    }
+
+    #[test]
+    fn pls_ignores_non_ascii_and_malformed_keys() {
+        let text = "[playlist]\n标题1=片头\nFilé=x.mp4\nFile=y.mp4\nFileX=z.mp4\n\
+                    文件1=w.mp4\nFile1=a.mp4\n";
+        let entries = parse_pls(text, Path::new("/playlists/list.pls"));
+        assert_eq!(entries, [PathBuf::from("/playlists/a.mp4")]);
+    }
+}
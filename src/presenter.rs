@@ -0,0 +1,163 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use ffmpeg_next::util::frame::Video as VideoFrame;
+
+use crate::clock::PlaybackClock;
+use crate::deinterlace::Field;
+
+/// 渲染端帧队列最多缓存的帧数，超出时丢弃最旧的帧
+const MAX_QUEUED_FRAMES: usize = 16;
+
+struct TimedFrame {
+    frame: VideoFrame,
+    pts: Duration,
+    /// GPU 去隔行时显示的场
+    field: Option<Field>,
+}
+
+/// 解码线程与渲染循环之间的帧队列，按 PTS 升序排列
+#[derive(Clone)]
+pub struct FrameQueue {
+    frames: Arc<Mutex<VecDeque<TimedFrame>>>,
+}
+
+impl FrameQueue {
+    pub fn new() -> Self {
+        Self {
+            frames: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+
+    pub fn push(&self, frame: &VideoFrame, pts: Duration, field: Option<Field>) {
+        let mut frames = self.frames.lock().unwrap();
+        // PTS 回退说明发生了跳转（例如循环播放），队列中的旧帧不会再到期
+        if frames.back().is_some_and(|last| pts < last.pts) {
+            tracing::debug!("PTS 回退，清空帧队列");
+            frames.clear();
+        }
+        if frames.len() >= MAX_QUEUED_FRAMES {
+            tracing::debug!("帧队列已满，丢弃最旧的帧");
+            frames.pop_front();
+        }
+        frames.push_back(TimedFrame {
+            frame: frame.clone(),
+            pts,
+            field,
+        });
+    }
+
+    /// 队首帧的尺寸，用于在创建渲染器前获知视频大小
+    pub fn front_size(&self) -> Option<(u32, u32)> {
+        self.frames
+            .lock()
+            .unwrap()
+            .front()
+            .map(|timed| (timed.frame.width(), timed.frame.height()))
+    }
+
+    /// 取出所有已到期的帧，返回最新的一帧以及被跳过的帧数
+    fn take_due(&self, position: Duration) -> Option<(TimedFrame, usize)> {
+        let mut frames = self.frames.lock().unwrap();
+        let mut latest = None;
+        let mut skipped = 0;
+        while frames.front().is_some_and(|timed| timed.pts <= position) {
+            if latest.is_some() {
+                skipped += 1;
+            }
+            latest = frames.pop_front();
+        }
+        latest.map(|timed| (timed, skipped))
+    }
+}
+
+impl Default for FrameQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PresentStats {
+    /// 实际呈现的新帧数
+    pub presented: u64,
+    /// 到期但被更新的帧覆盖、从未显示的帧数
+    pub dropped: u64,
+    /// 没有新帧到期时重复呈现上一帧的次数
+    pub repeated: u64,
+}
+
+pub enum Presentation {
+    /// 有新帧到期，需要上传并呈现
+    NewFrame(VideoFrame, Duration, Option<Field>),
+    /// 没有新帧，重复呈现当前画面
+    Repeat,
+    /// 还没到下一个刷新周期或者处于暂停状态
+    Idle,
+}
+
+/// 按显示器刷新率驱动画面呈现：每个刷新周期根据播放时钟挑选应显示的帧
+pub struct Presenter {
+    queue: FrameQueue,
+    clock: PlaybackClock,
+    refresh_interval: Duration,
+    next_refresh: Instant,
+    has_frame: bool,
+    stats: PresentStats,
+}
+
+impl Presenter {
+    pub fn new(queue: FrameQueue, clock: PlaybackClock, refresh_interval: Duration) -> Self {
+        tracing::info!("呈现器刷新间隔: {:?}", refresh_interval);
+        Self {
+            queue,
+            clock,
+            refresh_interval,
+            next_refresh: Instant::now(),
+            has_frame: false,
+            stats: PresentStats::default(),
+        }
+    }
+
+    /// 下一次需要唤醒事件循环的时刻
+    pub fn next_refresh(&self) -> Instant {
+        self.next_refresh
+    }
+
+    /// 切换到新的帧来源（播放列表切换到下一项），当前画面保持到新帧到期
+    pub fn set_source(&mut self, queue: FrameQueue, clock: PlaybackClock) {
+        self.queue = queue;
+        self.clock = clock;
+    }
+
+    pub fn stats(&self) -> PresentStats {
+        self.stats
+    }
+
+    pub fn poll(&mut self, now: Instant) -> Presentation {
+        if now < self.next_refresh {
+            return Presentation::Idle;
+        }
+
+        // 落后超过一个周期时直接对齐到当前时刻，避免连续补帧
+        self.next_refresh += self.refresh_interval;
+        if self.next_refresh < now {
+            self.next_refresh = now + self.refresh_interval;
+        }
+
+        match self.queue.take_due(self.clock.position()) {
+            Some((timed, skipped)) => {
+                self.has_frame = true;
+                self.stats.presented += 1;
+                self.stats.dropped += skipped as u64;
+                Presentation::NewFrame(timed.frame, timed.pts, timed.field)
+            }
+            None if self.has_frame && !self.clock.is_paused() => {
+                self.stats.repeated += 1;
+                Presentation::Repeat
+            }
+            None => Presentation::Idle,
+        }
+    }
+}
@@ -0,0 +1,364 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use ffmpeg::codec::{threading, Profile};
+use ffmpeg::format::stream::{Disposition, Stream};
+use ffmpeg::media::Type;
+use ffmpeg::util::dictionary::Ref as DictionaryRef;
+use ffmpeg::Rational;
+use serde_json::{json, Value};
+
+/// 不播放、不启动任何线程或音频设备，只读取容器和流的信息
+#[derive(Clone, Debug)]
+pub struct MediaInfo {
+    pub path: PathBuf,
+    /// 容器格式的短名称，例如 mov,mp4,m4a,3gp,3g2,mj2
+    pub format: String,
+    pub format_description: String,
+    pub duration: Option<Duration>,
+    /// 整体码率（比特每秒）
+    pub bit_rate: Option<u64>,
+    pub size: Option<u64>,
+    /// 容器元数据标签，保持文件中的顺序
+    pub tags: Vec<(String, String)>,
+    pub chapters: Vec<ChapterInfo>,
+    pub streams: Vec<StreamInfo>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ChapterInfo {
+    pub id: i64,
+    pub start: Duration,
+    pub end: Duration,
+    pub title: Option<String>,
+    pub tags: Vec<(String, String)>,
+}
+
+#[derive(Clone, Debug)]
+pub struct StreamInfo {
+    /// 流在容器中的索引
+    pub index: usize,
+    pub kind: StreamKind,
+    /// 编解码器短名称，例如 h264、aac
+    pub codec: String,
+    /// 编解码器的完整名称，本机 FFmpeg 没有对应解码器时为 None
+    pub codec_description: Option<String>,
+    pub profile: Option<String>,
+    pub bit_rate: Option<u64>,
+    pub duration: Option<Duration>,
+    /// 容器记录的帧数，未知时为 None
+    pub frames: Option<u64>,
+    pub time_base: Rational,
+    pub language: Option<String>,
+    pub title: Option<String>,
+    /// 容器是否把该流标记为默认流
+    pub default: bool,
+    /// 是否是作为封面的附加图片
+    pub attached_picture: bool,
+    pub tags: Vec<(String, String)>,
+    pub video: Option<VideoInfo>,
+    pub audio: Option<AudioInfo>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StreamKind {
+    Video,
+    Audio,
+    Subtitle,
+    Data,
+    Attachment,
+    Unknown,
+}
+
+impl StreamKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            StreamKind::Video => "video",
+            StreamKind::Audio => "audio",
+            StreamKind::Subtitle => "subtitle",
+            StreamKind::Data => "data",
+            StreamKind::Attachment => "attachment",
+            StreamKind::Unknown => "unknown",
+        }
+    }
+}
+
+impl From<Type> for StreamKind {
+    fn from(medium: Type) -> Self {
+        match medium {
+            Type::Video => StreamKind::Video,
+            Type::Audio => StreamKind::Audio,
+            Type::Subtitle => StreamKind::Subtitle,
+            Type::Data => StreamKind::Data,
+            Type::Attachment => StreamKind::Attachment,
+            Type::Unknown => StreamKind::Unknown,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct VideoInfo {
+    pub width: u32,
+    pub height: u32,
+    pub pixel_format: Option<String>,
+    /// 像素宽高比，未知时为 None
+    pub sample_aspect_ratio: Option<Rational>,
+    /// 平均帧率
+    pub frame_rate: Option<Rational>,
+    pub color_space: Option<String>,
+    pub color_range: Option<String>,
+    pub color_primaries: Option<String>,
+    pub color_transfer: Option<String>,
+    /// 显示时需要逆时针旋转的角度，与 ffprobe 的 rotation 一致，没有旋转信息时为 None
+    pub rotation: Option<f64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct AudioInfo {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub sample_format: Option<String>,
+}
+
+/// 打开 path 读取容器格式、时长、码率、章节、元数据和各个流的编解码参数
+pub fn probe(path: &Path) -> Result<MediaInfo, anyhow::Error> {
+    let input_context = ffmpeg::format::input(&path)?;
+    let format = input_context.format();
+
+    // 容器时长以 AV_TIME_BASE（微秒）为单位，未知时为负数
+    let duration = u64::try_from(input_context.duration())
+        .ok()
+        .filter(|&duration| duration > 0)
+        .map(Duration::from_micros);
+
+    let chapters = input_context
+        .chapters()
+        .map(|chapter| {
+            let time_base = chapter.time_base();
+            ChapterInfo {
+                id: chapter.id(),
+                start: timestamp(chapter.start(), time_base).unwrap_or_default(),
+                end: timestamp(chapter.end(), time_base).unwrap_or_default(),
+                title: chapter.metadata().get("title").map(str::to_string),
+                tags: tags(&chapter.metadata()),
+            }
+        })
+        .collect();
+
+    Ok(MediaInfo {
+        path: path.to_path_buf(),
+        format: format.name().to_string(),
+        format_description: format.description().to_string(),
+        duration,
+        bit_rate: u64::try_from(input_context.bit_rate()).ok().filter(|&rate| rate > 0),
+        size: std::fs::metadata(path).ok().map(|metadata| metadata.len()),
+        tags: tags(&input_context.metadata()),
+        chapters,
+        streams: input_context.streams().map(|stream| stream_info(&stream)).collect(),
+    })
+}
+
+fn stream_info(stream: &Stream) -> StreamInfo {
+    let parameters = stream.parameters();
+    let kind = StreamKind::from(parameters.medium());
+    let metadata = stream.metadata();
+    let time_base = stream.time_base();
+    let codec = ffmpeg::codec::decoder::find(parameters.id());
+
+    // 编解码参数只有打开解码器后才能通过 ffmpeg-next 读到。解码器只打开不送数据，
+    // 线程数设为 1，不会创建解码线程
+    let decoder = ffmpeg::codec::Context::from_parameters(parameters.clone())
+        .ok()
+        .filter(|_| codec.is_some() && matches!(kind, StreamKind::Video | StreamKind::Audio))
+        .map(|mut context| {
+            context.set_threading(threading::Config::count(1));
+            context.decoder()
+        });
+    let (video, audio, profile, bit_rate) = match kind {
+        StreamKind::Video => match decoder.and_then(|decoder| decoder.video().ok()) {
+            Some(decoder) => {
+                let info = VideoInfo {
+                    width: decoder.width(),
+                    height: decoder.height(),
+                    pixel_format: decoder.format().descriptor().map(|d| d.name().to_string()),
+                    sample_aspect_ratio: Some(decoder.aspect_ratio())
+                        .filter(|ratio| ratio.numerator() > 0 && ratio.denominator() > 0),
+                    frame_rate: Some(stream.avg_frame_rate())
+                        .filter(|rate| rate.numerator() > 0 && rate.denominator() > 0),
+                    color_space: decoder.color_space().name().map(str::to_string),
+                    color_range: decoder.color_range().name().map(str::to_string),
+                    color_primaries: decoder.color_primaries().name().map(str::to_string),
+                    color_transfer: decoder
+                        .color_transfer_characteristic()
+                        .name()
+                        .map(str::to_string),
+                    rotation: rotation(stream),
+                };
+                (Some(info), None, profile_name(decoder.profile()), decoder.bit_rate())
+            }
+            None => (None, None, None, 0),
+        },
+        StreamKind::Audio => match decoder.and_then(|decoder| decoder.audio().ok()) {
+            Some(decoder) => {
+                let sample_format = decoder.format();
+                let info = AudioInfo {
+                    sample_rate: decoder.rate(),
+                    channels: decoder.channels(),
+                    sample_format: (sample_format != ffmpeg::format::Sample::None)
+                        .then(|| sample_format.name().to_string()),
+                };
+                (None, Some(info), profile_name(decoder.profile()), decoder.bit_rate())
+            }
+            None => (None, None, None, 0),
+        },
+        _ => (None, None, None, 0),
+    };
+
+    StreamInfo {
+        index: stream.index(),
+        kind,
+        codec: parameters.id().name().to_string(),
+        codec_description: codec.map(|codec| codec.description().to_string()),
+        profile,
+        bit_rate: Some(bit_rate as u64).filter(|&rate| rate > 0),
+        duration: timestamp(stream.duration(), time_base),
+        frames: u64::try_from(stream.frames()).ok().filter(|&frames| frames > 0),
+        time_base,
+        language: metadata.get("language").map(str::to_string),
+        title: metadata.get("title").map(str::to_string),
+        default: stream.disposition().contains(Disposition::DEFAULT),
+        attached_picture: stream.disposition().contains(Disposition::ATTACHED_PIC),
+        tags: tags(&metadata),
+        video,
+        audio,
+    }
+}
+
+/// 从显示矩阵读取旋转角度，没有显示矩阵时回退到旧式的 rotate 标签（顺时针）
+fn rotation(stream: &Stream) -> Option<f64> {
+    let matrix = stream
+        .side_data()
+        .find(|side_data| side_data.kind() == ffmpeg::codec::packet::side_data::Type::DisplayMatrix)
+        .filter(|side_data| side_data.data().len() >= 9 * std::mem::size_of::<i32>());
+    if let Some(matrix) = matrix {
+        // 显示矩阵是 9 个 16.16 定点数
+        let angle = unsafe { ffmpeg::ffi::av_display_rotation_get(matrix.data().as_ptr().cast()) };
+        // 不旋转时可能得到 -0.0
+        return angle.is_finite().then_some(if angle == 0.0 { 0.0 } else { angle });
+    }
+    stream
+        .metadata()
+        .get("rotate")
+        .and_then(|rotate| rotate.trim().parse::<f64>().ok())
+        .map(|rotate| -rotate)
+}
+
+/// 以 time_base 为单位的时间戳转换为时长，未知或为负时返回 None
+fn timestamp(value: i64, time_base: Rational) -> Option<Duration> {
+    // 未知的时间戳是 AV_NOPTS_VALUE，即 i64::MIN
+    if value < 0 || time_base.denominator() == 0 {
+        return None;
+    }
+    Duration::try_from_secs_f64(value as f64 * f64::from(time_base)).ok()
+}
+
+/// 编解码器的 profile，例如 H264(High)
+fn profile_name(profile: Profile) -> Option<String> {
+    match profile {
+        Profile::Unknown | Profile::Reserved => None,
+        profile => Some(format!("{:?}", profile)),
+    }
+}
+
+fn tags(metadata: &DictionaryRef) -> Vec<(String, String)> {
+    metadata
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+impl MediaInfo {
+    /// 供 --probe 输出和其他服务使用的 JSON，时间以秒为单位
+    pub fn to_json(&self) -> Value {
+        json!({
+            "path": self.path.to_string_lossy(),
+            "format": self.format,
+            "format_description": self.format_description,
+            "duration": self.duration.map(|duration| duration.as_secs_f64()),
+            "bit_rate": self.bit_rate,
+            "size": self.size,
+            "tags": tags_json(&self.tags),
+            "chapters": self.chapters.iter().map(ChapterInfo::to_json).collect::<Vec<_>>(),
+            "streams": self.streams.iter().map(StreamInfo::to_json).collect::<Vec<_>>(),
+        })
+    }
+}
+
+impl ChapterInfo {
+    fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "start": self.start.as_secs_f64(),
+            "end": self.end.as_secs_f64(),
+            "title": self.title,
+            "tags": tags_json(&self.tags),
+        })
+    }
+}
+
+impl StreamInfo {
+    fn to_json(&self) -> Value {
+        let mut value = json!({
+            "index": self.index,
+            "type": self.kind.label(),
+            "codec": self.codec,
+            "codec_description": self.codec_description,
+            "profile": self.profile,
+            "bit_rate": self.bit_rate,
+            "duration": self.duration.map(|duration| duration.as_secs_f64()),
+            "frames": self.frames,
+            "time_base": rational_json(self.time_base),
+            "language": self.language,
+            "title": self.title,
+            "default": self.default,
+            "attached_picture": self.attached_picture,
+            "tags": tags_json(&self.tags),
+        });
+        if let Some(video) = &self.video {
+            value["video"] = json!({
+                "width": video.width,
+                "height": video.height,
+                "pixel_format": video.pixel_format,
+                "sample_aspect_ratio": video.sample_aspect_ratio.map(rational_json),
+                "frame_rate": video.frame_rate.map(f64::from),
+                "color_space": video.color_space,
+                "color_range": video.color_range,
+                "color_primaries": video.color_primaries,
+                "color_transfer": video.color_transfer,
+                "rotation": video.rotation,
+            });
+        }
+        if let Some(audio) = &self.audio {
+            value["audio"] = json!({
+                "sample_rate": audio.sample_rate,
+                "channels": audio.channels,
+                "sample_format": audio.sample_format,
+            });
+        }
+        value
+    }
+}
+
+fn tags_json(tags: &[(String, String)]) -> Value {
+    Value::Object(
+        tags.iter()
+            .map(|(key, value)| (key.clone(), Value::from(value.as_str())))
+            .collect(),
+    )
+}
+
+fn rational_json(rational: Rational) -> String {
+    format!("{}/{}", rational.numerator(), rational.denominator())
+}
@@ -1,5 +1,10 @@
 use glium::{
-    glutin::{dpi::PhysicalSize, event_loop::EventLoop, window::WindowBuilder, ContextBuilder},
+    glutin::{
+        dpi::{PhysicalPosition, PhysicalSize},
+        event_loop::EventLoop,
+        window::{Fullscreen, WindowBuilder},
+        ContextBuilder,
+    },
     implement_vertex,
     index::PrimitiveType,
     texture::{ClientFormat, MipmapsOption, RawImage2d, UncompressedFloatFormat},
@@ -8,22 +13,196 @@ use glium::{
 use tracing::info;
 
 use crate::config::Config;
+use crate::deinterlace::{Deinterlacer, Field};
+use crate::geometry::WindowGeometry;
+use crate::osd::Osd;
+use crate::overlay::{self, OverlayImage, OverlayPass};
+use crate::screenshot;
+use crate::subtitle::{StyledText, SubtitleCue, SubtitleItem};
+use crate::text::{TextAlign, TextRasterizer, TextStyle};
+use crate::visualization::{AudioWindow, VisualizationMode};
+use crate::visualizer::Visualizer;
 use ffmpeg_next::util::frame::Video as VideoFrame;
 use rayon::prelude::*;
 use std::borrow::Cow;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+const DEFAULT_REFRESH_RATE_MILLIHERTZ: u32 = 60_000;
 
 #[derive(Copy, Clone, Debug)]
 pub struct Vertex {
-    position: [f32; 2],
-    tex_coords: [f32; 2],
+    pub position: [f32; 2],
+    pub tex_coords: [f32; 2],
 }
 
 implement_vertex!(Vertex, position, tex_coords);
 
 #[derive(Copy, Clone, Debug)]
 pub enum ScaleMode {
-    Fit,  // 保持原始比例,两侧或者上下留黑
-    Fill, // 完全按原比例显示，，进行裁剪，画面全屏显示
+    Fit,         // 保持原始比例,两侧或者上下留黑
+    Fill,        // 完全按原比例显示，，进行裁剪，画面全屏显示
+    Stretch,     // 忽略比例，拉伸铺满窗口
+    Original,    // 按视频像素 1:1 显示
+    Aspect(f32), // 强制按指定宽高比显示，留黑方式同 Fit
+}
+
+impl ScaleMode {
+    /// 按 M 键循环切换的顺序
+    fn next(self) -> Self {
+        match self {
+            ScaleMode::Fit => ScaleMode::Fill,
+            ScaleMode::Fill => ScaleMode::Stretch,
+            ScaleMode::Stretch => ScaleMode::Original,
+            ScaleMode::Original => ScaleMode::Aspect(4.0 / 3.0),
+            ScaleMode::Aspect(aspect) if aspect < 16.0 / 9.0 - 0.01 => {
+                ScaleMode::Aspect(16.0 / 9.0)
+            }
+            ScaleMode::Aspect(aspect) if aspect < 2.35 - 0.01 => ScaleMode::Aspect(2.35),
+            ScaleMode::Aspect(_) => ScaleMode::Fit,
+        }
+    }
+
+    /// OSD 中显示的名称
+    pub fn label(self) -> String {
+        match self {
+            ScaleMode::Fit => "适应窗口".to_string(),
+            ScaleMode::Fill => "填充裁剪".to_string(),
+            ScaleMode::Stretch => "拉伸".to_string(),
+            ScaleMode::Original => "原始尺寸".to_string(),
+            ScaleMode::Aspect(aspect) => format!("宽高比 {:.2}", aspect),
+        }
+    }
+}
+
+impl FromStr for ScaleMode {
+    type Err = String;
+
+    /// fit、fill、stretch、original，或者 4:3、2.35:1、1.85 这样的宽高比
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "fit" => return Ok(ScaleMode::Fit),
+            "fill" => return Ok(ScaleMode::Fill),
+            "stretch" => return Ok(ScaleMode::Stretch),
+            "original" => return Ok(ScaleMode::Original),
+            _ => {}
+        }
+        let aspect = match s.split_once(':') {
+            Some((width, height)) => width
+                .trim()
+                .parse::<f32>()
+                .ok()
+                .zip(height.trim().parse::<f32>().ok())
+                .map(|(width, height)| width / height),
+            None => s.trim().parse::<f32>().ok(),
+        };
+        match aspect {
+            Some(aspect) if aspect.is_finite() && aspect > 0.0 => Ok(ScaleMode::Aspect(aspect)),
+            _ => Err(format!(
+                "无效的缩放模式: {}，可选 fit、fill、stretch、original 或 16:9 这样的宽高比",
+                s
+            )),
+        }
+    }
+}
+
+/// 纯文本字幕的样式，ASS/SSA 字幕使用脚本里的样式
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SubtitleStyle {
+    /// 字号相对于窗口高度的比例
+    pub scale: f32,
+    /// RGBA
+    pub color: [u8; 4],
+    /// 描边宽度，单位为像素，0 表示不描边
+    pub outline: f32,
+    pub outline_color: [u8; 4],
+}
+
+impl Default for SubtitleStyle {
+    fn default() -> Self {
+        let text = TextStyle::default();
+        Self {
+            scale: SUBTITLE_FONT_SCALE,
+            color: text.color,
+            outline: text.outline,
+            outline_color: text.outline_color,
+        }
+    }
+}
+
+/// 视频像素坐标下的裁剪矩形，原点在左上角
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CropRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl FromStr for CropRect {
+    type Err = String;
+
+    /// WxH+X+Y，省略 +X+Y 时从左上角开始裁剪
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("无效的裁剪区域: {}，应写作 WxH+X+Y，例如 1920x800+0+140", s);
+        let (size, offset) = s.split_once('+').unwrap_or((s, "0+0"));
+        let (width, height) = size.split_once(['x', 'X']).ok_or_else(invalid)?;
+        let (x, y) = offset.split_once('+').ok_or_else(invalid)?;
+        let crop = Self {
+            x: x.trim().parse().map_err(|_| invalid())?,
+            y: y.trim().parse().map_err(|_| invalid())?,
+            width: width.trim().parse().map_err(|_| invalid())?,
+            height: height.trim().parse().map_err(|_| invalid())?,
+        };
+        if crop.width == 0 || crop.height == 0 {
+            return Err(invalid());
+        }
+        Ok(crop)
+    }
+}
+
+/// 字幕默认字号相对于窗口高度的比例
+const SUBTITLE_FONT_SCALE: f32 = 0.05;
+/// 文本字幕底边距相对于窗口高度的比例
+const SUBTITLE_MARGIN_SCALE: f32 = 0.05;
+
+const MIN_ZOOM: f32 = 0.1;
+const MAX_ZOOM: f32 = 16.0;
+/// 没有画面时音频可视化占据的区域（标准化设备坐标），四周留出边距
+const VISUALIZATION_RECT: [f32; 4] = [-0.9, -0.6, 0.9, 0.6];
+/// 叠加在封面或视频上时，音频可视化占据窗口底部的区域
+const VISUALIZATION_OVERLAY_RECT: [f32; 4] = [-1.0, -1.0, 1.0, -0.4];
+
+/// 交互式缩放、平移和裁剪状态
+#[derive(Copy, Clone, Debug)]
+struct ViewState {
+    zoom: f32,
+    /// 平移量，单位为标准化设备坐标
+    pan: [f32; 2],
+    crop: Option<CropRect>,
+}
+
+enum SubtitleImage {
+    /// 文本字幕，左上角位于窗口像素坐标 (x, y)
+    Text { image: OverlayImage, x: f32, y: f32 },
+    /// 位图字幕，按字幕画布坐标映射到视频画面上
+    Bitmap {
+        image: OverlayImage,
+        placement: BitmapPlacement,
+    },
+}
+
+/// 位图字幕在字幕画布上的位置
+#[derive(Copy, Clone, Debug)]
+struct BitmapPlacement {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    canvas_width: u32,
+    canvas_height: u32,
 }
 
 struct YuvBuffer {
@@ -131,10 +310,30 @@ pub struct Renderer {
     u_texture: Option<Texture2d>,
     v_texture: Option<Texture2d>,
     scale_mode: ScaleMode,
+    view: ViewState,
     frame_width: u32,
     frame_height: u32,
     front_buffer: YuvBuffer,
     back_buffer: YuvBuffer,
+    /// 最近一次 Resized 事件给出的绘制区域尺寸，全屏切换过程中 inner_size 可能尚未更新
+    surface_size: PhysicalSize<u32>,
+    /// 进入全屏前的窗口几何信息，用于退出全屏后恢复以及保存
+    windowed_geometry: Option<WindowGeometry>,
+    always_on_top: bool,
+    /// 当前视频画面四个顶点，用于把位图字幕映射到画面上
+    display_vertices: Vec<Vertex>,
+    overlay: OverlayPass,
+    text: Option<TextRasterizer>,
+    subtitles_visible: bool,
+    subtitle_cues: Vec<Arc<SubtitleCue>>,
+    subtitle_images: Vec<SubtitleImage>,
+    subtitle_style: SubtitleStyle,
+    osd: Osd,
+    /// GPU 去隔行方式，yadif/bwdif 在解码线程处理，这里不使用
+    deinterlacer: Deinterlacer,
+    /// 当前帧需要显示的场，None 表示按逐行画面显示
+    field: Option<Field>,
+    visualizer: Visualizer,
 }
 
 impl Renderer {
@@ -158,7 +357,8 @@ impl Renderer {
         let window_builder = WindowBuilder::new()
             .with_title(&config.window_title)
             .with_inner_size(PhysicalSize::new(physical_width, physical_height))
-            .with_resizable(true);
+            .with_resizable(true)
+            .with_always_on_top(config.always_on_top);
 
         let context_builder = ContextBuilder::new()
             .with_vsync(true)
@@ -210,6 +410,12 @@ impl Renderer {
         let front_buffer = YuvBuffer::new(frame_width, frame_height);
         let back_buffer = YuvBuffer::new(frame_width, frame_height);
 
+        let surface_size = display.gl_window().window().inner_size();
+        let overlay = OverlayPass::new(&display);
+        let text = TextRasterizer::load(config.subtitle_font.as_deref());
+        let osd = Osd::new(&display, config.subtitle_font.as_deref());
+        let visualizer = Visualizer::new(&display, VisualizationMode::Off);
+
         let mut renderer = Self {
             display,
             program,
@@ -219,10 +425,29 @@ impl Renderer {
             u_texture: None,
             v_texture: None,
             scale_mode: config.scale_mode,
+            view: ViewState {
+                zoom: 1.0,
+                pan: [0.0, 0.0],
+                crop: None,
+            },
             frame_width,
             frame_height,
             front_buffer,
             back_buffer,
+            surface_size,
+            windowed_geometry: None,
+            always_on_top: config.always_on_top,
+            display_vertices: Vec::new(),
+            overlay,
+            text,
+            subtitles_visible: true,
+            subtitle_cues: Vec::new(),
+            subtitle_images: Vec::new(),
+            subtitle_style: config.subtitle_style,
+            osd,
+            deinterlacer: config.deinterlacer,
+            field: None,
+            visualizer,
         };
 
         renderer.update_vertex_buffer();
@@ -231,14 +456,144 @@ impl Renderer {
     }
 
     pub fn toggle_scale_mode(&mut self) {
-        self.scale_mode = match self.scale_mode {
-            ScaleMode::Fit => ScaleMode::Fill,
-            ScaleMode::Fill => ScaleMode::Fit,
-        };
+        self.scale_mode = self.scale_mode.next();
         info!("切换到缩放模式: {:?}", self.scale_mode);
         self.update_vertex_buffer();
     }
 
+    pub fn scale_mode(&self) -> ScaleMode {
+        self.scale_mode
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.view.zoom
+    }
+
+    /// 以画面中心为基准缩放，平移量随之缩放以保持中心内容不变
+    pub fn zoom_by(&mut self, factor: f32) {
+        let zoom = (self.view.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        let applied = zoom / self.view.zoom;
+        self.view.zoom = zoom;
+        self.view.pan = [self.view.pan[0] * applied, self.view.pan[1] * applied];
+        info!("[Renderer] 缩放: {:.0}%", zoom * 100.0);
+        self.update_vertex_buffer();
+    }
+
+    /// 按窗口物理像素平移画面
+    pub fn pan_by(&mut self, dx: f64, dy: f64) {
+        if self.surface_size.width == 0 || self.surface_size.height == 0 {
+            return;
+        }
+        self.view.pan[0] += (2.0 * dx / self.surface_size.width as f64) as f32;
+        self.view.pan[1] -= (2.0 * dy / self.surface_size.height as f64) as f32;
+        self.update_vertex_buffer();
+    }
+
+    pub fn reset_view(&mut self) {
+        info!("[Renderer] 重置缩放和平移");
+        self.view.zoom = 1.0;
+        self.view.pan = [0.0, 0.0];
+        self.update_vertex_buffer();
+    }
+
+    pub fn set_crop(&mut self, crop: Option<CropRect>) {
+        info!("[Renderer] 设置裁剪区域: {:?}", crop);
+        self.view.crop = crop;
+        self.update_vertex_buffer();
+    }
+
+    pub fn is_fullscreen(&self) -> bool {
+        self.display.gl_window().window().fullscreen().is_some()
+    }
+
+    /// 在窗口当前所在的显示器上进入或退出无边框全屏
+    pub fn set_fullscreen(&mut self, fullscreen: bool) {
+        if fullscreen == self.is_fullscreen() {
+            return;
+        }
+
+        if fullscreen {
+            self.windowed_geometry = self.current_geometry();
+            let gl_window = self.display.gl_window();
+            let window = gl_window.window();
+            info!(
+                "[Renderer] 进入全屏: {:?}",
+                window.current_monitor().and_then(|m| m.name())
+            );
+            window.set_fullscreen(Some(Fullscreen::Borderless(window.current_monitor())));
+        } else {
+            info!("[Renderer] 退出全屏");
+            self.display.gl_window().window().set_fullscreen(None);
+            if let Some(geometry) = self.windowed_geometry.take() {
+                self.restore_geometry(&geometry);
+            }
+        }
+    }
+
+    pub fn toggle_fullscreen(&mut self) {
+        let fullscreen = !self.is_fullscreen();
+        self.set_fullscreen(fullscreen);
+    }
+
+    pub fn is_always_on_top(&self) -> bool {
+        self.always_on_top
+    }
+
+    pub fn toggle_always_on_top(&mut self) {
+        self.always_on_top = !self.always_on_top;
+        info!("[Renderer] 窗口置顶: {}", self.always_on_top);
+        self.display
+            .gl_window()
+            .window()
+            .set_always_on_top(self.always_on_top);
+    }
+
+    /// 把窗口调整为视频尺寸的指定倍数，全屏时先退出全屏
+    pub fn fit_to_video(&mut self, scale: f64) {
+        self.set_fullscreen(false);
+
+        let width = ((self.frame_width as f64 * scale).round() as u32).max(1);
+        let height = ((self.frame_height as f64 * scale).round() as u32).max(1);
+        info!(
+            "[Renderer] 窗口适配视频尺寸 {}x: {}x{}",
+            scale, width, height
+        );
+        self.display
+            .gl_window()
+            .window()
+            .set_inner_size(PhysicalSize::new(width, height));
+    }
+
+    /// 窗口化状态下的几何信息；全屏时返回进入全屏前的值
+    pub fn geometry(&self) -> Option<WindowGeometry> {
+        if self.is_fullscreen() {
+            self.windowed_geometry
+        } else {
+            self.current_geometry()
+        }
+    }
+
+    pub fn restore_geometry(&mut self, geometry: &WindowGeometry) {
+        info!("[Renderer] 恢复窗口几何信息: {:?}", geometry);
+        let gl_window = self.display.gl_window();
+        let window = gl_window.window();
+        window.set_outer_position(PhysicalPosition::new(geometry.x, geometry.y));
+        window.set_inner_size(PhysicalSize::new(geometry.width, geometry.height));
+    }
+
+    fn current_geometry(&self) -> Option<WindowGeometry> {
+        let gl_window = self.display.gl_window();
+        let window = gl_window.window();
+        let position = window.outer_position().ok()?;
+        let size = window.inner_size();
+        Some(WindowGeometry {
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+        })
+    }
+
     pub fn handle_resize(&mut self, new_size: PhysicalSize<u32>) {
         info!(
             "[Renderer] 处理窗口调整大小: {}x{}",
@@ -267,15 +622,15 @@ impl Renderer {
             return;
         }
 
+        self.surface_size = new_size;
         self.update_vertex_buffer();
+        // 字幕字号跟随窗口高度，需要重新光栅化
+        self.rebuild_subtitle_images();
     }
 
     pub fn update_vertex_buffer(&mut self) {
-        let (physical_size, scale_factor) = {
-            let gl_window = self.display.gl_window();
-            let window = gl_window.window();
-            (window.inner_size(), window.scale_factor())
-        };
+        let physical_size = self.surface_size;
+        let scale_factor = self.display.gl_window().window().scale_factor();
 
         let logical_size = physical_size.to_logical::<f64>(scale_factor);
 
@@ -296,13 +651,288 @@ impl Renderer {
             self.frame_width,
             self.frame_height,
             self.scale_mode,
+            &self.view,
         );
 
         self.vertex_buffer =
             VertexBuffer::new(&self.display, &vertices).expect("Failed to create vertex buffer");
+        self.display_vertices = vertices;
+    }
+
+    /// 更新当前应显示的字幕，返回画面是否需要重绘
+    pub fn set_subtitles(&mut self, cues: Vec<Arc<SubtitleCue>>) -> bool {
+        let unchanged = cues.len() == self.subtitle_cues.len()
+            && cues
+                .iter()
+                .zip(&self.subtitle_cues)
+                .all(|(new, old)| new.id == old.id);
+        if unchanged {
+            return false;
+        }
+
+        self.subtitle_cues = cues;
+        self.rebuild_subtitle_images();
+        self.subtitles_visible
+    }
+
+    pub fn toggle_subtitles(&mut self) {
+        self.subtitles_visible = !self.subtitles_visible;
+        info!("[Renderer] 字幕显示: {}", self.subtitles_visible);
+    }
+
+    pub fn subtitles_visible(&self) -> bool {
+        self.subtitles_visible
+    }
+
+    pub fn osd(&mut self) -> &mut Osd {
+        &mut self.osd
+    }
+
+    pub fn visualization(&self) -> VisualizationMode {
+        self.visualizer.mode()
+    }
+
+    pub fn set_visualization(&mut self, mode: VisualizationMode) {
+        info!("[Renderer] 音频可视化: {:?}", mode);
+        self.visualizer.set_mode(mode);
+    }
+
+    /// 更新音频可视化使用的采样，调用 redraw 后生效
+    pub fn set_audio_window(&mut self, window: Option<AudioWindow>) {
+        self.visualizer.update(window);
+    }
+
+    /// 丢弃已上传的画面，切换到没有视频也没有封面的媒体时使用
+    pub fn clear_video(&mut self) {
+        self.y_texture = None;
+        self.u_texture = None;
+        self.v_texture = None;
+    }
+
+    fn rebuild_subtitle_images(&mut self) {
+        self.subtitle_images.clear();
+
+        let mut lines = Vec::new();
+        let mut styled = Vec::new();
+        for cue in &self.subtitle_cues {
+            for item in &cue.items {
+                match item {
+                    SubtitleItem::Text(text) => lines.push(text.as_str()),
+                    SubtitleItem::Styled(text) => styled.push(text),
+                    SubtitleItem::Bitmap(bitmap) => {
+                        self.subtitle_images.push(SubtitleImage::Bitmap {
+                            image: OverlayImage::new(
+                                &self.display,
+                                bitmap.width,
+                                bitmap.height,
+                                bitmap.rgba.clone(),
+                            ),
+                            placement: BitmapPlacement {
+                                x: bitmap.x,
+                                y: bitmap.y,
+                                width: bitmap.width,
+                                height: bitmap.height,
+                                canvas_width: bitmap.canvas_width,
+                                canvas_height: bitmap.canvas_height,
+                            },
+                        });
+                    }
+                }
+            }
+        }
+
+        let Some(text) = &self.text else {
+            return;
+        };
+        let surface_width = self.surface_size.width as f32;
+        let surface_height = self.surface_size.height as f32;
+        let default_size = (surface_height * self.subtitle_style.scale).max(12.0);
+
+        if !lines.is_empty() {
+            let style = TextStyle {
+                size: default_size,
+                color: self.subtitle_style.color,
+                outline: self.subtitle_style.outline,
+                outline_color: self.subtitle_style.outline_color,
+                ..Default::default()
+            };
+            if let Some(image) = text.render(&lines.join("\n"), &style) {
+                let margin = surface_height * SUBTITLE_MARGIN_SCALE;
+                let x = (surface_width - image.width as f32) / 2.0;
+                let y = surface_height - margin - image.height as f32;
+                self.subtitle_images.push(SubtitleImage::Text {
+                    image: OverlayImage::new(&self.display, image.width, image.height, image.rgba),
+                    x,
+                    y,
+                });
+            }
+        }
+
+        for styled_text in styled {
+            if let Some(subtitle) = Self::layout_styled_text(
+                &self.display,
+                text,
+                styled_text,
+                self.surface_size,
+                default_size,
+            ) {
+                self.subtitle_images.push(subtitle);
+            }
+        }
+    }
+
+    /// 按 ASS 的对齐方式、边距和 `\pos` 把带样式的字幕放到窗口中，脚本坐标按窗口尺寸缩放
+    fn layout_styled_text(
+        display: &Display,
+        text: &TextRasterizer,
+        styled: &StyledText,
+        surface_size: PhysicalSize<u32>,
+        default_size: f32,
+    ) -> Option<SubtitleImage> {
+        let surface_width = surface_size.width as f32;
+        let surface_height = surface_size.height as f32;
+        let (play_res_x, play_res_y) = styled.play_res;
+        let scale_x = surface_width / play_res_x.max(1) as f32;
+        let scale_y = surface_height / play_res_y.max(1) as f32;
+
+        let column = (styled.alignment.clamp(1, 9) - 1) % 3;
+        let row = (styled.alignment.clamp(1, 9) - 1) / 3;
+        let align = match column {
+            0 => TextAlign::Left,
+            1 => TextAlign::Center,
+            _ => TextAlign::Right,
+        };
+
+        let style = TextStyle {
+            size: styled
+                .font_size
+                .map_or(default_size, |size| (size * scale_y).max(8.0)),
+            color: styled.color,
+            outline_color: styled.outline_color,
+            bold: styled.bold,
+            italic: styled.italic,
+            align,
+            ..Default::default()
+        };
+        let image = text.render(&styled.text, &style)?;
+        let width = image.width as f32;
+        let height = image.height as f32;
+
+        let (x, y) = match styled.position {
+            // \pos 指定的是按对齐方式确定的锚点
+            Some((pos_x, pos_y)) => {
+                let anchor_x = pos_x * scale_x;
+                let anchor_y = pos_y * scale_y;
+                let x = match column {
+                    0 => anchor_x,
+                    1 => anchor_x - width / 2.0,
+                    _ => anchor_x - width,
+                };
+                let y = match row {
+                    0 => anchor_y - height,
+                    1 => anchor_y - height / 2.0,
+                    _ => anchor_y,
+                };
+                (x, y)
+            }
+            None => {
+                let margin_left = styled.margin_left as f32 * scale_x;
+                let margin_right = styled.margin_right as f32 * scale_x;
+                let margin_vertical = if styled.margin_vertical > 0 {
+                    styled.margin_vertical as f32 * scale_y
+                } else {
+                    surface_height * SUBTITLE_MARGIN_SCALE
+                };
+                let x = match column {
+                    0 => margin_left,
+                    1 => margin_left + (surface_width - margin_left - margin_right - width) / 2.0,
+                    _ => surface_width - margin_right - width,
+                };
+                let y = match row {
+                    0 => surface_height - margin_vertical - height,
+                    1 => (surface_height - height) / 2.0,
+                    _ => margin_vertical,
+                };
+                (x, y)
+            }
+        };
+
+        Some(SubtitleImage::Text {
+            image: OverlayImage::new(display, image.width, image.height, image.rgba),
+            x,
+            y,
+        })
+    }
+
+    fn draw_subtitles(&self, target: &mut glium::Frame) {
+        if !self.subtitles_visible {
+            return;
+        }
+
+        for subtitle in &self.subtitle_images {
+            let (image, rect) = match subtitle {
+                SubtitleImage::Text { image, x, y } => (
+                    image,
+                    overlay::pixel_rect_to_ndc(
+                        *x,
+                        *y,
+                        image.width as f32,
+                        image.height as f32,
+                        self.surface_size.width,
+                        self.surface_size.height,
+                    ),
+                ),
+                SubtitleImage::Bitmap { image, placement } => match self.bitmap_rect(placement) {
+                    Some(rect) => (image, rect),
+                    None => continue,
+                },
+            };
+            self.overlay.draw(&self.display, target, image, rect, 1.0);
+        }
+    }
+
+    /// 把字幕画布上的矩形经由视频画面的纹理坐标映射到标准化设备坐标
+    fn bitmap_rect(&self, placement: &BitmapPlacement) -> Option<[f32; 4]> {
+        let (bottom_left, top_right) = (
+            self.display_vertices.first()?,
+            self.display_vertices.get(2)?,
+        );
+        let canvas_width = if placement.canvas_width > 0 {
+            placement.canvas_width
+        } else {
+            self.frame_width
+        };
+        let canvas_height = if placement.canvas_height > 0 {
+            placement.canvas_height
+        } else {
+            self.frame_height
+        };
+
+        let map = |u: f32, v: f32| {
+            let [tex_left, tex_bottom] = bottom_left.tex_coords;
+            let [tex_right, tex_top] = top_right.tex_coords;
+            let [left, bottom] = bottom_left.position;
+            let [right, top] = top_right.position;
+            (
+                left + (u - tex_left) / (tex_right - tex_left) * (right - left),
+                bottom + (v - tex_bottom) / (tex_top - tex_bottom) * (top - bottom),
+            )
+        };
+
+        let (left, bottom) = map(
+            placement.x as f32 / canvas_width as f32,
+            (placement.y + placement.height) as f32 / canvas_height as f32,
+        );
+        let (right, top) = map(
+            (placement.x + placement.width) as f32 / canvas_width as f32,
+            placement.y as f32 / canvas_height as f32,
+        );
+        Some([left, bottom, right, top])
     }
 
-    pub fn render_frame(&mut self, frame: &VideoFrame) {
+    /// 上传并显示一帧，field 不为 None 时在着色器中只用该场的行重建画面
+    pub fn render_frame(&mut self, frame: &VideoFrame, field: Option<Field>) {
+        self.field = field;
         let width = frame.width() as u32;
         let height = frame.height() as u32;
 
@@ -314,6 +944,10 @@ impl Renderer {
             self.frame_width = width;
             self.frame_height = height;
             self.update_vertex_buffer();
+            // 纹理尺寸与帧绑定，尺寸变化后重新创建
+            self.y_texture = None;
+            self.u_texture = None;
+            self.v_texture = None;
         }
 
         self.back_buffer.copy_from_frame(frame);
@@ -445,28 +1079,94 @@ impl Renderer {
             );
         }
 
+        self.draw();
+
+        std::mem::swap(&mut self.front_buffer, &mut self.back_buffer);
+    }
+
+    /// 用已上传的纹理重新绘制当前画面，用于窗口尺寸或缩放模式变化后刷新；
+    /// 没有画面时只绘制音频可视化、字幕和 OSD
+    pub fn redraw(&mut self) {
+        self.draw();
+    }
+
+    fn draw(&mut self) {
         let mut target = self.display.draw();
         target.clear_color(0.0, 0.0, 0.0, 1.0);
 
-        let uniforms = uniform! {
-            y_tex: self.y_texture.as_ref().unwrap(),
-            u_tex: self.u_texture.as_ref().unwrap(),
-            v_tex: self.v_texture.as_ref().unwrap(),
+        if let (Some(y_tex), Some(u_tex), Some(v_tex)) =
+            (&self.y_texture, &self.u_texture, &self.v_texture)
+        {
+            // 0 不去隔行，1 bob，2 linear，与片段着色器中的约定一致
+            let deinterlace = match (self.field, self.deinterlacer) {
+                (Some(_), Deinterlacer::Bob) => 1,
+                (Some(_), Deinterlacer::Linear) => 2,
+                _ => 0,
+            };
+            let uniforms = uniform! {
+                y_tex: y_tex,
+                u_tex: u_tex,
+                v_tex: v_tex,
+                deinterlace: deinterlace as i32,
+                field: if self.field == Some(Field::Bottom) { 1i32 } else { 0i32 },
+            };
+
+            target
+                .draw(
+                    &self.vertex_buffer,
+                    &self.index_buffer,
+                    &self.program,
+                    &uniforms,
+                    &Default::default(),
+                )
+                .unwrap();
+        }
+
+        // 有封面或视频时画在底部，不挡住画面主体
+        let (rect, background) = if self.y_texture.is_some() {
+            (VISUALIZATION_OVERLAY_RECT, true)
+        } else {
+            (VISUALIZATION_RECT, false)
         };
+        self.visualizer.draw(&self.display, &mut target, rect, background);
 
-        target
-            .draw(
-                &self.vertex_buffer,
-                &self.index_buffer,
-                &self.program,
-                &uniforms,
-                &Default::default(),
-            )
-            .unwrap();
+        self.draw_subtitles(&mut target);
+        self.osd.draw(
+            &self.display,
+            &mut target,
+            (self.surface_size.width, self.surface_size.height),
+        );
 
         target.finish().unwrap();
+    }
 
-        std::mem::swap(&mut self.front_buffer, &mut self.back_buffer);
+    /// 读回 GL 前缓冲区，保存窗口中实际显示的内容（包含黑边、缩放和裁剪效果）
+    pub fn screenshot_window(&self, path: &Path) -> Result<(), anyhow::Error> {
+        let image: RawImage2d<u8> = self.display.read_front_buffer()?;
+        let width = image.width;
+        let height = image.height;
+
+        // GL 的行序是自下而上，PNG 需要自上而下
+        let row_bytes = width as usize * 4;
+        let mut pixels = Vec::with_capacity(image.data.len());
+        for row in image.data.chunks_exact(row_bytes).rev() {
+            pixels.extend_from_slice(row);
+        }
+
+        screenshot::write_png(path, width, height, png::ColorType::Rgba, &pixels)
+    }
+
+    /// 当前显示器的刷新间隔，无法获取刷新率时按 60Hz 处理
+    pub fn refresh_interval(&self) -> Duration {
+        let millihertz = self
+            .display
+            .gl_window()
+            .window()
+            .current_monitor()
+            .and_then(|monitor| monitor.refresh_rate_millihertz())
+            .filter(|&millihertz| millihertz > 0)
+            .unwrap_or(DEFAULT_REFRESH_RATE_MILLIHERTZ);
+        Duration::from_secs_f64(1000.0 / millihertz as f64)
     }
 
     fn calculate_display_vertices(
@@ -475,8 +1175,29 @@ impl Renderer {
         video_width: u32,
         video_height: u32,
         mode: ScaleMode,
+        view: &ViewState,
     ) -> Vec<Vertex> {
-        let video_aspect = video_width as f32 / video_height as f32;
+        // 裁剪区域限制在视频范围内，通过纹理坐标只采样这一部分
+        let (crop_x, crop_y, crop_width, crop_height) = match view.crop {
+            Some(crop) => {
+                let x = crop.x.min(video_width.saturating_sub(1));
+                let y = crop.y.min(video_height.saturating_sub(1));
+                let width = crop.width.clamp(1, video_width - x);
+                let height = crop.height.clamp(1, video_height - y);
+                (x, y, width, height)
+            }
+            None => (0, 0, video_width, video_height),
+        };
+
+        let tex_left = crop_x as f32 / video_width as f32;
+        let tex_right = (crop_x + crop_width) as f32 / video_width as f32;
+        let tex_top = crop_y as f32 / video_height as f32;
+        let tex_bottom = (crop_y + crop_height) as f32 / video_height as f32;
+
+        let video_aspect = match mode {
+            ScaleMode::Aspect(aspect) => aspect,
+            _ => crop_width as f32 / crop_height as f32,
+        };
         let window_aspect = window_width as f32 / window_height as f32;
 
         info!("[Renderer] 计算显示顶点");
@@ -488,10 +1209,10 @@ impl Renderer {
             "[Renderer] 视频尺寸: {}x{} (比例: {:.3})",
             video_width, video_height, video_aspect
         );
-        info!("[Renderer] 缩放模式: {:?}", mode);
+        info!("[Renderer] 缩放模式: {:?}, 视图: {:?}", mode, view);
 
         let (scale_x, scale_y) = match mode {
-            ScaleMode::Fit => {
+            ScaleMode::Fit | ScaleMode::Aspect(_) => {
                 if window_aspect > video_aspect {
                     (video_aspect / window_aspect, 1.0)
                 } else {
@@ -505,8 +1226,17 @@ impl Renderer {
                     (video_aspect / window_aspect, 1.0)
                 }
             }
+            ScaleMode::Stretch => (1.0, 1.0),
+            ScaleMode::Original => (
+                crop_width as f32 / window_width as f32,
+                crop_height as f32 / window_height as f32,
+            ),
         };
 
+        let scale_x = scale_x * view.zoom;
+        let scale_y = scale_y * view.zoom;
+        let [pan_x, pan_y] = view.pan;
+
         info!("[Renderer] 缩放比例: ({:.3}, {:.3})", scale_x, scale_y);
         info!(
             "[Renderer] 最终显示尺寸: {:.3} x {:.3}",
@@ -516,21 +1246,45 @@ impl Renderer {
 
         vec![
             Vertex {
-                position: [-scale_x, -scale_y],
-                tex_coords: [0.0, 1.0],
+                position: [pan_x - scale_x, pan_y - scale_y],
+                tex_coords: [tex_left, tex_bottom],
             },
             Vertex {
-                position: [scale_x, -scale_y],
-                tex_coords: [1.0, 1.0],
+                position: [pan_x + scale_x, pan_y - scale_y],
+                tex_coords: [tex_right, tex_bottom],
             },
             Vertex {
-                position: [scale_x, scale_y],
-                tex_coords: [1.0, 0.0],
+                position: [pan_x + scale_x, pan_y + scale_y],
+                tex_coords: [tex_right, tex_top],
             },
             Vertex {
-                position: [-scale_x, scale_y],
-                tex_coords: [0.0, 0.0],
+                position: [pan_x - scale_x, pan_y + scale_y],
+                tex_coords: [tex_left, tex_top],
             },
         ]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_crop_rect() {
+        let crop: CropRect = "1920x800+0+140".parse().unwrap();
+        assert_eq!(crop, CropRect { x: 0, y: 140, width: 1920, height: 800 });
+        let crop: CropRect = "640X360".parse().unwrap();
+        assert_eq!(crop, CropRect { x: 0, y: 0, width: 640, height: 360 });
+    }
+
+    #[test]
+    fn rejects_invalid_crop_rect() {
+        let invalid = [
+            "", "1920", "1920x", "axb", "0x800", "1920x0", "1920x800+10", "1920x800+-1+0",
+        ];
+        for text in invalid {
+            let error = text.parse::<CropRect>().unwrap_err();
+            assert!(error.starts_with("无效的裁剪区域"), "{}: {}", text, error);
+        }
+    }
+}
@@ -0,0 +1,81 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use ffmpeg::{format::Pixel, util::frame::Video};
+
+/// 截图文件路径：`<目录>/<媒体文件名>_<时:分:秒.毫秒>[_后缀].png`
+pub fn screenshot_path(dir: &Path, media_path: &Path, pts: Duration, suffix: &str) -> PathBuf {
+    let media_name = media_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| String::from("screenshot"));
+
+    let millis = pts.as_millis();
+    let timestamp = format!(
+        "{:02}-{:02}-{:02}.{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    );
+
+    let file_name = if suffix.is_empty() {
+        format!("{}_{}.png", media_name, timestamp)
+    } else {
+        format!("{}_{}_{}.png", media_name, timestamp, suffix)
+    };
+    dir.join(file_name)
+}
+
+/// 以解码帧的原始分辨率保存为 PNG
+pub fn save_video_frame(frame: &Video, path: &Path) -> Result<(), anyhow::Error> {
+    let width = frame.width();
+    let height = frame.height();
+
+    let mut rgb_frame = Video::empty();
+    let mut context = ffmpeg::software::scaling::Context::get(
+        frame.format(),
+        width,
+        height,
+        Pixel::RGB24,
+        width,
+        height,
+        ffmpeg::software::scaling::Flags::BILINEAR,
+    )?;
+    context.run(frame, &mut rgb_frame)?;
+
+    // 去掉每行末尾的对齐填充
+    let row_bytes = width as usize * 3;
+    let stride = rgb_frame.stride(0);
+    let data = rgb_frame.data(0);
+    let mut pixels = Vec::with_capacity(row_bytes * height as usize);
+    for row in 0..height as usize {
+        pixels.extend_from_slice(&data[row * stride..row * stride + row_bytes]);
+    }
+
+    write_png(path, width, height, png::ColorType::Rgb, &pixels)
+}
+
+pub fn write_png(
+    path: &Path,
+    width: u32,
+    height: u32,
+    color: png::ColorType,
+    pixels: &[u8],
+) -> Result<(), anyhow::Error> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let file = std::fs::File::create(path)?;
+    let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), width, height);
+    encoder.set_color(color);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(pixels)?;
+
+    tracing::info!("截图已保存: {:?} ({}x{})", path, width, height);
+    Ok(())
+}
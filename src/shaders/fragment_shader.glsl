@@ -6,17 +6,44 @@ out vec4 color;
 uniform sampler2D y_tex;
 uniform sampler2D u_tex;
 uniform sampler2D v_tex;
+// 去隔行方式：0 不处理，1 bob，2 linear
+uniform int deinterlace;
+// 要显示的场：0 顶场（偶数行），1 底场（奇数行）
+uniform int field;
 
 // BT.601 标准的 YUV 到 RGB 转换矩阵
 const vec3 Rcoeff = vec3(1.164, 0.000, 1.596);
 const vec3 Gcoeff = vec3(1.164, -0.392, -0.813);
 const vec3 Bcoeff = vec3(1.164, 2.017, 0.000);
 
+float sample_row(sampler2D tex, float row, float height) {
+    return texture(tex, vec2(v_tex_coords.x, (row + 0.5) / height)).r;
+}
+
+// 只用指定场的行重建画面：本场的行直接采样，另一场的行
+// bob 取相邻的本场行，linear 取上下两行本场行的平均
+float sample_field(sampler2D tex) {
+    if (deinterlace == 0) {
+        return texture(tex, v_tex_coords).r;
+    }
+    float height = float(textureSize(tex, 0).y);
+    float row = floor(v_tex_coords.y * height);
+    if (mod(row, 2.0) == float(field)) {
+        return sample_row(tex, row, height);
+    }
+    float above = row > 0.0 ? row - 1.0 : row + 1.0;
+    if (deinterlace == 1) {
+        return sample_row(tex, above, height);
+    }
+    float below = row + 1.0 < height ? row + 1.0 : row - 1.0;
+    return 0.5 * (sample_row(tex, above, height) + sample_row(tex, below, height));
+}
+
 void main() {
     // 从纹理中采样 YUV 值
-    float y = texture(y_tex, v_tex_coords).r;
-    float u = texture(u_tex, v_tex_coords).r;
-    float v = texture(v_tex, v_tex_coords).r;
+    float y = sample_field(y_tex);
+    float u = sample_field(u_tex);
+    float v = sample_field(v_tex);
     
     // 调整 YUV 值的范围
     y = (y - 16.0/255.0) * (255.0/219.0);
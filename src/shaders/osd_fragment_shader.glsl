@@ -0,0 +1,13 @@
+#version 140
+
+in vec2 v_tex_coords;
+in vec4 v_color;
+out vec4 color;
+
+uniform sampler2D atlas_tex;
+
+void main() {
+    // 字形图集是单通道覆盖率，纯色矩形采样图集中保留的不透明区域
+    float coverage = texture(atlas_tex, v_tex_coords).r;
+    color = vec4(v_color.rgb, v_color.a * coverage);
+}
@@ -0,0 +1,13 @@
+#version 140
+
+in vec2 v_tex_coords;
+out vec4 color;
+
+uniform sampler2D overlay_tex;
+uniform float opacity;
+
+void main() {
+    // 叠加层纹理是非预乘的 RGBA，透明度在混合阶段生效
+    vec4 texel = texture(overlay_tex, v_tex_coords);
+    color = vec4(texel.rgb, texel.a * opacity);
+}
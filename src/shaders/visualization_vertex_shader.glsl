@@ -0,0 +1,9 @@
+#version 140
+in vec2 position;
+in vec4 color;
+out vec4 v_color;
+
+void main() {
+    v_color = color;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
@@ -0,0 +1,225 @@
+use std::sync::atomic::{AtomicI64, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 解码耗时等滑动平均的平滑系数，新样本占 1/8
+const AVERAGE_WEIGHT: u64 = 8;
+
+/// 视频解码器的静态信息，视频线程启动时填写
+#[derive(Clone, Debug, Default)]
+pub struct VideoStreamInfo {
+    pub codec: String,
+    pub width: u32,
+    pub height: u32,
+    pub pixel_format: String,
+    pub color_space: String,
+    pub color_range: String,
+    pub decoder_threads: usize,
+}
+
+/// 音频解码器和输出设备的静态信息，切换音轨或输出设备时更新
+#[derive(Clone, Debug, Default)]
+pub struct AudioStreamInfo {
+    pub codec: String,
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub output_device: String,
+    pub output_sample_rate: u32,
+    pub output_channels: u16,
+}
+
+/// 解复用、解码线程和渲染循环共享的播放统计，
+/// 计数器用原子量记录，渲染循环通过 snapshot 读取
+#[derive(Default)]
+pub struct PlaybackStats {
+    container: Mutex<String>,
+    video: Mutex<Option<VideoStreamInfo>>,
+    audio: Mutex<Option<AudioStreamInfo>>,
+    /// 解码线程已消费的数据包字节数，用于估算码率
+    bytes_consumed: AtomicU64,
+    video_packets_queued: AtomicUsize,
+    audio_packets_queued: AtomicUsize,
+    ring_buffer_len: AtomicUsize,
+    ring_buffer_capacity: AtomicUsize,
+    /// 正在从扬声器播出的音频位置（微秒），负数表示未知
+    audio_position_us: AtomicI64,
+    /// 音频从写入环形缓冲区到播出的总延迟（微秒），负数表示未知
+    output_latency_us: AtomicI64,
+    /// cpal 回调取不到足够采样、只能补静音的次数
+    underruns: AtomicU64,
+    /// 交付时已经过了显示时间的帧数
+    late_frames: AtomicU64,
+    decode_time_us: AtomicU64,
+}
+
+/// 某一时刻的统计快照
+#[derive(Clone, Debug, Default)]
+pub struct StatsSnapshot {
+    pub container: String,
+    pub video: Option<VideoStreamInfo>,
+    pub audio: Option<AudioStreamInfo>,
+    pub bytes_consumed: u64,
+    pub video_packets_queued: usize,
+    pub audio_packets_queued: usize,
+    pub ring_buffer_len: usize,
+    pub ring_buffer_capacity: usize,
+    pub audio_position: Option<Duration>,
+    pub output_latency: Option<Duration>,
+    pub underruns: u64,
+    pub late_frames: u64,
+    pub decode_time: Duration,
+}
+
+impl PlaybackStats {
+    pub fn new() -> Self {
+        let stats = Self::default();
+        stats.audio_position_us.store(-1, Ordering::Relaxed);
+        stats.output_latency_us.store(-1, Ordering::Relaxed);
+        stats
+    }
+
+    pub fn set_container(&self, container: String) {
+        *self.container.lock().unwrap() = container;
+    }
+
+    pub fn set_video_info(&self, info: VideoStreamInfo) {
+        *self.video.lock().unwrap() = Some(info);
+    }
+
+    pub fn set_audio_info(&self, info: AudioStreamInfo) {
+        *self.audio.lock().unwrap() = Some(info);
+    }
+
+    /// 音频线程打开或重建输出流后更新输出设备的参数
+    pub fn set_audio_output(&self, device: String, sample_rate: u32, channels: u16) {
+        if let Some(info) = self.audio.lock().unwrap().as_mut() {
+            info.output_device = device;
+            info.output_sample_rate = sample_rate;
+            info.output_channels = channels;
+        }
+    }
+
+    pub fn add_bytes(&self, bytes: usize) {
+        self.bytes_consumed
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn set_video_packets_queued(&self, queued: usize) {
+        self.video_packets_queued.store(queued, Ordering::Relaxed);
+    }
+
+    pub fn set_audio_packets_queued(&self, queued: usize) {
+        self.audio_packets_queued.store(queued, Ordering::Relaxed);
+    }
+
+    pub fn set_ring_buffer(&self, len: usize, capacity: usize) {
+        self.ring_buffer_len.store(len, Ordering::Relaxed);
+        self.ring_buffer_capacity.store(capacity, Ordering::Relaxed);
+    }
+
+    pub fn set_audio_position(&self, position: Option<Duration>) {
+        let micros = position.map_or(-1, |position| position.as_micros() as i64);
+        self.audio_position_us.store(micros, Ordering::Relaxed);
+    }
+
+    pub fn set_output_latency(&self, latency: Option<Duration>) {
+        let micros = latency.map_or(-1, |latency| latency.as_micros() as i64);
+        self.output_latency_us.store(micros, Ordering::Relaxed);
+    }
+
+    pub fn output_latency(&self) -> Option<Duration> {
+        u64::try_from(self.output_latency_us.load(Ordering::Relaxed))
+            .ok()
+            .map(Duration::from_micros)
+    }
+
+    /// 由 cpal 回调调用，只做一次原子加法
+    pub fn add_underrun(&self) {
+        self.underruns.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn underruns(&self) -> u64 {
+        self.underruns.load(Ordering::Relaxed)
+    }
+
+    pub fn add_late_frame(&self) {
+        self.late_frames.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 只有视频线程写入，读改写不需要原子操作
+    pub fn record_decode_time(&self, elapsed: Duration) {
+        let average = self.decode_time_us.load(Ordering::Relaxed);
+        self.decode_time_us.store(
+            moving_average(average, elapsed.as_micros() as u64),
+            Ordering::Relaxed,
+        );
+    }
+
+    pub fn snapshot(&self) -> StatsSnapshot {
+        let audio_position_us = self.audio_position_us.load(Ordering::Relaxed);
+        StatsSnapshot {
+            container: self.container.lock().unwrap().clone(),
+            video: self.video.lock().unwrap().clone(),
+            audio: self.audio.lock().unwrap().clone(),
+            bytes_consumed: self.bytes_consumed.load(Ordering::Relaxed),
+            video_packets_queued: self.video_packets_queued.load(Ordering::Relaxed),
+            audio_packets_queued: self.audio_packets_queued.load(Ordering::Relaxed),
+            ring_buffer_len: self.ring_buffer_len.load(Ordering::Relaxed),
+            ring_buffer_capacity: self.ring_buffer_capacity.load(Ordering::Relaxed),
+            audio_position: u64::try_from(audio_position_us)
+                .ok()
+                .map(Duration::from_micros),
+            output_latency: self.output_latency(),
+            underruns: self.underruns(),
+            late_frames: self.late_frames.load(Ordering::Relaxed),
+            decode_time: Duration::from_micros(self.decode_time_us.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// 根据两次快照之间消费的字节数估算当前码率
+pub struct BitrateMeter {
+    last_bytes: u64,
+    last_sample: Instant,
+    bits_per_second: f64,
+}
+
+impl BitrateMeter {
+    pub fn new() -> Self {
+        Self {
+            last_bytes: 0,
+            last_sample: Instant::now(),
+            bits_per_second: 0.0,
+        }
+    }
+
+    /// 计数器回退（切换到播放列表的下一项）时重新开始计算
+    pub fn update(&mut self, bytes_consumed: u64, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.last_sample);
+        if bytes_consumed < self.last_bytes {
+            self.last_bytes = bytes_consumed;
+            self.last_sample = now;
+        } else if elapsed >= Duration::from_millis(500) {
+            let bits = (bytes_consumed - self.last_bytes) as f64 * 8.0;
+            self.bits_per_second = bits / elapsed.as_secs_f64();
+            self.last_bytes = bytes_consumed;
+            self.last_sample = now;
+        }
+        self.bits_per_second
+    }
+}
+
+impl Default for BitrateMeter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 整数滑动平均，第一个样本直接作为初值
+pub fn moving_average(average: u64, sample: u64) -> u64 {
+    if average == 0 {
+        sample
+    } else {
+        (average * (AVERAGE_WEIGHT - 1) + sample) / AVERAGE_WEIGHT
+    }
+}
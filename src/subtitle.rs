@@ -0,0 +1,389 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// 没有明确结束时间的字幕（如 PGS）默认显示时长，等待下一条字幕或清屏事件来结束
+const UNKNOWN_END: Duration = Duration::MAX;
+/// 文本字幕缺少时长信息时的默认显示时长
+const DEFAULT_TEXT_DURATION: Duration = Duration::from_secs(5);
+
+/// 流式字幕轨道保留已结束字幕的时长，便于向后调整字幕延迟
+const RETAIN_AFTER_END: Duration = Duration::from_secs(10);
+
+static NEXT_CUE_ID: AtomicU64 = AtomicU64::new(1);
+
+/// 位图字幕，RGBA 像素，坐标相对于字幕画布
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubtitleBitmap {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+    /// 字幕画布尺寸，为 0 时使用视频尺寸
+    pub canvas_width: u32,
+    pub canvas_height: u32,
+}
+
+/// 带样式的文本字幕（来自 ASS/SSA），坐标和字号以脚本分辨率为单位
+#[derive(Clone, Debug, PartialEq)]
+pub struct StyledText {
+    pub text: String,
+    /// 字号，None 时使用默认字幕字号
+    pub font_size: Option<f32>,
+    /// RGBA
+    pub color: [u8; 4],
+    pub outline_color: [u8; 4],
+    pub bold: bool,
+    pub italic: bool,
+    /// 小键盘方位：1-3 底部，4-6 中部，7-9 顶部；左中右依次排列
+    pub alignment: u8,
+    pub margin_left: u32,
+    pub margin_right: u32,
+    pub margin_vertical: u32,
+    /// `\pos(x,y)` 指定的锚点
+    pub position: Option<(f32, f32)>,
+    /// 脚本分辨率 PlayResX/PlayResY
+    pub play_res: (u32, u32),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SubtitleItem {
+    Text(String),
+    Styled(StyledText),
+    Bitmap(SubtitleBitmap),
+}
+
+/// 一条带时间范围的字幕，items 为空表示清屏事件
+#[derive(Clone, Debug)]
+pub struct SubtitleCue {
+    pub id: u64,
+    pub start: Duration,
+    pub end: Duration,
+    pub items: Vec<SubtitleItem>,
+}
+
+impl SubtitleCue {
+    pub fn new(start: Duration, end: Duration, items: Vec<SubtitleItem>) -> Self {
+        Self {
+            id: NEXT_CUE_ID.fetch_add(1, Ordering::Relaxed),
+            start,
+            end,
+            items,
+        }
+    }
+
+    fn is_bitmap(&self) -> bool {
+        self.items
+            .iter()
+            .any(|item| matches!(item, SubtitleItem::Bitmap(_)))
+    }
+}
+
+/// 字幕集合，渲染循环按播放时钟从中取出当前应显示的字幕
+#[derive(Clone, Default)]
+pub struct SubtitleTrack {
+    cues: Arc<Mutex<Vec<Arc<SubtitleCue>>>>,
+    /// 字幕相对于视频的延迟，单位毫秒，正值表示字幕推后显示
+    delay_ms: Arc<AtomicI64>,
+    /// 外挂字幕一次性加载全部字幕，不随播放丢弃
+    persistent: bool,
+}
+
+impl SubtitleTrack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从外挂字幕文件解析出的完整字幕列表创建轨道
+    pub fn from_cues(mut cues: Vec<SubtitleCue>) -> Self {
+        cues.sort_by_key(|cue| cue.start);
+        Self {
+            cues: Arc::new(Mutex::new(cues.into_iter().map(Arc::new).collect())),
+            delay_ms: Arc::new(AtomicI64::new(0)),
+            persistent: true,
+        }
+    }
+
+    pub fn push(&self, cue: SubtitleCue) {
+        let mut cues = self.cues.lock().unwrap();
+
+        // 清屏事件或新的位图字幕会结束之前尚未结束的位图字幕
+        if cue.items.is_empty() || cue.is_bitmap() {
+            for previous in cues.iter_mut() {
+                if previous.is_bitmap() && previous.start <= cue.start && previous.end > cue.start {
+                    Arc::make_mut(previous).end = cue.start;
+                }
+            }
+        }
+
+        if cue.items.is_empty() {
+            return;
+        }
+
+        // 循环播放跳回后同一条字幕会再次解码出来
+        let duplicate = cues
+            .iter()
+            .any(|existing| existing.start == cue.start && existing.items == cue.items);
+        if duplicate {
+            return;
+        }
+
+        let index = cues.partition_point(|existing| existing.start <= cue.start);
+        cues.insert(index, Arc::new(cue));
+    }
+
+    /// 当前时刻应显示的字幕（已计入字幕延迟）
+    pub fn active_at(&self, position: Duration) -> Vec<Arc<SubtitleCue>> {
+        let delay = Duration::from_millis(self.delay_ms().unsigned_abs());
+        let position = if self.delay_ms() >= 0 {
+            position.saturating_sub(delay)
+        } else {
+            position + delay
+        };
+
+        let mut cues = self.cues.lock().unwrap();
+        if !self.persistent {
+            cues.retain(|cue| cue.end.saturating_add(RETAIN_AFTER_END) > position);
+        }
+        cues.iter()
+            .take_while(|cue| cue.start <= position)
+            .filter(|cue| cue.end > position)
+            .cloned()
+            .collect()
+    }
+
+    pub fn delay_ms(&self) -> i64 {
+        self.delay_ms.load(Ordering::Relaxed)
+    }
+
+    pub fn adjust_delay(&self, delta_ms: i64) -> i64 {
+        let delay = self.delay_ms.fetch_add(delta_ms, Ordering::Relaxed) + delta_ms;
+        tracing::info!("字幕延迟: {} ms", delay);
+        delay
+    }
+
+    pub fn clear(&self) {
+        self.cues.lock().unwrap().clear();
+    }
+}
+
+pub struct SubtitlePlaybackThread {
+    packet_sender: smol::channel::Sender<ffmpeg::codec::packet::packet::Packet>,
+    receiver_thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl SubtitlePlaybackThread {
+    pub fn start(
+        stream: &ffmpeg::format::stream::Stream,
+        mut subtitle_callback: Box<dyn FnMut(SubtitleCue) + Send>,
+    ) -> Result<Self, anyhow::Error> {
+        let parameters = stream.parameters();
+        tracing::info!("字幕线程启动 - 编码: {:?}", parameters.id());
+
+        // 位图字幕的坐标相对于编码参数中的画布尺寸
+        let (canvas_width, canvas_height) = unsafe {
+            let parameters = &*parameters.as_ptr();
+            (
+                parameters.width.max(0) as u32,
+                parameters.height.max(0) as u32,
+            )
+        };
+
+        let (packet_sender, packet_receiver) =
+            smol::channel::bounded::<ffmpeg::codec::packet::packet::Packet>(128);
+
+        let decoder_context = ffmpeg::codec::Context::from_parameters(parameters)?;
+        let mut packet_decoder = decoder_context.decoder().subtitle()?;
+
+        let time_base = stream.time_base();
+        let time_base_seconds = time_base.numerator() as f64 / time_base.denominator() as f64;
+
+        let receiver_thread = std::thread::Builder::new()
+            .name("subtitle playback thread".into())
+            .spawn(move || {
+                smol::block_on(async move {
+                    while let Ok(packet) = packet_receiver.recv().await {
+                        let mut subtitle = ffmpeg::codec::subtitle::Subtitle::new();
+                        match packet_decoder.decode(&packet, &mut subtitle) {
+                            Ok(true) => {}
+                            Ok(false) => continue,
+                            Err(e) => {
+                                tracing::error!("字幕解码失败: {}", e);
+                                continue;
+                            }
+                        }
+
+                        let packet_start = packet
+                            .pts()
+                            .map(|pts| (pts as f64 * time_base_seconds).max(0.0))
+                            .unwrap_or(0.0);
+                        let start = Duration::from_secs_f64(packet_start)
+                            + Duration::from_millis(subtitle.start() as u64);
+                        let end = if subtitle.end() > subtitle.start() && subtitle.end() != u32::MAX
+                        {
+                            start
+                                + Duration::from_millis((subtitle.end() - subtitle.start()) as u64)
+                        } else if packet.duration() > 0 {
+                            start
+                                + Duration::from_secs_f64(
+                                    packet.duration() as f64 * time_base_seconds,
+                                )
+                        } else {
+                            UNKNOWN_END
+                        };
+
+                        let items: Vec<SubtitleItem> = subtitle
+                            .rects()
+                            .filter_map(|rect| convert_rect(&rect, canvas_width, canvas_height))
+                            .collect();
+                        // ffmpeg-next 的 Subtitle 没有实现 Drop，需要手动释放解码器分配的矩形
+                        unsafe { ffmpeg::ffi::avsubtitle_free(subtitle.as_mut_ptr()) };
+
+                        let end = if end == UNKNOWN_END
+                            && items
+                                .iter()
+                                .all(|item| matches!(item, SubtitleItem::Text(_)))
+                            && !items.is_empty()
+                        {
+                            start + DEFAULT_TEXT_DURATION
+                        } else {
+                            end
+                        };
+
+                        tracing::debug!("字幕: {:?} - {:?}, {} 项", start, end, items.len());
+                        subtitle_callback(SubtitleCue::new(start, end, items));
+                    }
+                    tracing::debug!("字幕包接收结束");
+                })
+            })?;
+
+        Ok(Self {
+            packet_sender,
+            receiver_thread: Some(receiver_thread),
+        })
+    }
+
+    pub async fn receive_packet(&self, packet: ffmpeg::codec::packet::packet::Packet) -> bool {
+        match self.packet_sender.send(packet).await {
+            Ok(_) => {
+                tracing::debug!("字幕包发送成功");
+                true
+            }
+            Err(e) => {
+                tracing::error!("字幕包发送失败: {}", e);
+                false
+            }
+        }
+    }
+}
+
+impl Drop for SubtitlePlaybackThread {
+    fn drop(&mut self) {
+        tracing::info!("SubtitlePlaybackThread drop");
+        self.packet_sender.close();
+        if let Some(receiver_join_handle) = self.receiver_thread.take() {
+            receiver_join_handle.join().unwrap();
+        }
+    }
+}
+
+fn convert_rect(
+    rect: &ffmpeg::codec::subtitle::Rect,
+    canvas_width: u32,
+    canvas_height: u32,
+) -> Option<SubtitleItem> {
+    match rect {
+        ffmpeg::codec::subtitle::Rect::Text(text) => {
+            Some(SubtitleItem::Text(text.get().trim_end().to_owned()))
+        }
+        ffmpeg::codec::subtitle::Rect::Ass(ass) => {
+            let text = ass_dialogue_text(ass.get());
+            (!text.is_empty()).then(|| SubtitleItem::Text(text))
+        }
+        ffmpeg::codec::subtitle::Rect::Bitmap(bitmap) => {
+            let width = bitmap.width();
+            let height = bitmap.height();
+            if width == 0 || height == 0 {
+                return None;
+            }
+            let rgba = unsafe { palette_to_rgba(&*bitmap.as_ptr()) };
+            Some(SubtitleItem::Bitmap(SubtitleBitmap {
+                x: bitmap.x() as u32,
+                y: bitmap.y() as u32,
+                width,
+                height,
+                rgba,
+                canvas_width,
+                canvas_height,
+            }))
+        }
+        ffmpeg::codec::subtitle::Rect::None(_) => None,
+    }
+}
+
+/// 把 PAL8 位图转换成 RGBA，调色板每项是本机字节序的 0xAARRGGBB
+///
+/// # Safety
+/// `rect` 必须是解码器输出的位图字幕，data[0] 为索引平面，data[1] 为调色板
+unsafe fn palette_to_rgba(rect: &ffmpeg::ffi::AVSubtitleRect) -> Vec<u8> {
+    let width = rect.w as usize;
+    let height = rect.h as usize;
+    let stride = rect.linesize[0] as usize;
+    let indices = std::slice::from_raw_parts(rect.data[0], stride * height);
+    let palette = std::slice::from_raw_parts(rect.data[1] as *const u32, 256);
+    let colors = (rect.nb_colors as usize).min(256);
+
+    let mut rgba = Vec::with_capacity(width * height * 4);
+    for row in 0..height {
+        for &index in &indices[row * stride..row * stride + width] {
+            let argb = if (index as usize) < colors {
+                palette[index as usize]
+            } else {
+                0
+            };
+            rgba.extend_from_slice(&[
+                (argb >> 16) as u8,
+                (argb >> 8) as u8,
+                argb as u8,
+                (argb >> 24) as u8,
+            ]);
+        }
+    }
+    rgba
+}
+
+/// 从 ASS 事件行中取出正文：去掉前面的逗号分隔字段和 `{...}` 覆盖标签，`\N` 换行
+pub fn ass_dialogue_text(line: &str) -> String {
+    // 解码器输出格式: ReadOrder,Layer,Style,Name,MarginL,MarginR,MarginV,Effect,Text
+    let text = line.splitn(9, ',').nth(8).unwrap_or(line);
+    strip_ass_tags(text)
+}
+
+pub fn strip_ass_tags(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut in_tag = false;
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '{' => in_tag = true,
+            '}' if in_tag => in_tag = false,
+            _ if in_tag => {}
+            '\\' => match chars.peek() {
+                Some('N') | Some('n') => {
+                    chars.next();
+                    result.push('\n');
+                }
+                Some('h') => {
+                    chars.next();
+                    result.push(' ');
+                }
+                _ => result.push(ch),
+            },
+            _ => result.push(ch),
+        }
+    }
+    result.trim().to_owned()
+}
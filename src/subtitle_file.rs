@@ -0,0 +1,442 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::subtitle::{strip_ass_tags, StyledText, SubtitleCue, SubtitleItem};
+
+/// 自动查找外挂字幕时依次尝试的扩展名
+const SIDECAR_EXTENSIONS: &[&str] = &["ass", "ssa", "srt", "vtt"];
+
+/// ASS 脚本未声明 PlayResX/PlayResY 时的默认脚本分辨率
+const DEFAULT_PLAY_RES: (u32, u32) = (384, 288);
+
+/// 在视频文件旁边查找同名字幕文件，例如 `movie.mkv` 对应 `movie.srt` 或 `movie.zh.ass`
+pub fn find_sidecar(video_path: &Path) -> Option<PathBuf> {
+    let dir = video_path.parent()?;
+    let stem = video_path.file_stem()?.to_string_lossy().into_owned();
+
+    for extension in SIDECAR_EXTENSIONS {
+        let candidate = dir.join(format!("{}.{}", stem, extension));
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+    }
+
+    // 带语言后缀的字幕文件，按文件名排序保证结果稳定
+    let mut candidates: Vec<PathBuf> = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            let matches_extension = path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| {
+                    SIDECAR_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+                });
+            let matches_stem = path.file_name().is_some_and(|name| {
+                name.to_string_lossy().starts_with(&format!("{}.", stem))
+            });
+            matches_extension && matches_stem
+        })
+        .collect();
+    candidates.sort();
+    candidates.into_iter().next()
+}
+
+/// 按扩展名解析外挂字幕文件
+pub fn load(path: &Path) -> Result<Vec<SubtitleCue>, anyhow::Error> {
+    let bytes = std::fs::read(path)?;
+    let content = String::from_utf8_lossy(&bytes);
+    let content = content.trim_start_matches('\u{feff}');
+
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+
+    let cues = match extension.as_str() {
+        "srt" => parse_srt(content),
+        "vtt" => parse_vtt(content),
+        "ass" | "ssa" => parse_ass(content),
+        _ => anyhow::bail!("不支持的字幕格式: {:?}", path),
+    };
+
+    tracing::info!("加载外挂字幕 {:?}: {} 条", path, cues.len());
+    Ok(cues)
+}
+
+pub fn parse_srt(content: &str) -> Vec<SubtitleCue> {
+    parse_timed_blocks(content)
+}
+
+pub fn parse_vtt(content: &str) -> Vec<SubtitleCue> {
+    parse_timed_blocks(content)
+}
+
+/// SRT 和 WebVTT 都是空行分隔的块，块内带 `-->` 的行是时间行，其后是正文
+fn parse_timed_blocks(content: &str) -> Vec<SubtitleCue> {
+    let content = content.replace("\r\n", "\n");
+    let mut cues = Vec::new();
+
+    for block in content.split("\n\n") {
+        let mut lines = block.lines().skip_while(|line| !line.contains("-->"));
+        let Some(timing) = lines.next() else {
+            // WEBVTT 头、NOTE、STYLE 等块没有时间行
+            continue;
+        };
+
+        let mut parts = timing.split("-->");
+        let (Some(start), Some(end)) = (
+            parts.next().and_then(parse_timestamp),
+            parts
+                .next()
+                .and_then(|rest| rest.split_whitespace().next())
+                .and_then(parse_timestamp),
+        ) else {
+            tracing::warn!("无法解析字幕时间: {}", timing);
+            continue;
+        };
+
+        let raw_text = lines.collect::<Vec<_>>().join("\n");
+        let italic = raw_text.contains("<i>");
+        let bold = raw_text.contains("<b>");
+        let text = strip_markup(&raw_text);
+        if text.is_empty() {
+            continue;
+        }
+
+        let item = if italic || bold {
+            SubtitleItem::Styled(StyledText {
+                bold,
+                italic,
+                ..default_styled_text(text)
+            })
+        } else {
+            SubtitleItem::Text(text)
+        };
+        cues.push(SubtitleCue::new(start, end, vec![item]));
+    }
+
+    cues
+}
+
+/// 解析 `hh:mm:ss,mmm`、`hh:mm:ss.mmm` 或 `mm:ss.mmm`
+fn parse_timestamp(value: &str) -> Option<Duration> {
+    let value = value.trim().replace(',', ".");
+    let (clock, fraction) = value.split_once('.').unwrap_or((value.as_str(), "0"));
+
+    let mut seconds = 0u64;
+    for field in clock.split(':') {
+        seconds = seconds * 60 + field.trim().parse::<u64>().ok()?;
+    }
+
+    // 小数部分按位数换算，兼容 ASS 的百分之一秒
+    let digits = fraction.len().min(9) as u32;
+    let fraction: u64 = fraction.get(..digits as usize)?.parse().ok()?;
+    let nanos = fraction * 10u64.pow(9 - digits);
+
+    Some(Duration::from_secs(seconds) + Duration::from_nanos(nanos))
+}
+
+/// 去掉 SRT/WebVTT 中的 HTML 风格标签和 `{\an8}` 之类的 ASS 标签
+fn strip_markup(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut in_tag = false;
+    for ch in text.chars() {
+        match ch {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if in_tag => {}
+            _ => result.push(ch),
+        }
+    }
+    let result = result
+        .replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ");
+    strip_ass_tags(&result)
+}
+
+fn default_styled_text(text: String) -> StyledText {
+    StyledText {
+        text,
+        font_size: None,
+        color: [255, 255, 255, 255],
+        outline_color: [0, 0, 0, 255],
+        bold: false,
+        italic: false,
+        alignment: 2,
+        margin_left: 0,
+        margin_right: 0,
+        margin_vertical: 0,
+        position: None,
+        play_res: DEFAULT_PLAY_RES,
+    }
+}
+
+#[derive(Clone)]
+struct AssStyle {
+    font_size: Option<f32>,
+    color: [u8; 4],
+    outline_color: [u8; 4],
+    bold: bool,
+    italic: bool,
+    alignment: u8,
+    margin_left: u32,
+    margin_right: u32,
+    margin_vertical: u32,
+}
+
+impl Default for AssStyle {
+    fn default() -> Self {
+        Self {
+            font_size: None,
+            color: [255, 255, 255, 255],
+            outline_color: [0, 0, 0, 255],
+            bold: false,
+            italic: false,
+            alignment: 2,
+            margin_left: 10,
+            margin_right: 10,
+            margin_vertical: 10,
+        }
+    }
+}
+
+pub fn parse_ass(content: &str) -> Vec<SubtitleCue> {
+    let mut section = String::new();
+    let mut play_res = DEFAULT_PLAY_RES;
+    let mut legacy_alignment = false;
+    let mut style_format: Vec<String> = Vec::new();
+    let mut event_format: Vec<String> = Vec::new();
+    let mut styles: HashMap<String, AssStyle> = HashMap::new();
+    let mut cues = Vec::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('[') && line.ends_with(']') {
+            section = line.to_ascii_lowercase();
+            // SSA（V4 Styles）使用旧的对齐编号
+            legacy_alignment = section == "[v4 styles]";
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+
+        match (section.as_str(), key.trim()) {
+            ("[script info]", "PlayResX") => {
+                play_res.0 = value.parse().unwrap_or(play_res.0);
+            }
+            ("[script info]", "PlayResY") => {
+                play_res.1 = value.parse().unwrap_or(play_res.1);
+            }
+            ("[v4+ styles]" | "[v4 styles]", "Format") => {
+                style_format = parse_format(value);
+            }
+            ("[v4+ styles]" | "[v4 styles]", "Style") => {
+                let fields = split_fields(value, style_format.len());
+                let field = |name: &str| lookup(&style_format, &fields, name);
+
+                let mut style = AssStyle::default();
+                if let Some(size) = field("fontsize").and_then(|size| size.parse().ok()) {
+                    style.font_size = Some(size);
+                }
+                if let Some(color) = field("primarycolour").and_then(parse_ass_color) {
+                    style.color = color;
+                }
+                if let Some(color) = field("outlinecolour").and_then(parse_ass_color) {
+                    style.outline_color = color;
+                }
+                style.bold = field("bold").is_some_and(|value| value != "0");
+                style.italic = field("italic").is_some_and(|value| value != "0");
+                if let Some(alignment) = field("alignment").and_then(|value| value.parse().ok()) {
+                    style.alignment = if legacy_alignment {
+                        convert_legacy_alignment(alignment)
+                    } else {
+                        alignment
+                    };
+                }
+                if let Some(margin) = field("marginl").and_then(|value| value.parse().ok()) {
+                    style.margin_left = margin;
+                }
+                if let Some(margin) = field("marginr").and_then(|value| value.parse().ok()) {
+                    style.margin_right = margin;
+                }
+                if let Some(margin) = field("marginv").and_then(|value| value.parse().ok()) {
+                    style.margin_vertical = margin;
+                }
+
+                let name = field("name").unwrap_or("Default").to_owned();
+                styles.insert(name, style);
+            }
+            ("[events]", "Format") => {
+                event_format = parse_format(value);
+            }
+            ("[events]", "Dialogue") => {
+                if event_format.is_empty() {
+                    continue;
+                }
+                let fields = split_fields(value, event_format.len());
+                let field = |name: &str| lookup(&event_format, &fields, name);
+
+                let (Some(start), Some(end)) = (
+                    field("start").and_then(parse_timestamp),
+                    field("end").and_then(parse_timestamp),
+                ) else {
+                    tracing::warn!("无法解析 ASS 事件时间: {}", line);
+                    continue;
+                };
+
+                let style_name = field("style").unwrap_or("Default").trim_start_matches('*');
+                let style = styles.get(style_name).cloned().unwrap_or_default();
+
+                // 事件中非零的边距覆盖样式边距
+                let margin = |name: &str, fallback: u32| {
+                    field(name)
+                        .and_then(|value| value.parse::<u32>().ok())
+                        .filter(|&margin| margin > 0)
+                        .unwrap_or(fallback)
+                };
+
+                let mut styled = StyledText {
+                    text: String::new(),
+                    font_size: style.font_size,
+                    color: style.color,
+                    outline_color: style.outline_color,
+                    bold: style.bold,
+                    italic: style.italic,
+                    alignment: style.alignment,
+                    margin_left: margin("marginl", style.margin_left),
+                    margin_right: margin("marginr", style.margin_right),
+                    margin_vertical: margin("marginv", style.margin_vertical),
+                    position: None,
+                    play_res,
+                };
+
+                let raw_text = field("text").unwrap_or_default();
+                apply_override_tags(raw_text, &mut styled);
+                styled.text = strip_ass_tags(raw_text);
+                if styled.text.is_empty() {
+                    continue;
+                }
+
+                cues.push(SubtitleCue::new(
+                    start,
+                    end,
+                    vec![SubtitleItem::Styled(styled)],
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    cues
+}
+
+fn parse_format(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|field| field.trim().to_ascii_lowercase())
+        .collect()
+}
+
+/// 按 Format 的字段数切分，最后一个字段（Text）可以包含逗号
+fn split_fields(value: &str, count: usize) -> Vec<&str> {
+    value.splitn(count.max(1), ',').map(str::trim).collect()
+}
+
+fn lookup<'a>(format: &[String], fields: &[&'a str], name: &str) -> Option<&'a str> {
+    let index = format.iter().position(|field| field == name)?;
+    fields.get(index).copied()
+}
+
+/// 解析 `&HAABBGGRR`、`&HBBGGRR&` 或旧 SSA 的十进制颜色，ASS 的 alpha 中 00 为不透明
+fn parse_ass_color(value: &str) -> Option<[u8; 4]> {
+    let value = value.trim();
+    let value = match value
+        .strip_prefix("&H")
+        .or_else(|| value.strip_prefix("&h"))
+    {
+        Some(hex) => u32::from_str_radix(hex.trim_end_matches('&'), 16).ok()?,
+        None => value.parse::<i64>().ok()? as u32,
+    };
+
+    Some([
+        value as u8,
+        (value >> 8) as u8,
+        (value >> 16) as u8,
+        255 - (value >> 24) as u8,
+    ])
+}
+
+/// SSA 的对齐方式：1-3 底部，5-7 顶部，9-11 中部
+fn convert_legacy_alignment(alignment: u8) -> u8 {
+    match alignment {
+        5..=7 => alignment + 2,
+        9..=11 => alignment - 5,
+        _ => alignment,
+    }
+}
+
+/// 应用事件正文中 `{...}` 覆盖标签里的基本样式，后出现的标签覆盖前面的
+fn apply_override_tags(text: &str, styled: &mut StyledText) {
+    let mut rest = text;
+    while let Some(open) = rest.find('{') {
+        let Some(close) = rest[open..].find('}') else {
+            break;
+        };
+        let block = &rest[open + 1..open + close];
+        rest = &rest[open + close + 1..];
+
+        for tag in block
+            .split('\\')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+        {
+            if let Some(arguments) = tag
+                .strip_prefix("pos(")
+                .and_then(|tag| tag.strip_suffix(')'))
+            {
+                let mut values = arguments
+                    .split(',')
+                    .map(|value| value.trim().parse::<f32>());
+                if let (Some(Ok(x)), Some(Ok(y))) = (values.next(), values.next()) {
+                    styled.position = Some((x, y));
+                }
+            } else if let Some(value) = tag.strip_prefix("an") {
+                if let Ok(alignment) = value.parse() {
+                    styled.alignment = alignment;
+                }
+            } else if let Some(value) = tag.strip_prefix("fs") {
+                if let Ok(size) = value.parse() {
+                    styled.font_size = Some(size);
+                }
+            } else if let Some(value) = tag.strip_prefix("1c").or_else(|| tag.strip_prefix('c')) {
+                if let Some(color) = parse_ass_color(value) {
+                    styled.color = [color[0], color[1], color[2], styled.color[3]];
+                }
+            } else if let Some(value) = tag.strip_prefix("3c") {
+                if let Some(color) = parse_ass_color(value) {
+                    styled.outline_color = [color[0], color[1], color[2], styled.outline_color[3]];
+                }
+            } else if let Some(value) = tag.strip_prefix('b') {
+                // \b1、\b0 以及 \b700 这样的字重
+                if let Ok(weight) = value.parse::<u32>() {
+                    styled.bold = weight == 1 || weight >= 700;
+                }
+            } else if let Some(value) = tag.strip_prefix('i') {
+                if let Ok(italic) = value.parse::<u32>() {
+                    styled.italic = italic != 0;
+                }
+            } else if let Some(value) = tag.strip_prefix('a') {
+                if let Ok(alignment) = value.parse() {
+                    styled.alignment = convert_legacy_alignment(alignment);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use std::path::{Path, PathBuf};
+
+use ab_glyph::{point, Font, FontVec, GlyphId, PxScale, ScaleFont};
+
+/// 未配置字体时依次尝试的系统字体，优先选择带中文字形的字体
+const FALLBACK_FONTS: &[&str] = &[
+    "/System/Library/Fonts/PingFang.ttc",
+    "/System/Library/Fonts/STHeiti Medium.ttc",
+    "/System/Library/Fonts/Helvetica.ttc",
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/google-noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "C:\\Windows\\Fonts\\msyh.ttc",
+    "C:\\Windows\\Fonts\\arial.ttf",
+];
+
+/// 斜体的水平错切系数
+const ITALIC_SHEAR: f32 = 0.2;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextStyle {
+    /// 字号，单位为像素
+    pub size: f32,
+    /// RGBA
+    pub color: [u8; 4],
+    /// 描边宽度，单位为像素，0 表示不描边
+    pub outline: f32,
+    pub outline_color: [u8; 4],
+    pub bold: bool,
+    pub italic: bool,
+    pub align: TextAlign,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            size: 32.0,
+            color: [255, 255, 255, 255],
+            outline: 2.0,
+            outline_color: [0, 0, 0, 255],
+            bold: false,
+            italic: false,
+            align: TextAlign::Center,
+        }
+    }
+}
+
+/// 光栅化后的文本图像，RGBA 像素按行自上而下排列
+pub struct TextImage {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+/// 基于 ab_glyph 的文本光栅化器，把多行文本渲染成带描边的 RGBA 图像
+pub struct TextRasterizer {
+    font: FontVec,
+}
+
+impl TextRasterizer {
+    /// 优先加载指定字体，失败时回退到常见系统字体
+    pub fn load(preferred: Option<&Path>) -> Option<Self> {
+        load_font(preferred).map(|font| Self { font })
+    }
+
+    pub fn render(&self, text: &str, style: &TextStyle) -> Option<TextImage> {
+        let scale = PxScale::from(style.size.max(1.0));
+        let font = self.font.as_scaled(scale);
+        let line_height = font.height() + font.line_gap();
+
+        let lines: Vec<(&str, f32)> = text
+            .lines()
+            .map(|line| (line, self.line_width(line, scale)))
+            .collect();
+        if lines.is_empty() {
+            return None;
+        }
+
+        let text_width = lines.iter().map(|(_, width)| *width).fold(0.0, f32::max);
+        let italic_extra = if style.italic {
+            font.height() * ITALIC_SHEAR
+        } else {
+            0.0
+        };
+        let bold_extra = if style.bold { 1.0 } else { 0.0 };
+        let padding = style.outline.ceil() + 1.0;
+
+        let width = (text_width + italic_extra + bold_extra + padding * 2.0).ceil() as usize;
+        let height = (line_height * lines.len() as f32 + padding * 2.0).ceil() as usize;
+        if text_width <= 0.0 || width == 0 || height == 0 {
+            return None;
+        }
+
+        let mut coverage = vec![0.0f32; width * height];
+
+        for (line_index, (line, line_width)) in lines.iter().enumerate() {
+            let offset_x = match style.align {
+                TextAlign::Left => 0.0,
+                TextAlign::Center => (text_width - line_width) / 2.0,
+                TextAlign::Right => text_width - line_width,
+            };
+            let baseline = padding + font.ascent() + line_height * line_index as f32;
+
+            let mut caret = padding + offset_x;
+            let mut previous: Option<GlyphId> = None;
+            for ch in line.chars() {
+                let id = font.glyph_id(ch);
+                if let Some(previous) = previous {
+                    caret += font.kern(previous, id);
+                }
+                previous = Some(id);
+
+                let glyph = id.with_scale_and_position(scale, point(caret, baseline));
+                caret += font.h_advance(id);
+
+                let Some(outlined) = self.font.outline_glyph(glyph) else {
+                    continue;
+                };
+                let bounds = outlined.px_bounds();
+                outlined.draw(|x, y, value| {
+                    let py = bounds.min.y + y as f32;
+                    // 以下伸部底部为错切原点，保证整行向右倾斜且不越过左边界
+                    let shear = if style.italic {
+                        (baseline - font.descent() - py) * ITALIC_SHEAR
+                    } else {
+                        0.0
+                    };
+                    let px = (bounds.min.x + x as f32 + shear).round() as i64;
+                    let py = py.round() as i64;
+                    if px < 0 || py < 0 || px >= width as i64 || py >= height as i64 {
+                        return;
+                    }
+                    let index = py as usize * width + px as usize;
+                    coverage[index] = (coverage[index] + value).min(1.0);
+                });
+            }
+        }
+
+        if style.bold {
+            coverage = dilate(&coverage, width, height, 1);
+        }
+
+        let outline = if style.outline > 0.0 {
+            Some(dilate(
+                &coverage,
+                width,
+                height,
+                style.outline.ceil() as usize,
+            ))
+        } else {
+            None
+        };
+
+        let mut rgba = vec![0u8; width * height * 4];
+        for (index, pixel) in rgba.chunks_exact_mut(4).enumerate() {
+            let fill_alpha = coverage[index] * style.color[3] as f32 / 255.0;
+            let outline_alpha = outline.as_ref().map_or(0.0, |outline| {
+                outline[index] * style.outline_color[3] as f32 / 255.0
+            });
+
+            // 文字颜色叠加在描边之上
+            let alpha = fill_alpha + outline_alpha * (1.0 - fill_alpha);
+            if alpha <= 0.0 {
+                continue;
+            }
+            for channel in 0..3 {
+                let value = (style.color[channel] as f32 * fill_alpha
+                    + style.outline_color[channel] as f32 * outline_alpha * (1.0 - fill_alpha))
+                    / alpha;
+                pixel[channel] = value.round().clamp(0.0, 255.0) as u8;
+            }
+            pixel[3] = (alpha * 255.0).round() as u8;
+        }
+
+        Some(TextImage {
+            width: width as u32,
+            height: height as u32,
+            rgba,
+        })
+    }
+
+    fn line_width(&self, line: &str, scale: PxScale) -> f32 {
+        let font = self.font.as_scaled(scale);
+        let mut width = 0.0;
+        let mut previous: Option<GlyphId> = None;
+        for ch in line.chars() {
+            let id = font.glyph_id(ch);
+            if let Some(previous) = previous {
+                width += font.kern(previous, id);
+            }
+            width += font.h_advance(id);
+            previous = Some(id);
+        }
+        width
+    }
+}
+
+/// 优先加载指定字体，失败时依次尝试常见系统字体
+pub fn load_font(preferred: Option<&Path>) -> Option<FontVec> {
+    let candidates = preferred
+        .map(Path::to_path_buf)
+        .into_iter()
+        .chain(FALLBACK_FONTS.iter().map(PathBuf::from));
+
+    for path in candidates {
+        let Ok(data) = std::fs::read(&path) else {
+            continue;
+        };
+        match FontVec::try_from_vec_and_index(data, 0) {
+            Ok(font) => {
+                tracing::info!("加载字体: {:?}", path);
+                return Some(font);
+            }
+            Err(e) => tracing::warn!("字体解析失败 {:?}: {}", path, e),
+        }
+    }
+
+    tracing::warn!("没有找到可用的字体，文本叠加层将不可用");
+    None
+}
+
+/// 方形邻域取最大值，用于描边和加粗
+fn dilate(source: &[f32], width: usize, height: usize, radius: usize) -> Vec<f32> {
+    // 先横向再纵向，两次一维膨胀等价于方形膨胀
+    let mut horizontal = vec![0.0f32; source.len()];
+    for y in 0..height {
+        let row = &source[y * width..(y + 1) * width];
+        for x in 0..width {
+            let from = x.saturating_sub(radius);
+            let to = (x + radius + 1).min(width);
+            horizontal[y * width + x] = row[from..to].iter().copied().fold(0.0, f32::max);
+        }
+    }
+
+    let mut result = vec![0.0f32; source.len()];
+    for y in 0..height {
+        let from = y.saturating_sub(radius);
+        let to = (y + radius + 1).min(height);
+        for x in 0..width {
+            result[y * width + x] = (from..to)
+                .map(|row| horizontal[row * width + x])
+                .fold(0.0, f32::max);
+        }
+    }
+    result
+}
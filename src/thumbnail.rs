@@ -0,0 +1,362 @@
+extern crate ffmpeg_next as ffmpeg;
+
+use std::ops::Range;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use ffmpeg::codec::discard::Discard;
+use ffmpeg::format::{context::Input, Pixel};
+use ffmpeg::util::frame::Video;
+
+use crate::clock::format_time;
+use crate::player::{is_attached_picture, seek_input};
+use crate::video::{pts_to_duration, VideoPlaybackThread};
+
+/// JPEG 编码质量
+const JPEG_QUALITY: u8 = 85;
+
+/// 缩略图尺寸，宽高之一为 0 时按画面的显示宽高比计算
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ThumbnailSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for ThumbnailSize {
+    fn default() -> Self {
+        Self {
+            width: 160,
+            height: 0,
+        }
+    }
+}
+
+impl FromStr for ThumbnailSize {
+    type Err = String;
+
+    /// 160x90、160x0 或只写宽度 160
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("无效的缩略图尺寸: {}，例如 160x90 或 160", s);
+        let (width, height) = s.split_once(['x', 'X']).unwrap_or((s, "0"));
+        let size = Self {
+            width: width.trim().parse().map_err(|_| invalid())?,
+            height: height.trim().parse().map_err(|_| invalid())?,
+        };
+        if size.width == 0 && size.height == 0 {
+            return Err(invalid());
+        }
+        Ok(size)
+    }
+}
+
+/// 缩略图的保存格式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    #[default]
+    Jpeg,
+}
+
+impl ImageFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpg",
+        }
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "png" => Ok(ImageFormat::Png),
+            "jpg" | "jpeg" => Ok(ImageFormat::Jpeg),
+            _ => Err(format!("无效的图片格式: {}，可选 png、jpeg", s)),
+        }
+    }
+}
+
+/// 紧密排列的 RGB24 图像
+#[derive(Clone, Debug)]
+pub struct RgbImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl RgbImage {
+    /// 按 format 编码后写入 path
+    pub fn save(&self, path: &Path, format: ImageFormat) -> Result<(), anyhow::Error> {
+        match format {
+            ImageFormat::Png => crate::screenshot::write_png(
+                path,
+                self.width,
+                self.height,
+                png::ColorType::Rgb,
+                &self.pixels,
+            ),
+            ImageFormat::Jpeg => self.write_jpeg(path),
+        }
+    }
+
+    fn write_jpeg(&self, path: &Path) -> Result<(), anyhow::Error> {
+        let (Ok(width), Ok(height)) = (u16::try_from(self.width), u16::try_from(self.height))
+        else {
+            anyhow::bail!("图像尺寸 {}x{} 超出 JPEG 的上限", self.width, self.height);
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let encoder = jpeg_encoder::Encoder::new_file(path, JPEG_QUALITY)?;
+        encoder.encode(&self.pixels, width, height, jpeg_encoder::ColorType::Rgb)?;
+        tracing::info!("图片已保存: {:?} ({}x{})", path, self.width, self.height);
+        Ok(())
+    }
+}
+
+/// 一张缩略图及其对应关键帧的时间
+#[derive(Clone, Debug)]
+pub struct Thumbnail {
+    pub position: Duration,
+    pub image: RgbImage,
+}
+
+/// 把多张缩略图按行排成一张大图，配合 WebVTT 索引用于进度条的悬停预览
+#[derive(Clone, Debug)]
+pub struct Storyboard {
+    pub image: RgbImage,
+    /// 每格覆盖的时间段和在大图中的位置 [x, y, 宽, 高]
+    pub tiles: Vec<(Range<Duration>, [u32; 4])>,
+}
+
+impl Storyboard {
+    /// WebVTT 索引，每条字幕的内容是 `image_url#xywh=x,y,w,h`
+    pub fn webvtt(&self, image_url: &str) -> String {
+        let mut vtt = String::from("WEBVTT\n");
+        for (range, [x, y, width, height]) in &self.tiles {
+            vtt.push_str(&format!(
+                "\n{} --> {}\n{}#xywh={},{},{},{}\n",
+                format_time(range.start),
+                format_time(range.end),
+                image_url,
+                x,
+                y,
+                width,
+                height
+            ));
+        }
+        vtt
+    }
+}
+
+/// 在整个时长上均匀取 count 张缩略图，每张取自各段中点之前最近的关键帧
+pub fn thumbnails(
+    path: &Path,
+    count: usize,
+    size: ThumbnailSize,
+) -> Result<Vec<Thumbnail>, anyhow::Error> {
+    let mut reader = KeyframeReader::open(path)?;
+    let ranges = segments(reader.duration, count)?;
+    let thumbnails = extract(&mut reader, &ranges, size)?;
+    Ok(thumbnails.into_iter().map(|(_, thumbnail)| thumbnail).collect())
+}
+
+/// 取 count 张缩略图拼成每行 columns 格的大图，并记录每格覆盖的时间段
+pub fn storyboard(
+    path: &Path,
+    count: usize,
+    columns: usize,
+    size: ThumbnailSize,
+) -> Result<Storyboard, anyhow::Error> {
+    let mut reader = KeyframeReader::open(path)?;
+    let ranges = segments(reader.duration, count)?;
+    let thumbnails = extract(&mut reader, &ranges, size)?;
+    let tile_width = thumbnails[0].1.image.width;
+    let tile_height = thumbnails[0].1.image.height;
+    let columns = columns.clamp(1, thumbnails.len());
+    let rows = thumbnails.len().div_ceil(columns);
+    let width = tile_width * columns as u32;
+    let height = tile_height * rows as u32;
+
+    let row_bytes = width as usize * 3;
+    let tile_row_bytes = tile_width as usize * 3;
+    let mut pixels = vec![0; row_bytes * height as usize];
+    let mut tiles = Vec::with_capacity(thumbnails.len());
+    for (i, (segment, thumbnail)) in thumbnails.iter().enumerate() {
+        let x = tile_width * (i % columns) as u32;
+        let y = tile_height * (i / columns) as u32;
+        for row in 0..tile_height as usize {
+            let source = &thumbnail.image.pixels[row * tile_row_bytes..][..tile_row_bytes];
+            let offset = (y as usize + row) * row_bytes + x as usize * 3;
+            pixels[offset..offset + tile_row_bytes].copy_from_slice(source);
+        }
+        tiles.push((ranges[*segment].clone(), [x, y, tile_width, tile_height]));
+    }
+
+    Ok(Storyboard {
+        image: RgbImage {
+            width,
+            height,
+            pixels,
+        },
+        tiles,
+    })
+}
+
+/// 逐段解码中点之前最近的关键帧，返回段的序号和缩略图，解码不出关键帧的段被跳过
+fn extract(
+    reader: &mut KeyframeReader,
+    ranges: &[Range<Duration>],
+    size: ThumbnailSize,
+) -> Result<Vec<(usize, Thumbnail)>, anyhow::Error> {
+    let mut thumbnails = Vec::with_capacity(ranges.len());
+    let mut tile_size = None;
+    for (segment, range) in ranges.iter().enumerate() {
+        let target = range.start + (range.end - range.start) / 2;
+        let Some((position, frame)) = reader.frame_at(target)? else {
+            tracing::warn!("{:?} 之前没有可以解码的关键帧", target);
+            continue;
+        };
+        // 所有缩略图使用第一帧算出的尺寸，中途分辨率变化时也能拼成大图
+        let (width, height) = *tile_size.get_or_insert_with(|| scaled_size(&frame, size));
+        let image = to_rgb(&frame, width, height)?;
+        thumbnails.push((segment, Thumbnail { position, image }));
+    }
+    if thumbnails.is_empty() {
+        anyhow::bail!("没有解码出任何关键帧");
+    }
+    Ok(thumbnails)
+}
+
+/// 把时长均分为 count 段
+fn segments(duration: Duration, count: usize) -> Result<Vec<Range<Duration>>, anyhow::Error> {
+    if count == 0 {
+        anyhow::bail!("缩略图数量必须大于 0");
+    }
+    Ok((0..count as u32)
+        .map(|i| duration * i / count as u32..duration * (i + 1) / count as u32)
+        .collect())
+}
+
+/// 只解码关键帧的读取器：跳转到目标之前最近的关键帧，丢弃非关键帧的数据包
+struct KeyframeReader {
+    input: Input,
+    stream_index: usize,
+    decoder: ffmpeg::decoder::Video,
+    time_base: ffmpeg::Rational,
+    duration: Duration,
+}
+
+impl KeyframeReader {
+    fn open(path: &Path) -> Result<Self, anyhow::Error> {
+        let input = ffmpeg::format::input(&path)?;
+        // 封面图只有一帧，不能用来生成缩略图
+        let stream = input
+            .streams()
+            .best(ffmpeg::media::Type::Video)
+            .filter(|stream| !is_attached_picture(stream))
+            .or_else(|| {
+                input.streams().find(|stream| {
+                    stream.parameters().medium() == ffmpeg::media::Type::Video
+                        && !is_attached_picture(stream)
+                })
+            })
+            .ok_or_else(|| anyhow::anyhow!("{:?} 没有视频流", path))?;
+        let stream_index = stream.index();
+        let time_base = stream.time_base();
+
+        // 容器时长以 AV_TIME_BASE（微秒）为单位，未知时为负数
+        let duration = u64::try_from(input.duration())
+            .ok()
+            .filter(|&duration| duration > 0)
+            .map(Duration::from_micros)
+            .ok_or_else(|| anyhow::anyhow!("无法确定 {:?} 的时长", path))?;
+
+        let mut decoder =
+            ffmpeg::codec::Context::from_parameters(stream.parameters())?.decoder();
+        decoder.skip_frame(Discard::NonKey);
+        let decoder = decoder.video()?;
+
+        Ok(Self {
+            input,
+            stream_index,
+            decoder,
+            time_base,
+            duration,
+        })
+    }
+
+    /// 解码 target 之前最近的关键帧，返回该帧的时间；读到文件末尾也没有关键帧时返回 None
+    fn frame_at(&mut self, target: Duration) -> Result<Option<(Duration, Video)>, anyhow::Error> {
+        seek_input(&mut self.input, target)?;
+        self.decoder.flush();
+
+        let mut frame = Video::empty();
+        let mut decoded = false;
+        for (stream, packet) in self.input.packets() {
+            if stream.index() != self.stream_index || !packet.is_key() {
+                continue;
+            }
+            if let Err(e) = self.decoder.send_packet(&packet) {
+                tracing::warn!("关键帧解码失败: {}", e);
+                continue;
+            }
+            if self.decoder.receive_frame(&mut frame).is_ok() {
+                decoded = true;
+                break;
+            }
+        }
+        if !decoded {
+            // 有延迟输出的解码器要送入结束标记才会吐出最后一帧
+            self.decoder.send_eof()?;
+            if self.decoder.receive_frame(&mut frame).is_err() {
+                return Ok(None);
+            }
+        }
+        Ok(Some((self.frame_position(&frame, target), frame)))
+    }
+
+    fn frame_position(&self, frame: &Video, target: Duration) -> Duration {
+        frame
+            .timestamp()
+            .or_else(|| frame.pts())
+            .map_or(target, |pts| pts_to_duration(pts, self.time_base))
+    }
+}
+
+/// 按像素宽高比换算出显示尺寸，再按 size 中缺省的一边等比缩放
+fn scaled_size(frame: &Video, size: ThumbnailSize) -> (u32, u32) {
+    let aspect = frame.aspect_ratio();
+    let pixel_aspect = if aspect.numerator() > 0 && aspect.denominator() > 0 {
+        f64::from(aspect)
+    } else {
+        1.0
+    };
+    let display_aspect =
+        frame.width() as f64 * pixel_aspect / frame.height().max(1) as f64;
+    match (size.width, size.height) {
+        (0, height) => (((height as f64 * display_aspect).round() as u32).max(1), height),
+        (width, 0) => (width, ((width as f64 / display_aspect).round() as u32).max(1)),
+        (width, height) => (width, height),
+    }
+}
+
+/// 经 swscale 缩放并转换为 RGB24，去掉每行末尾的对齐填充
+fn to_rgb(frame: &Video, width: u32, height: u32) -> Result<RgbImage, anyhow::Error> {
+    let rgb_frame = VideoPlaybackThread::scale_frame(frame, Pixel::RGB24, width, height)?;
+    let row_bytes = width as usize * 3;
+    let stride = rgb_frame.stride(0);
+    let data = rgb_frame.data(0);
+    let mut pixels = Vec::with_capacity(row_bytes * height as usize);
+    for row in 0..height as usize {
+        pixels.extend_from_slice(&data[row * stride..row * stride + row_bytes]);
+    }
+    Ok(RgbImage {
+        width,
+        height,
+        pixels,
+    })
+}
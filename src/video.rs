@@ -1,21 +1,41 @@
 extern crate ffmpeg_next as ffmpeg;
 
-use futures::{future::OptionFuture, FutureExt};
+use futures::FutureExt;
 use ffmpeg::{format::Pixel, util::frame::Video as Video};
-use super::player::ControlCommand;
-use num_cpus;
+use super::player::{ControlCommand, PacketMessage};
+use super::clock::PlaybackClock;
+use super::deinterlace::{DeinterlaceMode, DeinterlaceSettings, Field};
+use super::filter::{FilterSpec, VideoFilter};
+use super::stats::{PlaybackStats, VideoStreamInfo};
 use tracing;
 
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 解码线程最多提前多久把帧交给渲染端排队，渲染端按 PTS 决定何时呈现
+const FRAME_LEAD: Duration = Duration::from_millis(100);
+
 pub struct VideoPlaybackThread {
     control_sender: smol::channel::Sender<ControlCommand>,
-    packet_sender: smol::channel::Sender<ffmpeg::codec::packet::packet::Packet>,
+    packet_sender: smol::channel::Sender<PacketMessage>,
     receiver_thread: Option<std::thread::JoinHandle<()>>,
+    stats: Arc<PlaybackStats>,
 }
 
 impl VideoPlaybackThread {
+    /// 输入结束时冲刷解码器，等最后一帧到达显示时间后把 finished 置为 true；
+    /// 输入跳转时等跳转前的最后一帧显示后把播放时钟拨到跳转位置
     pub fn start(
         stream: &ffmpeg::format::stream::Stream,
-        mut video_frame_callback: Box<dyn FnMut(&Video) + Send>,
+        clock: PlaybackClock,
+        finished: Arc<AtomicBool>,
+        stats: Arc<PlaybackStats>,
+        filter_spec: Arc<FilterSpec>,
+        deinterlace: Arc<DeinterlaceSettings>,
+        decoder_threads: usize,
+        mut video_frame_callback: Box<dyn FnMut(&Video, Duration, Option<Field>) + Send>,
     ) -> Result<Self, anyhow::Error> {
         tracing::info!("视频线程启动 - 流信息: {}", stream.duration());
 
@@ -31,43 +51,154 @@ impl VideoPlaybackThread {
             // 设置解码器参数以启用多线程
             decoder.set_threading(ffmpeg::codec::threading::Config {
                 kind: ffmpeg::codec::threading::Type::Frame,
-                count: num_cpus::get() as usize,  // 使用所有可用的 CPU 核心，不需要类型转换
+                count: decoder_threads,
             });
 
             decoder
         };
 
         tracing::info!("视频解码器初始化完成 - {:?}", packet_decoder.format());
+        stats.set_video_info(VideoStreamInfo {
+            codec: stream.parameters().id().name().to_string(),
+            width: packet_decoder.width(),
+            height: packet_decoder.height(),
+            pixel_format: format!("{:?}", packet_decoder.format()),
+            color_space: format!("{:?}", packet_decoder.color_space()),
+            color_range: format!("{:?}", packet_decoder.color_range()),
+            decoder_threads: packet_decoder.threading().count,
+        });
+        let thread_stats = stats.clone();
 
-        let clock = StreamClock::new(stream);
+        let time_base = stream.time_base();
+        // GPU 按场频输出时第二场的显示时间偏移，帧率未知时只显示第一场
+        let frame_rate = stream.avg_frame_rate();
+        let field_duration = (frame_rate.numerator() > 0).then(|| {
+            Duration::from_secs_f64(
+                frame_rate.denominator() as f64 / frame_rate.numerator() as f64 / 2.0,
+            )
+        });
 
         let receiver_thread = std::thread::Builder::new()
             .name("video playback thread".into())
             .spawn(move || {
                 smol::block_on(async move {
+                    let stats = thread_stats;
+                    // A-B 循环的终点，之后的帧不再交付
+                    let segment_end: Cell<Option<Duration>> = Cell::new(None);
+
                     let packet_receiver_impl = async {
+                        let mut frame_filter = FrameFilter::new(filter_spec, deinterlace, time_base, field_duration);
+                        let mut last_pts = Duration::ZERO;
+                        // 跳转后丢弃目标位置之前的帧（从关键帧开始解码出来的多余帧）
+                        let mut skip_until: Option<Duration> = None;
+                        // 已经处理过的时间线拨动次数，与时钟不一致说明有尚未处理的跳转，
+                        // 此时手上的帧都已过期
+                        let mut generation = clock.generation();
                         loop {
-                            let Ok(packet) = packet_receiver.recv().await else {
+                            let Ok(message) = packet_receiver.recv().await else {
                                 tracing::debug!("视频包接收结束");
                                 break;
                             };
 
+                            stats.set_video_packets_queued(packet_receiver.len());
+
                             smol::future::yield_now().await;
 
-                            if let Err(e) = packet_decoder.send_packet(&packet) {
+                            // 从送入数据包到帧转换完成的耗时，不含等待显示时间
+                            let decode_started = Instant::now();
+                            let end_of_stream = matches!(message, PacketMessage::EndOfStream);
+                            let sent = match message {
+                                PacketMessage::Packet(packet) => {
+                                    stats.add_bytes(packet.size());
+                                    packet_decoder.send_packet(&packet)
+                                }
+                                PacketMessage::Discontinuity(position) => {
+                                    tracing::info!("视频跳转到 {:?}", position);
+                                    packet_decoder.flush();
+                                    frame_filter.reset();
+                                    // 等待期间用户跳转时以用户的目标位置为准
+                                    if wait_for_clock(&clock, last_pts, generation).await {
+                                        clock.set_position(position);
+                                        generation += 1;
+                                    }
+                                    last_pts = position;
+                                    skip_until = Some(position);
+                                    continue;
+                                }
+                                // 时钟已由 Player::seek 拨到目标位置，这里只需丢弃旧数据
+                                PacketMessage::Seek(position) => {
+                                    tracing::info!("视频跳转到 {:?}", position);
+                                    packet_decoder.flush();
+                                    frame_filter.reset();
+                                    generation += 1;
+                                    last_pts = position;
+                                    skip_until = Some(position);
+                                    finished.store(false, Ordering::SeqCst);
+                                    continue;
+                                }
+                                PacketMessage::EndOfStream => {
+                                    tracing::info!("视频输入结束，冲刷解码器");
+                                    packet_decoder.send_eof()
+                                }
+                            };
+                            if let Err(e) = sent {
                                 tracing::error!("发送视频包到解码器失败: {}", e);
                                 continue;
                             }
 
                             let mut decoded_frame = Video::empty();
+                            // 解码并经过滤镜处理、等待交付的帧
+                            let mut ready = Vec::new();
 
                             while packet_decoder.receive_frame(&mut decoded_frame).is_ok() {
-                                if let Some(delay) =
-                                    clock.convert_pts_to_instant(decoded_frame.pts())
+                                let frame = std::mem::replace(&mut decoded_frame, Video::empty());
+                                frame_filter.process(frame, &mut ready);
+                            }
+                            if end_of_stream {
+                                frame_filter.finish(&mut ready);
+                            }
+                            // 同一批帧的解码耗时只计入第一帧
+                            let mut decode_time = decode_started.elapsed();
+
+                            for output in ready {
+                                let decoded_frame = output.frame;
+                                let pts = output.pts.unwrap_or_else(|| clock.position());
+
+                                if skip_until.is_some_and(|start| pts < start) {
+                                    tracing::debug!("丢弃跳转目标之前的帧: {:?}", pts);
+                                    continue;
+                                }
+                                // 暂停期间跳转时时钟停在目标位置之前，拨到第一帧让它立即呈现
+                                if skip_until.take().is_some()
+                                    && clock.set_paused_position(pts, generation)
                                 {
+                                    generation += 1;
+                                }
+                                if segment_end.get().is_some_and(|end| pts >= end) {
+                                    tracing::debug!("丢弃循环终点之后的帧: {:?}", pts);
+                                    continue;
+                                }
+
+                                // 等到帧接近显示时间再交付，避免渲染端队列无限增长；
+                                // 等待期间发生跳转时丢弃该帧
+                                let mut stale = false;
+                                loop {
+                                    if clock.generation() != generation {
+                                        stale = true;
+                                        break;
+                                    }
+                                    let position = clock.position();
+                                    if pts <= position + FRAME_LEAD {
+                                        break;
+                                    }
+                                    let delay = (pts - position - FRAME_LEAD).min(FRAME_LEAD);
                                     tracing::debug!("视频帧延迟: {:?}", delay);
                                     smol::Timer::after(delay).await;
                                 }
+                                if stale {
+                                    tracing::debug!("丢弃跳转前的帧: {:?}", pts);
+                                    continue;
+                                }
 
                                 tracing::debug!(
                                     "解码视频帧 - PTS: {:?}, 格式: {:?}",
@@ -75,24 +206,32 @@ impl VideoPlaybackThread {
                                     decoded_frame.format()
                                 );
 
+                                let convert_started = Instant::now();
                                 let frame = Self::rescaler_for_frame(&decoded_frame);
-                                video_frame_callback(&frame);
+                                stats.record_decode_time(decode_time + convert_started.elapsed());
+                                decode_time = Duration::ZERO;
+                                if pts < clock.position() {
+                                    stats.add_late_frame();
+                                }
+                                video_frame_callback(&frame, pts, output.field);
+                                last_pts = pts;
+                            }
+
+                            if end_of_stream {
+                                if wait_for_clock(&clock, last_pts, generation).await {
+                                    tracing::info!("视频播放结束");
+                                    finished.store(true, Ordering::SeqCst);
+                                }
                             }
                         }
                     }
                     .fuse()
                     .shared();
 
-                    let mut playing = true;
-
+                    // 暂停时也继续解码，帧的交付由暂停的时钟挡住；
+                    // 这样暂停期间跳转后仍能解码并呈现目标位置的画面
                     loop {
-                        let packet_receiver: OptionFuture<_> = if playing {
-                            Some(packet_receiver_impl.clone())
-                        } else {
-                            None
-                        }
-                        .into();
-
+                        let packet_receiver = packet_receiver_impl.clone();
                         smol::pin!(packet_receiver);
 
                         futures::select! {
@@ -101,11 +240,15 @@ impl VideoPlaybackThread {
                                 match received_command {
                                     Ok(ControlCommand::Pause) => {
                                         tracing::info!("视频播放暂停");
-                                        playing = false;
                                     }
                                     Ok(ControlCommand::Play) => {
                                         tracing::info!("视频播放开始");
-                                        playing = true;
+                                    }
+                                    Ok(ControlCommand::SetLoop(mode)) => {
+                                        segment_end.set(mode.segment_end());
+                                    }
+                                    Ok(command) => {
+                                        tracing::debug!("视频线程忽略控制命令: {:?}", command);
                                     }
                                     Err(e) => {
                                         tracing::error!("视频控制通道关闭: {}", e);
@@ -122,13 +265,19 @@ impl VideoPlaybackThread {
             control_sender,
             packet_sender,
             receiver_thread: Some(receiver_thread),
+            stats,
         })
     }
 
     pub async fn receive_packet(&self, packet: ffmpeg::codec::packet::packet::Packet) -> bool {
-        match self.packet_sender.send(packet).await {
+        self.send_packet_message(PacketMessage::Packet(packet)).await
+    }
+
+    pub async fn send_packet_message(&self, message: PacketMessage) -> bool {
+        match self.packet_sender.send(message).await {
             Ok(_) => {
                 tracing::debug!("视频包发送成功");
+                self.stats.set_video_packets_queued(self.packet_sender.len());
                 true
             }
             Err(e) => {
@@ -147,21 +296,30 @@ impl VideoPlaybackThread {
 
     // 缩放视频帧
     pub fn rescaler_for_frame(frame: &Video) -> Video {
-        // 创建新的视频帧，保持原始尺寸和格式
+        // 保持原始尺寸，只转换为渲染器使用的 YUV420P
+        Self::scale_frame(frame, Pixel::YUV420P, frame.width(), frame.height()).unwrap()
+    }
+
+    /// 用 swscale 把视频帧转换为 format 格式并缩放到 width x height
+    pub fn scale_frame(
+        frame: &Video,
+        format: Pixel,
+        width: u32,
+        height: u32,
+    ) -> Result<Video, ffmpeg::Error> {
         let mut new_frame = Video::empty();
         let mut context = ffmpeg_next::software::scaling::Context::get(
             frame.format(),
             frame.width(),
             frame.height(),
-            Pixel::YUV420P,
-            frame.width(),  // 使用原始宽度
-            frame.height(), // 使用原始高度
+            format,
+            width,
+            height,
             ffmpeg::software::scaling::Flags::BILINEAR,
-        )
-        .unwrap();
+        )?;
 
-        context.run(&frame, &mut new_frame).unwrap();
-        new_frame
+        context.run(frame, &mut new_frame)?;
+        Ok(new_frame)
     }
 }
 
@@ -175,31 +333,182 @@ impl Drop for VideoPlaybackThread {
     }
 }
 
-struct StreamClock {
-    time_base_seconds: f64,
-    start_time: std::time::Instant,
+/// 等待播放时钟走到 target，暂停时一直等待；
+/// 等待期间时间线被拨动（用户跳转）时提前返回 false
+async fn wait_for_clock(clock: &PlaybackClock, target: Duration, generation: u64) -> bool {
+    loop {
+        if clock.generation() != generation {
+            return false;
+        }
+        let position = clock.position();
+        if position >= target {
+            return true;
+        }
+        smol::Timer::after((target - position).min(FRAME_LEAD)).await;
+    }
 }
 
-impl StreamClock {
-    fn new(stream: &ffmpeg::format::stream::Stream) -> Self {
-        let time_base_seconds = stream.time_base();
-        let time_base_seconds =
-            time_base_seconds.numerator() as f64 / time_base_seconds.denominator() as f64;
+/// 滤镜链输出、等待交付的帧
+struct FilteredFrame {
+    frame: Video,
+    pts: Option<Duration>,
+    /// 需要在 GPU 上去隔行时显示的场
+    field: Option<Field>,
+}
 
-        let start_time = std::time::Instant::now();
+/// 解码线程里的滤镜状态：滤镜链描述、去隔行状态或输入帧参数变化时重建滤镜图，
+/// 跳转后丢弃滤镜内部缓存的旧帧
+struct FrameFilter {
+    spec: Arc<FilterSpec>,
+    deinterlace: Arc<DeinterlaceSettings>,
+    /// 当前滤镜图对应的描述版本，None 表示需要重建
+    generation: Option<u64>,
+    /// 当前滤镜图是否包含 CPU 去隔行滤镜
+    cpu_deinterlace: bool,
+    /// 自动模式下是否已经遇到过隔行帧，遇到后滤镜图一直保留去隔行滤镜，
+    /// 由 deint=interlaced 让逐行帧原样通过，避免隔行标记时有时无时反复重建
+    interlaced_seen: bool,
+    filter: Option<VideoFilter>,
+    /// 解码帧 PTS 的时间基
+    time_base: ffmpeg::Rational,
+    field_duration: Option<Duration>,
+}
 
+impl FrameFilter {
+    fn new(
+        spec: Arc<FilterSpec>,
+        deinterlace: Arc<DeinterlaceSettings>,
+        time_base: ffmpeg::Rational,
+        field_duration: Option<Duration>,
+    ) -> Self {
         Self {
-            time_base_seconds,
-            start_time,
+            spec,
+            deinterlace,
+            generation: None,
+            cpu_deinterlace: false,
+            interlaced_seen: false,
+            filter: None,
+            time_base,
+            field_duration,
         }
     }
 
-    fn convert_pts_to_instant(&self, pts: Option<i64>) -> Option<std::time::Duration> {
-        pts.and_then(|pts| {
-            let pts_since_start =
-                std::time::Duration::from_secs_f64(pts as f64 * self.time_base_seconds);
-            self.start_time.checked_add(pts_since_start)
-        })
-        .map(|absolute_pts| absolute_pts.duration_since(std::time::Instant::now()))
+    /// 送入一帧解码帧，把可以交付的帧追加到 output；
+    /// 滤镜图创建失败时不做处理直接交付
+    fn process(&mut self, frame: Video, output: &mut Vec<FilteredFrame>) {
+        let interlaced = frame.is_interlaced();
+        if interlaced && !self.interlaced_seen {
+            let order = if frame.is_top_first() { "顶场优先" } else { "底场优先" };
+            tracing::info!("检测到隔行视频，场序: {}", order);
+            self.interlaced_seen = true;
+        }
+        let mode = self.deinterlace.mode();
+        let deinterlacer = self.deinterlace.deinterlacer;
+        let cpu_deinterlace = !deinterlacer.is_gpu()
+            && match mode {
+                DeinterlaceMode::Auto => self.interlaced_seen,
+                DeinterlaceMode::On => true,
+                DeinterlaceMode::Off => false,
+            };
+        // GPU 去隔行由渲染器按场采样，这里只需要决定显示哪一场
+        let gpu_deinterlace = deinterlacer.is_gpu()
+            && match mode {
+                DeinterlaceMode::Auto => interlaced,
+                DeinterlaceMode::On => true,
+                DeinterlaceMode::Off => false,
+            };
+        let field = gpu_deinterlace.then(|| {
+            if interlaced && !frame.is_top_first() {
+                Field::Bottom
+            } else {
+                Field::Top
+            }
+        });
+
+        let generation = self.spec.generation();
+        let stale = self.filter.as_ref().is_some_and(|filter| !filter.accepts(&frame));
+        if self.generation != Some(generation) || self.cpu_deinterlace != cpu_deinterlace || stale {
+            self.generation = Some(generation);
+            self.cpu_deinterlace = cpu_deinterlace;
+            let mut filters = Vec::new();
+            if cpu_deinterlace {
+                let only_interlaced = mode == DeinterlaceMode::Auto;
+                filters.extend(deinterlacer.filter_spec(self.deinterlace.field_rate, only_interlaced));
+            }
+            let spec = self.spec.get();
+            if !spec.is_empty() {
+                filters.push(spec);
+            }
+            let spec = filters.join(",");
+            self.filter = if spec.is_empty() {
+                None
+            } else {
+                VideoFilter::new(&spec, &frame, self.time_base)
+                    .map_err(|e| tracing::error!("创建视频滤镜 {} 失败: {}", spec, e))
+                    .ok()
+            };
+        }
+
+        let first = output.len();
+        match &mut self.filter {
+            Some(filter) => {
+                if let Err(e) = filter.push(&frame) {
+                    tracing::error!("视频帧送入滤镜失败: {}", e);
+                }
+                self.drain(output);
+            }
+            None => {
+                let pts = frame.pts().map(|pts| pts_to_duration(pts, self.time_base));
+                output.push(FilteredFrame { frame, pts, field: None });
+            }
+        }
+
+        let Some(field) = field else {
+            return;
+        };
+        // GPU 去隔行：先显示第一场，按场频输出时半帧之后再显示第二场
+        let second_field = self.field_duration.filter(|_| self.deinterlace.field_rate);
+        let frames: Vec<FilteredFrame> = output.drain(first..).collect();
+        for mut filtered in frames {
+            filtered.field = Some(field);
+            let second = second_field.zip(filtered.pts).map(|(offset, pts)| FilteredFrame {
+                frame: filtered.frame.clone(),
+                pts: Some(pts + offset),
+                field: Some(field.other()),
+            });
+            output.push(filtered);
+            output.extend(second);
+        }
+    }
+
+    /// 输入结束，取出滤镜内部缓存的剩余帧
+    fn finish(&mut self, output: &mut Vec<FilteredFrame>) {
+        if let Some(filter) = &mut self.filter {
+            if let Err(e) = filter.flush() {
+                tracing::error!("冲刷视频滤镜失败: {}", e);
+            }
+        }
+        self.drain(output);
     }
+
+    fn reset(&mut self) {
+        self.filter = None;
+        self.generation = None;
+    }
+
+    fn drain(&mut self, output: &mut Vec<FilteredFrame>) {
+        let Some(filter) = &mut self.filter else {
+            return;
+        };
+        let time_base = filter.output_time_base();
+        while let Some(frame) = filter.pull() {
+            let pts = frame.pts().map(|pts| pts_to_duration(pts, time_base));
+            output.push(FilteredFrame { frame, pts, field: None });
+        }
+    }
+}
+
+pub(crate) fn pts_to_duration(pts: i64, time_base: ffmpeg::Rational) -> Duration {
+    let time_base_seconds = time_base.numerator() as f64 / time_base.denominator() as f64;
+    Duration::from_secs_f64((pts as f64 * time_base_seconds).max(0.0))
 }
@@ -0,0 +1,251 @@
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// 采样分接最多保留的采样数，48 kHz 时约 2.7 秒，足够覆盖环形缓冲区和设备的延迟
+const TAP_CAPACITY: usize = 1 << 17;
+/// 频谱的频率范围，频段按对数均匀划分
+const MIN_FREQUENCY: f64 = 30.0;
+const MAX_FREQUENCY: f64 = 16_000.0;
+/// 频谱显示的动态范围，低于该值的频段画成空条
+const SPECTRUM_FLOOR_DB: f32 = -70.0;
+/// 幅度达到该值视为削波
+pub const CLIP_LEVEL: f32 = 0.999;
+/// 峰值低于该值（约 -60 dBFS）视为静音
+pub const SILENCE_LEVEL: f32 = 0.001;
+
+/// 音频可视化方式，W 键按 频谱 -> 波形 -> 关 循环
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VisualizationMode {
+    Off,
+    /// FFT 频谱柱状图
+    #[default]
+    Spectrum,
+    /// 示波器式波形
+    Waveform,
+}
+
+impl VisualizationMode {
+    pub fn next(self) -> Self {
+        match self {
+            VisualizationMode::Spectrum => VisualizationMode::Waveform,
+            VisualizationMode::Waveform => VisualizationMode::Off,
+            VisualizationMode::Off => VisualizationMode::Spectrum,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            VisualizationMode::Off => "关",
+            VisualizationMode::Spectrum => "频谱",
+            VisualizationMode::Waveform => "波形",
+        }
+    }
+}
+
+impl FromStr for VisualizationMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "off" | "no" => Ok(VisualizationMode::Off),
+            "spectrum" | "fft" => Ok(VisualizationMode::Spectrum),
+            "waveform" | "wave" => Ok(VisualizationMode::Waveform),
+            _ => Err(format!("无效的可视化方式: {}，可选 spectrum、waveform、off", s)),
+        }
+    }
+}
+
+/// 以正在播出的位置为终点截取的一段单声道采样
+#[derive(Clone, Debug)]
+pub struct AudioWindow {
+    pub samples: Vec<f32>,
+    /// 各声道中绝对值最大的采样，用于判断削波
+    pub peak: f32,
+    /// 每秒媒体时间对应的采样数，变速后与声卡采样率不同
+    pub sample_rate: f64,
+}
+
+impl AudioWindow {
+    pub fn is_clipping(&self) -> bool {
+        self.peak >= CLIP_LEVEL
+    }
+
+    pub fn is_silent(&self) -> bool {
+        self.peak < SILENCE_LEVEL
+    }
+}
+
+/// 音频线程重采样后的采样分接：按媒体时间保存最近的一段单声道采样，
+/// 渲染循环按播放时钟取出与正在播出的声音对齐的一段用于绘制
+pub struct AudioTap {
+    state: Mutex<TapState>,
+}
+
+#[derive(Default)]
+struct TapState {
+    /// 各声道的平均值
+    samples: VecDeque<f32>,
+    /// 各声道中绝对值最大的值
+    peaks: VecDeque<f32>,
+    /// 最后一个采样结束时的媒体时间
+    end: Duration,
+    sample_rate: f64,
+}
+
+impl AudioTap {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(TapState::default()),
+        }
+    }
+
+    /// 追加一段交错排列的采样，end 为这段采样结束时的媒体时间，None 时接在上一段之后
+    pub fn push(
+        &self,
+        interleaved: &[f32],
+        channels: usize,
+        end: Option<Duration>,
+        sample_rate: f64,
+    ) {
+        if channels == 0 || sample_rate <= 0.0 {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        let frames = interleaved.len() / channels;
+        state.end = match end {
+            Some(end) => end,
+            None => state.end + Duration::from_secs_f64(frames as f64 / sample_rate),
+        };
+        state.sample_rate = sample_rate;
+        for frame in interleaved.chunks_exact(channels) {
+            let sum: f32 = frame.iter().sum();
+            let peak = frame.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+            state.samples.push_back(sum / channels as f32);
+            state.peaks.push_back(peak);
+        }
+        let excess = state.samples.len().saturating_sub(TAP_CAPACITY);
+        state.samples.drain(..excess);
+        state.peaks.drain(..excess);
+    }
+
+    /// 跳转后丢弃旧位置的采样
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.samples.clear();
+        state.peaks.clear();
+    }
+
+    /// 截取在 position 结束的 len 个采样，超出保存范围的部分补零；没有任何采样时返回 None
+    pub fn window(&self, position: Duration, len: usize) -> Option<AudioWindow> {
+        let state = self.state.lock().unwrap();
+        if state.samples.is_empty() {
+            return None;
+        }
+        // 分接领先于播出的位置，末尾还没播出的采样不取
+        let ahead = state.end.saturating_sub(position).as_secs_f64() * state.sample_rate;
+        let last = state.samples.len() as i64 - ahead.round() as i64;
+        let first = last - len as i64;
+        let mut samples = vec![0.0; len];
+        let mut peak = 0.0f32;
+        for (offset, sample) in samples.iter_mut().enumerate() {
+            let index = first + offset as i64;
+            if index >= 0 && (index as usize) < state.samples.len() {
+                *sample = state.samples[index as usize];
+                peak = peak.max(state.peaks[index as usize]);
+            }
+        }
+        Some(AudioWindow {
+            samples,
+            peak,
+            sample_rate: state.sample_rate,
+        })
+    }
+}
+
+impl Default for AudioTap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 把一段采样分析为 bands 个对数频段的电平，0 表示低于显示范围，1 表示满幅正弦；
+/// 采样数需要是 2 的幂
+pub fn spectrum(window: &AudioWindow, bands: usize) -> Vec<f32> {
+    let len = window.samples.len();
+    if len < 2 || !len.is_power_of_two() || bands == 0 {
+        return vec![0.0; bands];
+    }
+
+    // Hann 窗降低频谱泄漏
+    let mut re: Vec<f32> = window
+        .samples
+        .iter()
+        .enumerate()
+        .map(|(i, sample)| {
+            let hann = 0.5 - 0.5 * (2.0 * PI * i as f32 / (len - 1) as f32).cos();
+            sample * hann
+        })
+        .collect();
+    let mut im = vec![0.0; len];
+    fft(&mut re, &mut im);
+
+    // 加 Hann 窗后满幅正弦所在频点的模约为 N/4
+    let scale = 4.0 / len as f32;
+    let magnitudes: Vec<f32> = re[..len / 2]
+        .iter()
+        .zip(&im[..len / 2])
+        .map(|(re, im)| (re * re + im * im).sqrt() * scale)
+        .collect();
+
+    let nyquist = window.sample_rate / 2.0;
+    let max_frequency = MAX_FREQUENCY.min(nyquist);
+    let bin_width = window.sample_rate / len as f64;
+    let ratio = (max_frequency / MIN_FREQUENCY).max(1.0);
+    (0..bands)
+        .map(|band| {
+            let low = MIN_FREQUENCY * ratio.powf(band as f64 / bands as f64);
+            let high = MIN_FREQUENCY * ratio.powf((band + 1) as f64 / bands as f64);
+            let first = ((low / bin_width).floor() as usize).clamp(1, magnitudes.len() - 1);
+            let last = ((high / bin_width).ceil() as usize).clamp(first + 1, magnitudes.len());
+            let magnitude = magnitudes[first..last].iter().fold(0.0f32, |max, &m| max.max(m));
+            let db = 20.0 * magnitude.max(1e-10).log10();
+            ((db - SPECTRUM_FLOOR_DB) / -SPECTRUM_FLOOR_DB).clamp(0.0, 1.0)
+        })
+        .collect()
+}
+
+/// 原地基 2 快速傅里叶变换
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let len = re.len();
+    let bits = len.trailing_zeros();
+    for i in 0..len {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut size = 2;
+    while size <= len {
+        let half = size / 2;
+        let step = -2.0 * PI / size as f32;
+        for start in (0..len).step_by(size) {
+            for k in 0..half {
+                let (sin, cos) = (step * k as f32).sin_cos();
+                let a = start + k;
+                let b = a + half;
+                let t_re = re[b] * cos - im[b] * sin;
+                let t_im = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        size *= 2;
+    }
+}
@@ -0,0 +1,206 @@
+use std::time::Instant;
+
+use glium::{
+    implement_vertex, index::NoIndices, index::PrimitiveType, uniforms::EmptyUniforms, Blend,
+    Display, DrawParameters, Frame, Program, Surface, VertexBuffer,
+};
+
+use crate::visualization::{self, AudioWindow, VisualizationMode, CLIP_LEVEL};
+
+/// 每次分析的采样数，48 kHz 时约 43 ms
+pub const WINDOW_SAMPLES: usize = 2048;
+/// 频谱柱的数量
+const SPECTRUM_BANDS: usize = 48;
+/// 柱子之间的空隙占柱宽的比例
+const BAR_GAP: f32 = 0.2;
+/// 频谱柱每秒最多回落的高度（占满高的比例），上升不做平滑
+const BAR_FALL_PER_SECOND: f32 = 1.5;
+/// 波形按列绘制，每列画出该段采样的最小值到最大值
+const WAVEFORM_COLUMNS: usize = 512;
+/// 波形和静音线的最小厚度（标准化设备坐标）
+const MIN_THICKNESS: f32 = 0.004;
+/// 削波指示条占绘制区域高度的比例
+const CLIP_BAR_SCALE: f32 = 0.02;
+
+const BAR_COLOR: [f32; 4] = [0.35, 0.75, 1.0, 0.9];
+const CLIP_COLOR: [f32; 4] = [1.0, 0.25, 0.2, 0.95];
+const SILENCE_COLOR: [f32; 4] = [0.6, 0.6, 0.6, 0.6];
+const BACKGROUND_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.45];
+
+#[derive(Copy, Clone, Debug)]
+struct VisualizationVertex {
+    position: [f32; 2],
+    color: [f32; 4],
+}
+
+implement_vertex!(VisualizationVertex, position, color);
+
+/// 音频可视化绘制通道：根据正在播出的采样画频谱或波形，削波时在顶部显示红色指示条，
+/// 静音时只画一条灰线
+pub struct Visualizer {
+    program: Program,
+    mode: VisualizationMode,
+    window: Option<AudioWindow>,
+    /// 经过回落平滑的频谱电平
+    bars: Vec<f32>,
+    last_update: Option<Instant>,
+}
+
+impl Visualizer {
+    pub fn new(display: &Display, mode: VisualizationMode) -> Self {
+        let vertex_shader_src = include_str!("shaders/visualization_vertex_shader.glsl");
+        let fragment_shader_src = include_str!("shaders/visualization_fragment_shader.glsl");
+        let program = Program::from_source(display, vertex_shader_src, fragment_shader_src, None)
+            .expect("Failed to create visualization shader program");
+
+        Self {
+            program,
+            mode,
+            window: None,
+            bars: vec![0.0; SPECTRUM_BANDS],
+            last_update: None,
+        }
+    }
+
+    pub fn mode(&self) -> VisualizationMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: VisualizationMode) {
+        self.mode = mode;
+        self.bars.fill(0.0);
+        self.last_update = None;
+    }
+
+    /// 用新截取的采样更新画面，window 为 None 表示还没有声音
+    pub fn update(&mut self, window: Option<AudioWindow>) {
+        let now = Instant::now();
+        let elapsed = self
+            .last_update
+            .map_or(0.0, |last| now.duration_since(last).as_secs_f32());
+        self.last_update = Some(now);
+
+        if self.mode == VisualizationMode::Spectrum {
+            let levels = match &window {
+                Some(window) => visualization::spectrum(window, SPECTRUM_BANDS),
+                None => vec![0.0; SPECTRUM_BANDS],
+            };
+            let fall = BAR_FALL_PER_SECOND * elapsed;
+            for (bar, level) in self.bars.iter_mut().zip(levels) {
+                *bar = level.max(*bar - fall);
+            }
+        }
+        self.window = window;
+    }
+
+    /// 在标准化设备坐标矩形 [left, bottom, right, top] 内绘制，
+    /// background 为 true 时先铺一层半透明底色，用于叠加在封面或视频上
+    pub fn draw(&self, display: &Display, target: &mut Frame, rect: [f32; 4], background: bool) {
+        if self.mode == VisualizationMode::Off {
+            return;
+        }
+
+        let mut vertices = Vec::new();
+        if background {
+            push_rect(&mut vertices, rect, BACKGROUND_COLOR);
+        }
+        let [left, bottom, right, top] = rect;
+        match &self.window {
+            Some(window) if !window.is_silent() => {
+                match self.mode {
+                    VisualizationMode::Spectrum => self.layout_spectrum(&mut vertices, rect, window),
+                    VisualizationMode::Waveform => layout_waveform(&mut vertices, rect, window),
+                    VisualizationMode::Off => {}
+                }
+                if window.is_clipping() {
+                    let height = (top - bottom) * CLIP_BAR_SCALE;
+                    push_rect(&mut vertices, [left, top - height, right, top], CLIP_COLOR);
+                }
+            }
+            // 静音或还没有声音时只画一条线，一眼就能看出没有信号
+            _ => {
+                let baseline = match self.mode {
+                    VisualizationMode::Waveform => (bottom + top) / 2.0,
+                    _ => bottom,
+                };
+                push_rect(
+                    &mut vertices,
+                    [left, baseline, right, baseline + MIN_THICKNESS],
+                    SILENCE_COLOR,
+                );
+            }
+        }
+
+        let vertex_buffer = VertexBuffer::new(display, &vertices)
+            .expect("Failed to create visualization vertex buffer");
+        let parameters = DrawParameters {
+            blend: Blend::alpha_blending(),
+            ..Default::default()
+        };
+        target
+            .draw(
+                &vertex_buffer,
+                NoIndices(PrimitiveType::TrianglesList),
+                &self.program,
+                &EmptyUniforms,
+                &parameters,
+            )
+            .unwrap();
+    }
+
+    fn layout_spectrum(
+        &self,
+        vertices: &mut Vec<VisualizationVertex>,
+        rect: [f32; 4],
+        window: &AudioWindow,
+    ) {
+        let [left, bottom, right, top] = rect;
+        let slot = (right - left) / self.bars.len() as f32;
+        let width = slot * (1.0 - BAR_GAP);
+        let color = if window.is_clipping() { CLIP_COLOR } else { BAR_COLOR };
+        for (i, level) in self.bars.iter().enumerate() {
+            let x = left + slot * i as f32 + (slot - width) / 2.0;
+            let height = (top - bottom) * level;
+            push_rect(vertices, [x, bottom, x + width, bottom + height.max(MIN_THICKNESS)], color);
+        }
+    }
+}
+
+/// 示波器式波形，削波的列标成红色
+fn layout_waveform(vertices: &mut Vec<VisualizationVertex>, rect: [f32; 4], window: &AudioWindow) {
+    let [left, bottom, right, top] = rect;
+    let center = (bottom + top) / 2.0;
+    let half_height = (top - bottom) / 2.0;
+    let columns = WAVEFORM_COLUMNS.min(window.samples.len()).max(1);
+    let column_width = (right - left) / columns as f32;
+    let samples_per_column = window.samples.len() / columns;
+    for (i, chunk) in window.samples.chunks(samples_per_column.max(1)).take(columns).enumerate() {
+        let (min, max) = chunk
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(min, max), &sample| (min.min(sample), max.max(sample)));
+        let low = center + min.clamp(-1.0, 1.0) * half_height;
+        let high = (center + max.clamp(-1.0, 1.0) * half_height).max(low + MIN_THICKNESS);
+        let clipped = min <= -CLIP_LEVEL || max >= CLIP_LEVEL;
+        let color = if clipped { CLIP_COLOR } else { BAR_COLOR };
+        let x = left + column_width * i as f32;
+        push_rect(vertices, [x, low, x + column_width, high], color);
+    }
+}
+
+/// 两个三角形组成的矩形 [left, bottom, right, top]
+fn push_rect(vertices: &mut Vec<VisualizationVertex>, rect: [f32; 4], color: [f32; 4]) {
+    let [left, bottom, right, top] = rect;
+    let corners = [
+        [left, bottom],
+        [right, bottom],
+        [right, top],
+        [left, bottom],
+        [right, top],
+        [left, top],
+    ];
+    vertices.extend(
+        corners
+            .into_iter()
+            .map(|position| VisualizationVertex { position, color }),
+    );
+}
@@ -0,0 +1,206 @@
+//! 用真实的 TCP 连接测试 HTTP 遥控服务器的路由和错误响应
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use player::http::HttpServer;
+use player::ipc::{IpcCommand, LoadMode, SeekMode};
+use serde_json::{json, Value};
+
+struct Reply {
+    status: u16,
+    body: String,
+    commands: Vec<IpcCommand>,
+}
+
+fn start(token: Option<&str>) -> HttpServer {
+    HttpServer::start("127.0.0.1:0".parse().unwrap(), token.map(str::to_string)).unwrap()
+}
+
+fn host(server: &HttpServer) -> String {
+    format!("127.0.0.1:{}", server.local_address().port())
+}
+
+/// 组装请求，headers 之外自动加上 Host 和 Content-Length
+fn request(server: &HttpServer, method: &str, path: &str, headers: &[&str], body: &str) -> String {
+    let mut request = format!("{} {} HTTP/1.1\r\nHost: {}\r\n", method, path, host(server));
+    for header in headers {
+        request.push_str(header);
+        request.push_str("\r\n");
+    }
+    request.push_str(&format!("Content-Length: {}\r\n\r\n{}", body.len(), body));
+    request
+}
+
+fn post_json(server: &HttpServer, path: &str, body: &str) -> String {
+    request(server, "POST", path, &["Content-Type: application/json"], body)
+}
+
+/// 在另一个线程里发送请求，同时像渲染循环那样取出命令并回复
+fn send(server: &HttpServer, raw: String) -> Reply {
+    let address = server.local_address();
+    let client = std::thread::spawn(move || {
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+        stream.write_all(raw.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    });
+
+    let mut commands = Vec::new();
+    while !client.is_finished() {
+        while let Some(request) = server.try_recv() {
+            let result = match &request.command {
+                IpcCommand::GetProperty(name) if name == "volume" => Ok(json!(80.0)),
+                _ => Ok(Value::Null),
+            };
+            commands.push(request.command.clone());
+            request.reply(result);
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+
+    let response = client.join().unwrap();
+    let status = response.split_whitespace().nth(1).unwrap().parse().unwrap();
+    let body = response.split_once("\r\n\r\n").unwrap().1.to_string();
+    Reply { status, body, commands }
+}
+
+#[test]
+fn serves_remote_page() {
+    let server = start(None);
+    let reply = send(&server, request(&server, "GET", "/", &[], ""));
+    assert_eq!(reply.status, 200);
+    assert!(reply.body.contains("<html"));
+    assert!(reply.commands.is_empty());
+}
+
+#[test]
+fn routes_commands_to_the_render_loop() {
+    let server = start(None);
+
+    let reply = send(&server, post_json(&server, "/api/pause", ""));
+    assert_eq!(reply.status, 200);
+    assert_eq!(reply.commands, [IpcCommand::Pause]);
+
+    let reply = send(&server, post_json(&server, "/api/seek", r#"{"seconds": 10}"#));
+    assert_eq!(reply.status, 200);
+    assert_eq!(
+        reply.commands,
+        [IpcCommand::Seek { seconds: 10.0, mode: SeekMode::Absolute }]
+    );
+
+    let body = r#"{"url": "/tmp/next.mkv", "mode": "append"}"#;
+    let reply = send(&server, post_json(&server, "/api/open", body));
+    assert_eq!(reply.status, 200);
+    assert_eq!(
+        reply.commands,
+        [IpcCommand::LoadFile { path: "/tmp/next.mkv".into(), mode: LoadMode::Append }]
+    );
+}
+
+#[test]
+fn status_collects_properties() {
+    let server = start(None);
+    let reply = send(&server, request(&server, "GET", "/api/status", &[], ""));
+    assert_eq!(reply.status, 200);
+    assert_eq!(reply.commands.len(), 9);
+    let body: Value = serde_json::from_str(&reply.body).unwrap();
+    assert_eq!(body["data"]["volume"], json!(80.0));
+}
+
+#[test]
+fn rejects_bad_requests() {
+    let server = start(None);
+
+    let reply = send(&server, post_json(&server, "/api/seek", "{"));
+    assert_eq!(reply.status, 400);
+    let reply = send(&server, post_json(&server, "/api/seek", r#"{"mode": "absolute"}"#));
+    assert_eq!(reply.status, 400);
+    let reply = send(&server, post_json(&server, "/api/seek", r#"{"seconds": 1, "mode": "x"}"#));
+    assert_eq!(reply.status, 400);
+    let reply = send(&server, "garbage\r\n\r\n".to_string());
+    assert_eq!(reply.status, 400);
+
+    let reply = send(&server, post_json(&server, "/api/unknown", "{}"));
+    assert_eq!(reply.status, 404);
+    let reply = send(&server, request(&server, "GET", "/missing", &[], ""));
+    assert_eq!(reply.status, 404);
+
+    let reply = send(&server, request(&server, "GET", "/api/seek", &[], ""));
+    assert_eq!(reply.status, 405);
+    let reply = send(&server, request(&server, "DELETE", "/", &[], ""));
+    assert_eq!(reply.status, 405);
+
+    let raw = format!(
+        "POST /api/seek HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+         Content-Length: 1000000\r\n\r\n",
+        host(&server)
+    );
+    let reply = send(&server, raw);
+    assert_eq!(reply.status, 413);
+
+    assert!(reply.commands.is_empty());
+}
+
+#[test]
+fn rejects_cross_site_requests() {
+    let server = start(None);
+
+    // text/plain 的简单请求不经过 CORS 预检
+    let headers = ["Content-Type: text/plain"];
+    let raw = request(&server, "POST", "/api/open", &headers, r#"{"url": "/etc/passwd"}"#);
+    let reply = send(&server, raw);
+    assert_eq!(reply.status, 415);
+    assert!(reply.commands.is_empty());
+
+    let port = server.local_address().port();
+    let raw = format!(
+        "POST /api/pause HTTP/1.1\r\nHost: evil.example:{}\r\nContent-Type: application/json\r\n\
+         Content-Length: 0\r\n\r\n",
+        port
+    );
+    let reply = send(&server, raw);
+    assert_eq!(reply.status, 403);
+    assert!(reply.commands.is_empty());
+
+    let headers = ["Content-Type: application/json", "Origin: http://evil.example"];
+    let reply = send(&server, request(&server, "POST", "/api/pause", &headers, ""));
+    assert_eq!(reply.status, 403);
+    assert!(reply.commands.is_empty());
+
+    let origin = format!("Origin: http://{}", host(&server));
+    let headers = ["Content-Type: application/json", origin.as_str()];
+    let reply = send(&server, request(&server, "POST", "/api/pause", &headers, ""));
+    assert_eq!(reply.status, 200);
+
+    let raw = format!(
+        "GET /api/status HTTP/1.1\r\nHost: localhost:{}\r\nContent-Length: 0\r\n\r\n",
+        port
+    );
+    let reply = send(&server, raw);
+    assert_eq!(reply.status, 200);
+}
+
+#[test]
+fn requires_token_when_configured() {
+    let server = start(Some("secret"));
+
+    let reply = send(&server, request(&server, "GET", "/", &[], ""));
+    assert_eq!(reply.status, 200);
+
+    let reply = send(&server, post_json(&server, "/api/pause", ""));
+    assert_eq!(reply.status, 401);
+    assert!(reply.commands.is_empty());
+
+    let headers = ["Content-Type: application/json", "Authorization: Bearer wrong"];
+    let reply = send(&server, request(&server, "POST", "/api/pause", &headers, ""));
+    assert_eq!(reply.status, 401);
+
+    let headers = ["Content-Type: application/json", "Authorization: Bearer secret"];
+    let reply = send(&server, request(&server, "POST", "/api/pause", &headers, ""));
+    assert_eq!(reply.status, 200);
+    assert_eq!(reply.commands, [IpcCommand::Pause]);
+}